@@ -1,12 +1,127 @@
 use pyo3::prelude::*;
 
+/// Raises a `ValueError` describing what is wrong with a scene that can not be rendered.
+#[pyfunction]
+fn check_scene(scene_ron: String) -> PyResult<()> {
+    raytrace_rs::check_scene(&scene_ron).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
 #[pyfunction]
 fn create_scene(scene_ron: String) -> Vec<Vec<Vec<u8>>> {
     raytrace_rs::create_image(scene_ron)
 }
 
+/// Renders the scene to rows of linear RGB floats, before the output transform.
+#[pyfunction]
+fn create_scene_hdr(scene_ron: String) -> Vec<Vec<[f32; 3]>> {
+    raytrace_rs::create_image_hdr(scene_ron)
+}
+
+/// Renders the scene progressively, calling `callback(image, samples)` after every pass of
+/// `samples_per_pass` samples. Returning `False` from the callback stops rendering early.
+#[pyfunction]
+fn create_scene_progressive(
+    py: Python<'_>,
+    scene_ron: String,
+    samples_per_pass: usize,
+    callback: PyObject,
+) -> PyResult<Vec<Vec<Vec<u8>>>> {
+    let mut error = None;
+    let image =
+        raytrace_rs::create_image_progressive(scene_ron, samples_per_pass, |image, samples| {
+            match callback
+                .call1(py, (image.to_vec(), samples))
+                .and_then(|result| result.extract::<Option<bool>>(py))
+            {
+                Ok(keep_going) => keep_going.unwrap_or(true),
+                Err(e) => {
+                    error = Some(e);
+                    false
+                }
+            }
+        });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(image),
+    }
+}
+
+/// Continues the render saved in the checkpoint at `checkpoint_path` to the scene's samples.
+#[pyfunction]
+fn resume_scene(scene_ron: String, checkpoint_path: String) -> Vec<Vec<Vec<u8>>> {
+    raytrace_rs::resume_image(scene_ron, &checkpoint_path)
+}
+
+/// Combines checkpoints rendered with different seeds, optionally saving the result to `output`.
+#[pyfunction]
+fn merge_checkpoints(
+    paths: Vec<String>,
+    output_ron: String,
+    output: Option<String>,
+) -> Vec<Vec<Vec<u8>>> {
+    raytrace_rs::merge_checkpoints(&paths, output.as_deref(), &output_ron)
+}
+
+/// Splits the render between `workers` processes started with `command`.
+#[pyfunction]
+fn create_scene_distributed(
+    scene_ron: String,
+    workers: usize,
+    command: Vec<String>,
+) -> Vec<Vec<Vec<u8>>> {
+    raytrace_rs::create_image_distributed(scene_ron, workers, &command)
+}
+
+/// Combines the partial renders saved by every worker of a scene into the final image.
+#[pyfunction]
+fn assemble_partials(paths: Vec<String>, output_ron: String) -> Vec<Vec<Vec<u8>>> {
+    raytrace_rs::assemble_partials(&paths, &output_ron)
+}
+
+/// Renders the albedo, normal and depth buffers that guide the denoiser.
+#[pyfunction]
+fn create_features(scene_ron: String) -> raytrace_rs::FeatureBuffers {
+    raytrace_rs::create_features(scene_ron)
+}
+
+/// Denoises a rendered image guided by the buffers from `create_features`.
+#[pyfunction]
+fn denoise_image(
+    image: Vec<Vec<Vec<u8>>>,
+    albedo: Vec<Vec<[f32; 3]>>,
+    normal: Vec<Vec<[f32; 3]>>,
+    depth: Vec<Vec<f32>>,
+    denoise_ron: String,
+    output_ron: String,
+) -> Vec<Vec<Vec<u8>>> {
+    raytrace_rs::denoise_image(&image, &albedo, &normal, &depth, &denoise_ron, &output_ron)
+}
+
+/// Renders frames `start_frame` to `end_frame` of an animated scene to numbered images at
+/// `output`, returning their paths.
+#[pyfunction]
+fn create_sequence(
+    scene_ron: String,
+    start_frame: i32,
+    end_frame: i32,
+    output: String,
+) -> PyResult<Vec<String>> {
+    raytrace_rs::create_sequence(scene_ron, start_frame, end_frame, &output)
+        .map_err(pyo3::exceptions::PyIOError::new_err)
+}
+
 #[pymodule]
 fn pyrays_rs(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(check_scene, m)?)?;
     m.add_function(wrap_pyfunction!(create_scene, m)?)?;
+    m.add_function(wrap_pyfunction!(create_scene_hdr, m)?)?;
+    m.add_function(wrap_pyfunction!(create_scene_progressive, m)?)?;
+    m.add_function(wrap_pyfunction!(resume_scene, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_checkpoints, m)?)?;
+    m.add_function(wrap_pyfunction!(create_scene_distributed, m)?)?;
+    m.add_function(wrap_pyfunction!(assemble_partials, m)?)?;
+    m.add_function(wrap_pyfunction!(create_features, m)?)?;
+    m.add_function(wrap_pyfunction!(denoise_image, m)?)?;
+    m.add_function(wrap_pyfunction!(create_sequence, m)?)?;
     Ok(())
 }
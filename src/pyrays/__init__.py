@@ -1,3 +1,6 @@
 from .rayobject import *
 from .material import *
+from .texture import *
+from .light import *
+from .background import *
 from .scene import *
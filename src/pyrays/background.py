@@ -0,0 +1,101 @@
+"""Background class wrapper.
+
+Controls what rays that miss every object see, which also lights the scene.
+Currently support Solid, Gradient, HDR Image, and physically based Sky backgrounds.
+"""
+
+from .util import is_vec3, typed_scaler
+
+
+class Background():
+    """Base background object for all `raytrace-rs` backgrounds."""
+
+    ron_string = ''
+
+    def _to_ron(self):
+        return self.ron_string
+
+
+class SolidBackground(Background):
+    """A single colour in every direction."""
+
+    def __init__(self, colour):
+        self.colour = is_vec3(colour, 'SolidBackground colour property')
+
+    def _to_ron(self):
+        return f'Solid(colour: {self.colour})'
+
+
+class GradientBackground(Background):
+    """A vertical blend from the `bottom` colour straight down to the `top` colour straight up."""
+
+    def __init__(self, bottom, top):
+        self.bottom = is_vec3(bottom, 'GradientBackground bottom property')
+        self.top = is_vec3(top, 'GradientBackground top property')
+
+    def _to_ron(self):
+        return f'Gradient(bottom: {self.bottom}, top: {self.top})'
+
+
+class ImageBackground(Background):
+    """An equirectangular Radiance `.hdr` or `.pfm` environment map."""
+
+    def __init__(self, path, intensity=1.0, rotation=0.0):
+        if not isinstance(path, str):
+            raise TypeError(f'Expected a file path for the ImageBackground got {type(path)}.')
+        self.path = path
+        self.intensity = typed_scaler(intensity, float, 'ImageBackground intensity property')
+        self.rotation = typed_scaler(rotation, float, 'ImageBackground rotation property')
+
+    def _to_ron(self):
+        return (f'Image(path: "{self.path}", intensity: {self.intensity}, '
+                f'rotation: {self.rotation})')
+
+
+class TimeOfDay():
+    """A place and local time used to position the sun of a `SkyBackground`.
+
+    `hour` is the local clock time in hours and `utc_offset` the hours local time is ahead of UTC.
+    """
+
+    def __init__(self, latitude, longitude, year, month, day, hour, utc_offset=0.0):
+        self.latitude = typed_scaler(latitude, float, 'TimeOfDay latitude property')
+        self.longitude = typed_scaler(longitude, float, 'TimeOfDay longitude property')
+        self.year = typed_scaler(year, int, 'TimeOfDay year property')
+        self.month = typed_scaler(month, int, 'TimeOfDay month property')
+        self.day = typed_scaler(day, int, 'TimeOfDay day property')
+        self.hour = typed_scaler(hour, float, 'TimeOfDay hour property')
+        self.utc_offset = typed_scaler(utc_offset, float, 'TimeOfDay utc offset property')
+
+    def _to_ron(self):
+        return (f'(latitude: {self.latitude}, longitude: {self.longitude}, year: {self.year}, '
+                f'month: {self.month}, day: {self.day}, hour: {self.hour}, '
+                f'utc_offset: {self.utc_offset})')
+
+
+class SkyBackground(Background):
+    """A Preetham daylight sky with an optional sun casting soft shadows.
+
+    The sun is placed by `sun_elevation` and `sun_azimuth` in degrees, azimuth measured clockwise
+    from +z towards +x, or by a `TimeOfDay` which overrides them.
+    """
+
+    def __init__(self, sun_elevation=45.0, sun_azimuth=0.0, turbidity=3.0, intensity=1.0,
+                 sun=True, sun_angular_diameter=0.53, time_of_day=None):
+        self.sun_elevation = typed_scaler(sun_elevation, float, 'SkyBackground sun elevation')
+        self.sun_azimuth = typed_scaler(sun_azimuth, float, 'SkyBackground sun azimuth')
+        self.turbidity = typed_scaler(turbidity, float, 'SkyBackground turbidity property')
+        self.intensity = typed_scaler(intensity, float, 'SkyBackground intensity property')
+        self.sun = bool(sun)
+        self.sun_angular_diameter = typed_scaler(
+            sun_angular_diameter, float, 'SkyBackground sun angular diameter')
+        if time_of_day is not None and not isinstance(time_of_day, TimeOfDay):
+            raise TypeError(f'Expected a TimeOfDay for the SkyBackground got {type(time_of_day)}.')
+        self.time_of_day = time_of_day
+
+    def _to_ron(self):
+        time_of_day = 'None' if self.time_of_day is None else f'Some({self.time_of_day._to_ron()})'
+        return (f'Sky(sun_elevation: {self.sun_elevation}, sun_azimuth: {self.sun_azimuth}, '
+                f'turbidity: {self.turbidity}, intensity: {self.intensity}, '
+                f'sun: {"true" if self.sun else "false"}, '
+                f'sun_angular_diameter: {self.sun_angular_diameter}, time_of_day: {time_of_day})')
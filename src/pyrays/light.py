@@ -0,0 +1,63 @@
+"""Light class wrapper.
+
+Provides typed light sources for a scene.
+Currently support Point, Directional, and Spot lights.
+"""
+
+from .util import is_vec3, typed_scaler
+
+
+class Light():
+    """Base light object for all `raytrace-rs` lights."""
+
+    def _colour_ron(self):
+        return f'colour: {self.colour}, intensity: {self.intensity}'
+
+
+class PointLight(Light):
+    """Light emitted equally in every direction from a point, dimming with distance squared."""
+
+    def __init__(self, position, colour=(1.0, 1.0, 1.0), intensity=1.0, falloff=True):
+        self.position = is_vec3(position, 'PointLight position property')
+        self.colour = is_vec3(colour, 'PointLight colour property')
+        self.intensity = typed_scaler(intensity, float, 'PointLight intensity property')
+        self.falloff = bool(falloff)
+
+    def _to_ron(self):
+        return (f'Point(position: {self.position}, {self._colour_ron()}, '
+                f'falloff: {"true" if self.falloff else "false"})')
+
+
+class DirectionalLight(Light):
+    """Light from infinitely far away travelling along `direction`, such as the sun.
+
+    A non zero `angular_diameter` in degrees gives the light a disc and soft shadows.
+    """
+
+    def __init__(self, direction, colour=(1.0, 1.0, 1.0), intensity=1.0, angular_diameter=0.0):
+        self.direction = is_vec3(direction, 'DirectionalLight direction property')
+        self.colour = is_vec3(colour, 'DirectionalLight colour property')
+        self.intensity = typed_scaler(intensity, float, 'DirectionalLight intensity property')
+        self.angular_diameter = typed_scaler(
+            angular_diameter, float, 'DirectionalLight angular diameter property')
+
+    def _to_ron(self):
+        return (f'Directional(direction: {self.direction}, {self._colour_ron()}, '
+                f'angular_diameter: {self.angular_diameter})')
+
+
+class SpotLight(Light):
+    """Point light limited to a cone of `cone_angle` degrees, fading over `soft_edge` degrees."""
+
+    def __init__(self, position, direction, cone_angle, soft_edge=0.0, colour=(1.0, 1.0, 1.0),
+                 intensity=1.0):
+        self.position = is_vec3(position, 'SpotLight position property')
+        self.direction = is_vec3(direction, 'SpotLight direction property')
+        self.cone_angle = typed_scaler(cone_angle, float, 'SpotLight cone angle property')
+        self.soft_edge = typed_scaler(soft_edge, float, 'SpotLight soft edge property')
+        self.colour = is_vec3(colour, 'SpotLight colour property')
+        self.intensity = typed_scaler(intensity, float, 'SpotLight intensity property')
+
+    def _to_ron(self):
+        return (f'Spot(position: {self.position}, direction: {self.direction}, cone_angle: '
+                f'{self.cone_angle}, soft_edge: {self.soft_edge}, {self._colour_ron()})')
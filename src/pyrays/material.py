@@ -1,11 +1,12 @@
 """Material class wrapper.
 
 Provides methods that affect how the light reacts to a collision.
-Currently support Metal, Diffuse, Mirror, and Dielectric materials.
+Currently support Metal, Diffuse, Mirror, Dielectric, and Emissive materials.
 """
 
 from random import random
 
+from .texture import Texture
 from .util import is_vec3, typed_scaler
 
 
@@ -13,26 +14,37 @@ class Material():
     """Base material object for all `raytrace-rs` materials."""
 
     ron_string = ''
+    texture = None
 
     def _to_ron(self):
         return self.ron_string
 
+    def _texture_to_ron(self):
+        return '' if self.texture is None else f', texture: Some({self.texture._to_ron()})'
+
+
+def _colour_or_texture(colour, err_str):
+    """The colour of a material, which a pyrays Texture replaces with white."""
+    if isinstance(colour, Texture):
+        return [1.0, 1.0, 1.0], colour
+    return is_vec3(colour, err_str), None
+
 
 class Diffuse(Material):
-    """Wrapper for the `raytrace-rs` lambertian type."""
+    """Wrapper for the `raytrace-rs` lambertian type, coloured by a colour or a pyrays Texture."""
 
     def __init__(self, colour):
-        self.colour = is_vec3(colour, 'Diffuse colour property')
+        self.colour, self.texture = _colour_or_texture(colour, 'Diffuse colour property')
 
     def _to_ron(self):
         return f'["Lambertian", "{self.colour[0]}", "{self.colour[1]}", "{self.colour[2]}"]'
 
 
 class Metal(Material):
-    """Wrapper for the `raytrace-rs` metal type."""
+    """Wrapper for the `raytrace-rs` metal type, coloured by a colour or a pyrays Texture."""
 
     def __init__(self, colour, fuzz):
-        self.colour = is_vec3(colour, 'Metal colour property')
+        self.colour, self.texture = _colour_or_texture(colour, 'Metal colour property')
         self.fuzz = typed_scaler(fuzz, float, 'creation of Metal Fuzz property')
 
     def _to_ron(self):
@@ -60,6 +72,18 @@ class Dielectric(Material):
         return f'["Dielectric", "{self.refractive_index}"]'
 
 
+class Emissive(Material):
+    """Wrapper for the `raytrace-rs` emissive type, which turns the object into a light source."""
+
+    def __init__(self, colour, strength):
+        self.colour = is_vec3(colour, 'Emissive colour property')
+        self.strength = typed_scaler(strength, float, 'creation of Emissive strength property')
+
+    def _to_ron(self):
+        return (f'["Emissive", "{self.colour[0]}", "{self.colour[1]}", "{self.colour[2]}", '
+                f'"{self.strength}"]')
+
+
 class HeightMap(Material):
     """HeightMap material to produce different material objects bashed on a height map."""
 
@@ -19,25 +19,153 @@ class RayObject():
     #     return self.ron_str
 
 
+class Transform():
+    """A translation, rotation of degrees about the x, y and z axes in turn and uniform scale."""
+
+    def __init__(self, translation=(0.0, 0.0, 0.0), rotation=(0.0, 0.0, 0.0), scale=1.0):
+        self.translation = is_vec3(translation, 'Transform translation property')
+        self.rotation = is_vec3(rotation, 'Transform rotation property')
+        self.scale = typed_scaler(scale, float, 'Transform scale property')
+
+    def _to_ron(self):
+        return (f'(translation: {self.translation}, rotation: {self.rotation}, '
+                f'scale: {self.scale})')
+
+
+class Motion():
+    """Moves an object from `start` at time 0 to `end` at time 1 of the shutter.
+
+    Both transforms are applied to the object as it was created, scaling and rotating it about
+    `pivot` before translating it.
+    """
+
+    def __init__(self, end, start=None, pivot=(0.0, 0.0, 0.0)):
+        start = Transform() if start is None else start
+        if not isinstance(start, Transform) or not isinstance(end, Transform):
+            raise TypeError('Expected pyrays Transforms for the start and end of the Motion.')
+        self.start = start
+        self.end = end
+        self.pivot = is_vec3(pivot, 'Motion pivot property')
+
+    def _to_ron(self):
+        return (f'Some((pivot: {self.pivot}, start: {self.start._to_ron()}, '
+                f'end: {self.end._to_ron()}))')
+
+
+INTERPOLATIONS = ('Linear', 'Smooth', 'Step')
+
+
+class Keyframes():
+    """Keyframes of an object's transform, applied like a `Motion` about `pivot`.
+
+    Values are interpolated between keys with `interpolation`, one of `Linear`, `Smooth` or
+    `Step`, and held before the first key and after the last. Rotations are interpolated per
+    axis, so keys can turn the object more than once between them.
+    """
+
+    def __init__(self, pivot=(0.0, 0.0, 0.0), interpolation='Linear'):
+        if interpolation not in INTERPOLATIONS:
+            raise TypeError(
+                f'Expected one of {INTERPOLATIONS} for the interpolation got {interpolation}.')
+        self.pivot = is_vec3(pivot, 'Keyframes pivot property')
+        self.interpolation = interpolation
+        self.translation = []
+        self.rotation = []
+        self.scale = []
+
+    def add(self, frame, *, translation=None, rotation=None, scale=None):
+        """Key any of the translation, rotation and scale at `frame`."""
+        frame = typed_scaler(frame, float, 'keyframe frame')
+        if translation is not None:
+            self.translation.append((frame, is_vec3(translation, 'keyframe translation')))
+        if rotation is not None:
+            self.rotation.append((frame, is_vec3(rotation, 'keyframe rotation')))
+        if scale is not None:
+            self.scale.append((frame, typed_scaler(scale, float, 'keyframe scale')))
+        return self
+
+    def _to_ron(self):
+        return (f'Some((pivot: {self.pivot}, interpolation: {self.interpolation}, translation: '
+                f'{_keys_to_ron(self.translation)}, rotation: {_keys_to_ron(self.rotation)}, '
+                f'scale: {_keys_to_ron(self.scale)}))')
+
+
+def _keys_to_ron(keys):
+    return '[' + ', '.join(f'({frame}, {value})' for frame, value in keys) + ']'
+
+
+def _motion_to_ron(motion, keyframes):
+    res = '' if motion is None else f', motion: {motion._to_ron()}'
+    if keyframes is not None:
+        res += f', keyframes: {keyframes._to_ron()}'
+    return res
+
+
+def _name_to_ron(name):
+    return '' if name is None else f', name: Some("{name}")'
+
+
+def _typed_name(name):
+    if name is not None and not isinstance(name, str):
+        raise TypeError(f'Expected a str for the object name got {type(name)}.')
+    return name
+
+
+def _typed_motion(motion):
+    if motion is not None and not isinstance(motion, Motion):
+        raise TypeError(f'Expected a pyrays Motion for the object motion got {type(motion)}.')
+    return motion
+
+
+def _typed_keyframes(keyframes, motion):
+    if keyframes is not None and not isinstance(keyframes, Keyframes):
+        raise TypeError(
+            f'Expected pyrays Keyframes for the object keyframes got {type(keyframes)}.')
+    if keyframes is not None and motion is not None:
+        raise TypeError('An object can have a Motion or Keyframes but not both.')
+    return keyframes
+
+
 class Sphere(RayObject):
     """Wrapper for 3D sphere objects."""
 
-    def __init__(self, position, radius, material):
+    def __init__(self, position, radius, material, *, motion=None, keyframes=None, name=None):
         if not issubclass(type(material), Material):
             raise TypeError('Expected a pyrays Material for the Sphere object material property.')
         self.position = is_vec3(position, 'Sphere position property')
         self.radius = typed_scaler(radius, float, 'Sphere object radius property')
         self.material = material
+        self.motion = _typed_motion(motion)
+        self.keyframes = _typed_keyframes(keyframes, motion)
+        self.name = _typed_name(name)
 
     def _to_ron(self):
         return (f'(objtype: "Sphere", vectors: [{self.position}], scalars: [{self.radius}], '
-                f'material: {self.material._to_ron()})')
+                f'material: {self.material._to_ron()}{self.material._texture_to_ron()}'
+                f'{_motion_to_ron(self.motion, self.keyframes)}{_name_to_ron(self.name)})')
+
+
+def _typed_uvs(uvs):
+    if uvs is None:
+        return None
+    if not isinstance(uvs, (list, tuple)) or len(uvs) != 3:
+        raise TypeError('Expected a (u, v) texture coordinate for each point of the Triangle.')
+    res = []
+    for uv in uvs:
+        if not isinstance(uv, (list, tuple)) or len(uv) != 2:
+            raise TypeError(f'Expected a (u, v) texture coordinate got {uv}.')
+        res.append([typed_scaler(value, float, 'Triangle texture coordinate') for value in uv])
+    return res
 
 
 class Triangle(RayObject):
-    """Wrapper for 2D triangle objects."""
+    """Wrapper for 2D triangle objects.
 
-    def __init__(self, p1, p2, p3, material, back_face_culling, *, height=0.0):
+    The texture coordinates `uvs` of the three points default to (0, 0), (1, 0) and (0, 1).
+    """
+
+    def __init__(self, p1, p2, p3, material, back_face_culling, *, height=0.0, motion=None,
+                 keyframes=None, name=None, uvs=None):
         if back_face_culling:
             self.cull = 1.0
         else:
@@ -49,22 +177,33 @@ class Triangle(RayObject):
         self.p3 = is_vec3(p3, 'Triangle point three property')
         self.height = height
         self.material = material
+        self.motion = _typed_motion(motion)
+        self.keyframes = _typed_keyframes(keyframes, motion)
+        self.name = _typed_name(name)
+        self.uvs = _typed_uvs(uvs)
 
     def _to_ron(self):
+        uvs = '' if self.uvs is None else f', uvs: Some({self.uvs})'
         if isinstance(self.material, HeightMap):
             return (f'(objtype: "Triangle", vectors: [{str(self.p1)}, {str(self.p2)}, '
                     f'{str(self.p3)}],'
-                    f'scalars: [{self.cull}], material: {self.material._to_ron(self.height)})')
+                    f'scalars: [{self.cull}], material: {self.material._to_ron(self.height)}'
+                    f'{_motion_to_ron(self.motion, self.keyframes)}{_name_to_ron(self.name)})')
         else:
             return (f'(objtype: "Triangle", vectors: [{str(self.p1)}, {str(self.p2)}, '
                     f'{str(self.p3)}],'
-                    f'scalars: [{self.cull}], material: {self.material._to_ron()})')
+                    f'scalars: [{self.cull}], material: {self.material._to_ron()}'
+                    f'{self.material._texture_to_ron()}{uvs}'
+                    f'{_motion_to_ron(self.motion, self.keyframes)}{_name_to_ron(self.name)})')
 
 
 class Square(RayObject):
-    """Wrapper for 2D triangle objects."""
+    """Wrapper for 2D triangle objects.
+
+    Textures run from (0, 0) at `p1` through (1, 0) at `p2` to (1, 1) at `p3`.
+    """
 
-    def __init__(self, p1, p2, p3, p4, material):
+    def __init__(self, p1, p2, p3, p4, material, *, motion=None, keyframes=None, name=None):
         if not issubclass(type(material), Material):
             raise TypeError('Expected a pyrays Material for the Sphere object material property.')
         self.p1 = is_vec3(p1, 'Square point one property')
@@ -72,10 +211,17 @@ class Square(RayObject):
         self.p3 = is_vec3(p3, 'Square point three property')
         self.p4 = is_vec3(p4, 'Square point four property')
         self.material = material
+        self.motion = _typed_motion(motion)
+        self.keyframes = _typed_keyframes(keyframes, motion)
+        self.name = _typed_name(name)
 
     def _to_ron(self):
-        t1 = Triangle(self.p1, self.p2, self.p3, self.material, True)
-        t2 = Triangle(self.p1, self.p3, self.p4, self.material, True)
+        t1 = Triangle(self.p1, self.p2, self.p3, self.material, True, motion=self.motion,
+                      keyframes=self.keyframes, name=self.name,
+                      uvs=((0.0, 0.0), (1.0, 0.0), (1.0, 1.0)))
+        t2 = Triangle(self.p1, self.p3, self.p4, self.material, True, motion=self.motion,
+                      keyframes=self.keyframes, name=self.name,
+                      uvs=((0.0, 0.0), (1.0, 1.0), (0.0, 1.0)))
         return f'{t1._to_ron()}, {t2._to_ron()}'
 
 
@@ -2,13 +2,112 @@
 
 import sys
 
-from .pyrays_rs import create_scene
-from .rayobject import RayObject, ProceduralTerrain
+from .background import Background
+from .light import Light
+from .pyrays_rs import check_scene, create_scene, create_scene_distributed, create_scene_progressive
+from .pyrays_rs import create_features, create_scene_hdr, create_sequence, resume_scene
+from .pyrays_rs import denoise_image as _denoise_image
+from .pyrays_rs import assemble_partials as _assemble_partials
+from .pyrays_rs import merge_checkpoints as _merge_checkpoints
+from .rayobject import INTERPOLATIONS, RayObject, ProceduralTerrain, _keys_to_ron
 from .util import is_vec3, typed_scaler
 
 from PIL import Image
 
 
+INTEGRATORS = ('PathTracer', 'Legacy')
+SAMPLERS = ('Random', 'Stratified', 'Halton', 'Sobol')
+TILE_ORDERS = ('Scanline', 'Spiral', 'Morton')
+TONE_MAPS = ('Clamp', 'Reinhard', 'Filmic', 'Aces')
+TRANSFERS = ('Gamma2', 'Srgb')
+FILTERS = ('Box', 'Tent', 'Gaussian', 'Mitchell', 'Lanczos')
+PROJECTIONS = ('Perspective', 'Orthographic', 'Fisheye', 'Equirectangular')
+FISHEYE_MAPPINGS = ('Equidistant', 'Equisolid')
+STEREO_LAYOUTS = ('SideBySide', 'TopBottom')
+APERTURE_SHAPES = ('Circle', 'Polygon', 'Image')
+
+
+def _matrix(matrix, size, name):
+    try:
+        rows = [[float(value) for value in row] for row in matrix]
+    except (TypeError, ValueError):
+        raise TypeError(f'Expected a {size} by {size} {name} matrix of numbers.')
+    if len(rows) != size or any(len(row) != size for row in rows):
+        raise TypeError(f'Expected a {size} by {size} {name} matrix got {rows}.')
+    return rows
+
+
+def _to_pil_image(image):
+    image_height = len(image)
+    image_width = len(image[0])
+    pil_image = Image.new('RGB', (image_width, image_height))
+    for y in range(image_height):
+        for x in range(image_width):
+            pil_image.putpixel((x, y), (image[y][x][0], image[y][x][1], image[y][x][2]))
+    return pil_image
+
+
+def _output_ron(exposure, tone_map, transfer, dither):
+    if tone_map not in TONE_MAPS:
+        raise TypeError(f'Expected one of {TONE_MAPS} for the tone map got {tone_map}.')
+    if transfer not in TRANSFERS:
+        raise TypeError(f'Expected one of {TRANSFERS} for the transfer got {transfer}.')
+    return (
+        f'(exposure: {typed_scaler(exposure, float, "exposure")}, tone_map: {tone_map}, '
+        f'transfer: {transfer}, dither: {"true" if dither else "false"})'
+    )
+
+
+def merge_checkpoints(paths, output=None, *, exposure=0.0, tone_map='Clamp', transfer='Gamma2',
+                      dither=False) -> Image.Image:
+    """Combine checkpoints of the same scene rendered with different seeds into one image.
+
+    The combined checkpoint is also saved to `output` when given, so it can be merged again. The
+    output settings are those of `Scene.raytrace`.
+    """
+    if len(paths) == 0:
+        raise TypeError('Expected at least one checkpoint to merge.')
+    image = _merge_checkpoints([str(path) for path in paths],
+                               _output_ron(exposure, tone_map, transfer, dither),
+                               None if output is None else str(output))
+    return _to_pil_image(image)
+
+
+def assemble_partials(paths, *, exposure=0.0, tone_map='Clamp', transfer='Gamma2',
+                      dither=False) -> Image.Image:
+    """Combine the partial renders saved by every worker of a scene into the final image.
+
+    The output settings are those of `Scene.raytrace`.
+    """
+    if len(paths) == 0:
+        raise TypeError('Expected at least one partial render to assemble.')
+    image = _assemble_partials([str(path) for path in paths],
+                               _output_ron(exposure, tone_map, transfer, dither))
+    return _to_pil_image(image)
+
+
+def denoise_image(image, features, *, iterations=3, colour_sigma=0.5, normal_sigma=0.3,
+                  depth_sigma=0.03, transfer='Gamma2', dither=False) -> Image.Image:
+    """Denoise a rendered PIL image guided by the `features` returned alongside it by
+    `Scene.raytrace(..., features=True)`.
+
+    The filter runs for `iterations` passes, each twice as wide as the last. The sigmas set how
+    much the illumination, normals and relative depth of two pixels may differ before they stop
+    being blurred together. `transfer` and `dither` must match those the image was rendered with.
+    """
+    albedo, normal, depth = features
+    rows = [[list(image.getpixel((x, y)))[:3] for x in range(image.width)]
+            for y in range(image.height)]
+    denoise_ron = (
+        f'(iterations: {typed_scaler(iterations, int, "denoise iterations")}, colour_sigma: '
+        f'{typed_scaler(colour_sigma, float, "colour sigma")}, normal_sigma: '
+        f'{typed_scaler(normal_sigma, float, "normal sigma")}, depth_sigma: '
+        f'{typed_scaler(depth_sigma, float, "depth sigma")})'
+    )
+    output_ron = _output_ron(0.0, 'Clamp', transfer, dither)
+    return _to_pil_image(_denoise_image(rows, albedo, normal, depth, denoise_ron, output_ron))
+
+
 class Scene:
     """Base scene object to be ratraced."""
 
@@ -21,10 +120,219 @@ class Scene:
         self.v_fov = typed_scaler(v_fov, float, 'visual field of view property')
         self.lights = []
         self.objects = []
+        self.background = None
+        self.camera_end = None
+        self.interpolation = 'Linear'
+        self.camera_keys = {'camera_pos': [], 'camera_dir': [], 'camera_up': [], 'v_fov': []}
+        self.light_keys = {}
+        self.projection = None
+        self.stereo = None
+        self.physical_camera = None
+        self.aperture_shape = None
+        self.autofocus = None
+        self.look_at = None
+        self.intrinsics = None
+        self.world_to_camera = None
+        self.distortion = None
+
+    def add_light(self, light):
+        """Add a light to the scene, either a pyrays Light or the location of a white light."""
+        if issubclass(type(light), Light):
+            self.lights.append(light)
+        else:
+            self.lights.append(is_vec3(light, 'Light Location property'))
+        return self
+
+    def set_background(self, background):
+        """Set what rays that miss every object see, replacing the default sky gradient."""
+        if not issubclass(type(background), Background):
+            raise TypeError(f'Expected a pyrays Background type. Found {type(background)}')
+        self.background = background
+        return self
+
+    def set_camera_end(self, camera_pos, camera_direction, camera_up):
+        """Move the camera during the shutter interval, ending up here at time 1."""
+        self.camera_end = (
+            is_vec3(camera_pos, 'Camera end Position property'),
+            is_vec3(camera_direction, 'Camera end Direction property'),
+            is_vec3(camera_up, 'Camera end Up property'),
+        )
+        return self
+
+    def set_projection(self, projection, *, height=None, fov=180.0, mapping='Equidistant'):
+        """Set how the camera maps the image onto the scene.
+
+        `Perspective` is the default thin lens camera. `Orthographic` looks along parallel rays
+        covering a view `height` units tall, for maps and technical views. `Fisheye` fits a
+        circle covering `fov` degrees in the image, spaced by an `Equidistant` or `Equisolid`
+        `mapping`. `Equirectangular` renders a full 360 degree panorama, usually twice as wide as
+        it is tall.
+        """
+        if projection not in PROJECTIONS:
+            raise TypeError(f'Expected one of {PROJECTIONS} for the projection got {projection}.')
+        if projection == 'Orthographic':
+            if height is None:
+                raise TypeError('Expected a view height for the Orthographic projection.')
+            projection = f'Orthographic(height: {typed_scaler(height, float, "view height")})'
+        elif projection == 'Fisheye':
+            if mapping not in FISHEYE_MAPPINGS:
+                raise TypeError(
+                    f'Expected one of {FISHEYE_MAPPINGS} for the fisheye mapping got {mapping}.')
+            projection = (f'Fisheye(fov: {typed_scaler(fov, float, "fisheye field of view")}, '
+                          f'mapping: {mapping})')
+        self.projection = projection
+        return self
+
+    def set_stereo(self, eye_separation, layout='SideBySide'):
+        """Render a stereo pair of eyes `eye_separation` apart into one image for VR.
+
+        `layout` puts the left eye in the left half of the image with `SideBySide` or in the top
+        half with `TopBottom`. Perspective eyes converge on the focal distance.
+        """
+        if layout not in STEREO_LAYOUTS:
+            raise TypeError(f'Expected one of {STEREO_LAYOUTS} for the stereo layout got {layout}.')
+        self.stereo = (typed_scaler(eye_separation, float, 'eye separation'), layout)
+        return self
+
+    def set_physical_camera(self, focal_length, f_number, *, sensor=(36.0, 24.0),
+                            shutter_speed=0.01, iso=100.0, metres_per_unit=1.0, frame_rate=None):
+        """Set the field of view, depth of field and exposure from real camera settings.
+
+        The `focal_length` in millimetres and `sensor` size give the field of view, and the
+        `f_number` gives the aperture with the scene measured in `metres_per_unit`. The
+        exposure follows the `f_number`, `shutter_speed` in seconds and `iso`, with the sunny 16
+        rule leaving the render unchanged. With a `frame_rate` the shutter speed also sets the
+        motion blur.
+        """
+        if not isinstance(sensor, tuple) or len(sensor) != 2:
+            raise TypeError(f'Expected a (width, height) tuple for the sensor size got {sensor}.')
+        sensor_width = typed_scaler(sensor[0], float, 'sensor width')
+        sensor_height = typed_scaler(sensor[1], float, 'sensor height')
+        res = (f'focal_length: {typed_scaler(focal_length, float, "focal length")}, '
+               f'f_number: {typed_scaler(f_number, float, "f-number")}, '
+               f'sensor_width: {sensor_width}, sensor_height: {sensor_height}, '
+               f'shutter_speed: {typed_scaler(shutter_speed, float, "shutter speed")}, '
+               f'iso: {typed_scaler(iso, float, "ISO")}, '
+               f'metres_per_unit: {typed_scaler(metres_per_unit, float, "metres per unit")}')
+        if frame_rate is not None:
+            res += f', frame_rate: Some({typed_scaler(frame_rate, float, "frame rate")})'
+        self.physical_camera = res
+        return self
+
+    def set_aperture_shape(self, shape, *, blades=6, rotation=0.0, path=None):
+        """Set the shape out of focus highlights take on.
+
+        `Circle` is the default. `Polygon` has `blades` sides turned `rotation` degrees, like the
+        iris of a real lens. `Image` uses the bright parts of the `.hdr` or `.pfm` image at
+        `path`.
+        """
+        if shape not in APERTURE_SHAPES:
+            raise TypeError(
+                f'Expected one of {APERTURE_SHAPES} for the aperture shape got {shape}.')
+        if shape == 'Polygon':
+            blades = typed_scaler(blades, int, 'aperture blades')
+            if blades < 3:
+                raise TypeError('An aperture needs at least 3 blades.')
+            shape = (f'Polygon(blades: {blades}, '
+                     f'rotation: {typed_scaler(rotation, float, "aperture rotation")})')
+        elif shape == 'Image':
+            if not isinstance(path, str):
+                raise TypeError(f'Expected a path for the aperture image got {path}.')
+            shape = f'Image(path: "{path}")'
+        self.aperture_shape = shape
+        return self
+
+    def set_autofocus(self, target):
+        """Focus the camera on what it sees once the scene is built, replacing the focal distance.
+
+        `target` is either the `(x, y)` point of the image to focus through, from 0 at the left
+        and top to 1 at the right and bottom, or the name of the objects to focus on.
+        """
+        if isinstance(target, str):
+            self.autofocus = f'Object(name: "{target}")'
+        elif isinstance(target, tuple) and len(target) == 2:
+            x = typed_scaler(target[0], float, 'autofocus x')
+            y = typed_scaler(target[1], float, 'autofocus y')
+            self.autofocus = f'Screen(x: {x}, y: {y})'
+        else:
+            raise TypeError(
+                f'Expected an (x, y) tuple or an object name for the autofocus got {target}.')
+        return self
+
+    def set_look_at(self, name=None):
+        """Point the camera at the whole scene, or the objects called `name`, and move it along
+        its line of sight until they fill the view. Without autofocus it focuses on their middle.
+        """
+        if name is None:
+            self.look_at = 'Scene'
+        elif isinstance(name, str):
+            self.look_at = f'Object(name: "{name}")'
+        else:
+            raise TypeError(f'Expected a str for the name of the objects to look at got {name}.')
+        return self
+
+    def set_intrinsics(self, matrix):
+        """Match a calibrated camera with its 3x3 intrinsic `matrix` in pixels,
+        `[[fx, 0, cx], [0, fy, cy], [0, 0, 1]]`, replacing the field of view. The principal point
+        can be off the centre of the image.
+        """
+        self.intrinsics = _matrix(matrix, 3, 'intrinsic')
+        return self
+
+    def set_world_to_camera(self, matrix):
+        """Place the camera with a 4x4 world to camera `matrix`, with x to the right, y down and
+        z forward like OpenCV, replacing the camera position, direction and up vector.
+        """
+        self.world_to_camera = _matrix(matrix, 4, 'world to camera')
+        return self
+
+    def set_distortion(self, k1=0.0, k2=0.0, p1=0.0, p2=0.0, k3=0.0):
+        """Bend the camera's rays with the radial `k1`, `k2` and `k3` and tangential `p1` and `p2`
+        Brown-Conrady distortion coefficients of OpenCV.
+        """
+        coefficients = {name: typed_scaler(value, float, f'distortion {name}')
+                        for name, value in (('k1', k1), ('k2', k2), ('p1', p1), ('p2', p2),
+                                            ('k3', k3))}
+        self.distortion = ', '.join(f'{name}: {value}' for name, value in coefficients.items())
+        return self
+
+    def set_interpolation(self, interpolation):
+        """Set how camera and light keyframes change between keys, `Linear`, `Smooth` or `Step`."""
+        if interpolation not in INTERPOLATIONS:
+            raise TypeError(
+                f'Expected one of {INTERPOLATIONS} for the interpolation got {interpolation}.')
+        self.interpolation = interpolation
+        return self
+
+    def add_camera_keyframe(self, frame, *, camera_pos=None, camera_direction=None,
+                            camera_up=None, v_fov=None):
+        """Key any of the camera's position, target, up direction and field of view at `frame`.
+
+        Anything never keyed stays as the scene was created with.
+        """
+        frame = typed_scaler(frame, float, 'keyframe frame')
+        if camera_pos is not None:
+            self.camera_keys['camera_pos'].append(
+                (frame, is_vec3(camera_pos, 'keyframe Camera Position')))
+        if camera_direction is not None:
+            self.camera_keys['camera_dir'].append(
+                (frame, is_vec3(camera_direction, 'keyframe Camera Direction')))
+        if camera_up is not None:
+            self.camera_keys['camera_up'].append(
+                (frame, is_vec3(camera_up, 'keyframe Camera Up')))
+        if v_fov is not None:
+            self.camera_keys['v_fov'].append(
+                (frame, typed_scaler(v_fov, float, 'keyframe visual field of view')))
+        return self
 
-    def add_light(self, location):
-        """Add a light to the scene."""
-        self.lights.append(is_vec3(location, 'Light Location property'))
+    def add_light_keyframe(self, light_index, frame, position):
+        """Key the position of the `light_index`th light added to the scene at `frame`."""
+        light_index = typed_scaler(light_index, int, 'light index')
+        if light_index < 0:
+            raise TypeError(f'Expected a light index of at least 0 got {light_index}.')
+        self.light_keys.setdefault(light_index, []).append(
+            (typed_scaler(frame, float, 'keyframe frame'),
+             is_vec3(position, 'keyframe Light Location')))
         return self
 
     def add_object(self, obj):
@@ -57,7 +365,10 @@ class Scene:
                     res += ', '
         res += '], lights: ['
         for i in range(len(self.lights)):
-            res += str(self.lights[i])
+            if isinstance(self.lights[i], Light):
+                res += self.lights[i]._to_ron()
+            else:
+                res += str(self.lights[i])
             if i != len(self.lights) - 1:
                 res += ', '
         res += '],'
@@ -69,9 +380,10 @@ class Scene:
                 f'{terrain.lacunarity}, seed_value: {terrain.seed}, magnitude: {terrain.magnitude}, '
                 f'persistence: {terrain.persistence}, fuzz: {terrain.material.fuzz}, map_cutoff: '
                 f'{list(terrain.material.map.keys())}, map_value: '
-                f'{[x.colour for x in list(terrain.material.map.values())]}, erosion_factor: '
-                f'{terrain.erosion_factor}, rain_factor: {terrain.rain_factor}, drops_per_point: '
-                f'{terrain.drops_per_point})'
+                f'{[x.colour for x in list(terrain.material.map.values())]}, map_emission: '
+                f'{[getattr(x, "strength", 0.0) for x in list(terrain.material.map.values())]}, '
+                f'erosion_factor: {terrain.erosion_factor}, rain_factor: {terrain.rain_factor}, '
+                f'drops_per_point: {terrain.drops_per_point})'
             )
         else:
             res += (
@@ -80,6 +392,94 @@ class Scene:
                 'fuzz: 0.0, map_cutoff: [0.0], map_value: [[0.0, 0.0, 0.0]], erosion_factor: 0.0'
                 'rain_factor: 0.0, drops_per_point: 0)'
             )
+        res += f', integrator: {image_meta["integrator"]}'
+        res += f', seed: {image_meta["seed"]}'
+        res += f', sampler: {image_meta["sampler"]}'
+        res += f', tile_size: {image_meta["tile_size"]}, tile_order: {image_meta["tile_order"]}'
+        if image_meta['noise_threshold'] is not None:
+            sample_map = image_meta['sample_map']
+            sample_map = 'None' if sample_map is None else f'Some("{sample_map}")'
+            res += (
+                f', adaptive: Some((min_samples: {image_meta["min_samples"]}, max_samples: '
+                f'{image_meta["max_samples"]}, threshold: {image_meta["noise_threshold"]}, '
+                f'sample_map: {sample_map}))'
+            )
+        if image_meta['checkpoint'] is not None:
+            res += (
+                f', checkpoint: Some((path: "{image_meta["checkpoint"]}", interval: '
+                f'{image_meta["checkpoint_interval"]}))'
+            )
+        if image_meta['worker'] is not None:
+            index, count = image_meta['worker']
+            output = image_meta['partial_output']
+            output = 'None' if output is None else f'Some("{output}")'
+            res += f', worker: Some((index: {index}, count: {count}, output: {output}))'
+        if image_meta['crop'] is not None:
+            x, y, width, height = image_meta['crop']
+            res += (
+                f', crop: Some((x: {x}, y: {y}, width: {width}, height: {height}, normalized: '
+                f'{"true" if image_meta["crop_normalized"] else "false"}, full_frame: '
+                f'{"true" if image_meta["crop_full_frame"] else "false"}))'
+            )
+        if image_meta['filter_radius'] is None:
+            res += f', filter: (kind: {image_meta["filter"]})'
+        else:
+            res += (
+                f', filter: (kind: {image_meta["filter"]}, '
+                f'radius: Some({image_meta["filter_radius"]}))'
+            )
+        if image_meta['shutter'] is not None:
+            shutter_open, shutter_close = image_meta['shutter']
+            res += f', shutter: Some((open: {shutter_open}, close: {shutter_close}))'
+        if self.camera_end is not None:
+            camera_pos, camera_dir, camera_up = self.camera_end
+            res += (
+                f', camera_end: Some((camera_pos: {camera_pos}, camera_dir: {camera_dir}, '
+                f'camera_up: {camera_up}))'
+            )
+        res += f', frame: {image_meta["frame"]}'
+        if self.projection is not None:
+            res += f', projection: {self.projection}'
+        if self.stereo is not None:
+            eye_separation, layout = self.stereo
+            res += f', stereo: Some((eye_separation: {eye_separation}, layout: {layout}))'
+        if self.physical_camera is not None:
+            res += f', physical_camera: Some(({self.physical_camera}))'
+        if self.aperture_shape is not None:
+            res += f', aperture_shape: {self.aperture_shape}'
+        if self.autofocus is not None:
+            res += f', autofocus: Some({self.autofocus})'
+        if self.look_at is not None:
+            res += f', look_at: Some({self.look_at})'
+        if self.intrinsics is not None:
+            res += f', intrinsics: Some({self.intrinsics})'
+        if self.world_to_camera is not None:
+            res += f', world_to_camera: Some({self.world_to_camera})'
+        if self.distortion is not None:
+            res += f', distortion: Some(({self.distortion}))'
+        if image_meta['camera_output'] is not None:
+            res += f', camera_output: Some("{image_meta["camera_output"]}")'
+        if any(self.camera_keys.values()) or self.light_keys:
+            camera = ', '.join(f'{name}: {_keys_to_ron(keys)}'
+                               for name, keys in self.camera_keys.items())
+            lights = ', '.join(f'(light: {index}, position: {_keys_to_ron(keys)})'
+                               for index, keys in self.light_keys.items())
+            res += (
+                f', animation: Some((interpolation: {self.interpolation}, camera: ({camera}), '
+                f'lights: [{lights}]))'
+            )
+        if image_meta['denoise']:
+            res += ', denoise: Some(())'
+        if image_meta['hdr_output'] is not None:
+            res += (
+                f', hdr_output: Some((path: "{image_meta["hdr_output"]}", aovs: '
+                f'{"true" if image_meta["hdr_aovs"] else "false"}))'
+            )
+        output = _output_ron(image_meta['exposure'], image_meta['tone_map'],
+                             image_meta['transfer'], image_meta['dither'])
+        res += f', output: {output}'
+        if self.background is not None:
+            res += f', background: {self.background._to_ron()}'
         res += ')'
         return res
 
@@ -90,15 +490,187 @@ class Scene:
                  max_depth,
                  multithreading,
                  *,
+                 integrator='PathTracer',
+                 seed=0,
+                 sampler='Random',
+                 noise_threshold=None,
+                 min_samples=16,
+                 max_samples=1024,
+                 sample_map=None,
+                 callback=None,
+                 samples_per_pass=1,
+                 tile_size=32,
+                 tile_order='Spiral',
+                 checkpoint=None,
+                 checkpoint_interval=300.0,
+                 resume=False,
+                 workers=1,
+                 worker_command=('raytrace-worker',),
+                 worker=None,
+                 partial_output=None,
+                 crop=None,
+                 crop_full_frame=False,
+                 filter='Box',
+                 filter_radius=None,
+                 shutter=None,
+                 frame=0.0,
+                 denoise=False,
+                 features=False,
+                 exposure=0.0,
+                 tone_map='Clamp',
+                 transfer='Gamma2',
+                 dither=False,
+                 hdr_output=None,
+                 hdr_aovs=False,
+                 camera_output=None,
+                 linear=False,
+                 _sequence=None,
                  _debug=False
     ) -> Image.Image:
-        """Raytrace the scene."""
+        """Raytrace the scene.
+
+        `integrator` selects how light is gathered, either the physically based `PathTracer` or
+        the original `Legacy` shadow ray tracer.
+
+        `seed` picks the random number streams, the same scene and seed always render the same
+        image regardless of multithreading.
+
+        `sampler` picks how the samples of each pixel are spread out, `Stratified`, `Halton` and
+        `Sobol` all give less noise than `Random` for the same number of samples.
+
+        Setting `noise_threshold` switches to adaptive sampling, which ignores `samples_per_pixel`
+        and gives each pixel between `min_samples` and `max_samples` samples until the standard
+        error of its displayed brightness drops below the threshold. `sample_map` is an optional
+        PGM file path to save the number of samples each pixel took to.
+
+        Passing a `callback` renders progressively, `samples_per_pass` samples per pixel at a
+        time, calling `callback(image, samples)` with a PIL image of the render so far after every
+        pass. Returning `False` from the callback stops rendering early and returns that image.
+
+        The image is split into `tile_size` pixel square tiles which are handed out to threads in
+        `Scanline`, `Spiral` (centre outwards) or `Morton` order.
+
+        Setting `checkpoint` to a file path saves the render so far there every
+        `checkpoint_interval` seconds and once it finishes. With `resume` the render carries on
+        from that checkpoint instead of starting over, the scene and seed must be unchanged but
+        `samples_per_pixel` can be raised to refine a finished render. Checkpoints rendered with
+        different seeds can be combined with `merge_checkpoints`.
+
+        With more than one of `workers` the tiles are split between that many processes started
+        with `worker_command`, which can be prefixed with e.g. `ssh` to use other machines. To
+        render on machines by hand instead, give each an `(index, count)` `worker` so it only
+        renders its share of the tiles and saves them to `partial_output`, then combine the files
+        with `assemble_partials`.
+
+        `crop` renders only an `(x, y, width, height)` region of the image, given in pixels when
+        every value is an int and as fractions of the image size otherwise. Just the region is
+        returned unless `crop_full_frame` is set, which returns the whole image with the rest
+        left black.
+
+        Each sample is weighted by the reconstruction `filter`, one of `Box`, `Tent`, `Gaussian`,
+        `Mitchell` or `Lanczos`, and added to every pixel within `filter_radius` pixels of it. The
+        default `Box` only adds samples to their own pixel, the others trade a little sharpness
+        for less aliasing. Each filter has its own default radius.
+
+        `shutter` is an `(open, close)` interval to spread each pixel's samples over, blurring
+        objects created with a `Motion` and a camera moved with `set_camera_end`. Moving things
+        are at the start of their motion at time 0 and the end at time 1 of the shutter of every
+        frame. Without a shutter everything is rendered at time 0.
+
+        `frame` is the frame of the scene's keyframes to render, frame `n` being at time `n`
+        so a shutter blurs over the time after the frame. `render_sequence` renders a range of
+        frames at once.
+
+        `denoise` runs the built in denoiser over the finished image, guided by the albedo,
+        normals and depth of what each pixel sees. With `features` the render returns an
+        `(image, features)` pair instead, where `features` holds those albedo, normal and depth
+        buffers so the image can be denoised separately with `denoise_image`.
+
+        The finished radiance is scaled by `2 ** exposure` stops, brought into range by the
+        `tone_map`, one of `Clamp`, `Reinhard`, `Filmic` or `Aces`, and encoded with the
+        `transfer` curve, either the original `Gamma2` or `Srgb`. `dither` adds a little noise
+        before rounding to 8 bits to break up banding in smooth gradients.
+
+        `hdr_output` saves the linear radiance of the render, before any of that, to an `.exr`,
+        `.pfm` or `.hdr` file. With `hdr_aovs` an `.exr` also gets `albedo`, `normal` and `depth`
+        layers. Setting `linear` returns the linear radiance as rows of `[r, g, b]` floats instead
+        of a PIL image.
+
+        `camera_output` saves the intrinsic and world to camera matrices, distortion, focal
+        distance and aperture the image was rendered with to a JSON file, matching the cropped
+        image when there is a crop. Sequences number it like their frames.
+        """
+        if callback is not None and not callable(callback):
+            raise TypeError(f'Expected a callable for the callback got {type(callback)}.')
+        if integrator not in INTEGRATORS:
+            raise TypeError(f'Expected one of {INTEGRATORS} for the integrator got {integrator}.')
+        if sampler not in SAMPLERS:
+            raise TypeError(f'Expected one of {SAMPLERS} for the sampler got {sampler}.')
+        if tile_order not in TILE_ORDERS:
+            raise TypeError(f'Expected one of {TILE_ORDERS} for the tile order got {tile_order}.')
+        if filter not in FILTERS:
+            raise TypeError(f'Expected one of {FILTERS} for the filter got {filter}.')
+        if tone_map not in TONE_MAPS:
+            raise TypeError(f'Expected one of {TONE_MAPS} for the tone map got {tone_map}.')
+        if transfer not in TRANSFERS:
+            raise TypeError(f'Expected one of {TRANSFERS} for the transfer got {transfer}.')
+        if linear and (callback is not None or resume or workers != 1):
+            raise TypeError('Linear output is not supported with a callback, resume or workers.')
+        if checkpoint is None and resume:
+            raise TypeError('Expected a checkpoint path to resume from.')
+        if checkpoint is not None and (callback is not None or noise_threshold is not None):
+            raise TypeError('Checkpoints are not supported with a callback or noise threshold.')
+        if worker is not None and (len(worker) != 2 or not 0 <= worker[0] < worker[1]):
+            raise TypeError(f'Expected an (index, count) pair for the worker got {worker}.')
+        if worker is not None and (checkpoint is not None or noise_threshold is not None):
+            raise TypeError('Workers are not supported with a checkpoint or noise threshold.')
+        if _sequence is not None and (callback is not None or resume or workers != 1
+                                      or worker is not None or linear or features):
+            raise TypeError('Sequences are not supported with a callback, resume, workers, '
+                            'linear output or features.')
+        if shutter is not None and len(shutter) != 2:
+            raise TypeError(f'Expected an (open, close) shutter interval got {shutter}.')
+        if crop is not None and len(crop) != 4:
+            raise TypeError(f'Expected an (x, y, width, height) crop got {crop}.')
         image_meta = {
             'image_width': typed_scaler(image_width, int, 'image width'),
             'image_height': typed_scaler(image_height, int, 'image height'),
             'samples_per_pixel': typed_scaler(samples_per_pixel, int, 'samples per pixel'),
             'max_depth': typed_scaler(max_depth, int, 'max ray depth'),
-            'multithreading': multithreading
+            'multithreading': multithreading,
+            'integrator': integrator,
+            'seed': typed_scaler(seed, int, 'seed'),
+            'sampler': sampler,
+            'noise_threshold': (None if noise_threshold is None
+                                else typed_scaler(noise_threshold, float, 'noise threshold')),
+            'min_samples': typed_scaler(min_samples, int, 'min samples'),
+            'max_samples': typed_scaler(max_samples, int, 'max samples'),
+            'sample_map': sample_map,
+            'tile_size': typed_scaler(tile_size, int, 'tile size'),
+            'tile_order': tile_order,
+            'checkpoint': None if checkpoint is None else str(checkpoint),
+            'checkpoint_interval': typed_scaler(checkpoint_interval, float, 'checkpoint interval'),
+            'worker': (None if worker is None
+                       else tuple(typed_scaler(x, int, 'worker') for x in worker)),
+            'partial_output': None if partial_output is None else str(partial_output),
+            'crop': (None if crop is None
+                     else tuple(typed_scaler(x, float, 'crop') for x in crop)),
+            'crop_normalized': crop is not None and not all(isinstance(x, int) for x in crop),
+            'crop_full_frame': crop_full_frame,
+            'filter': filter,
+            'shutter': (None if shutter is None
+                        else tuple(typed_scaler(x, float, 'shutter') for x in shutter)),
+            'frame': typed_scaler(frame, float, 'frame'),
+            'filter_radius': (None if filter_radius is None
+                              else typed_scaler(filter_radius, float, 'filter radius')),
+            'denoise': denoise,
+            'exposure': typed_scaler(exposure, float, 'exposure'),
+            'tone_map': tone_map,
+            'transfer': transfer,
+            'dither': dither,
+            'hdr_output': None if hdr_output is None else str(hdr_output),
+            'hdr_aovs': hdr_aovs,
+            'camera_output': None if camera_output is None else str(camera_output),
         }
         pil_image = Image.new('RGB', (image_width, image_height))
         print('Creating raytracer scene config.', file=sys.stderr)
@@ -108,11 +680,54 @@ class Scene:
             print(ron_str)
             return pil_image
 
+        check_scene(ron_str)
         print('Loading Scene Data.', file=sys.stderr)
-        image = create_scene(ron_str)
+        workers = typed_scaler(workers, int, 'workers')
+        if _sequence is not None:
+            output, start_frame, end_frame = _sequence
+            return create_sequence(ron_str, start_frame, end_frame, output)
+        if linear:
+            image = create_scene_hdr(ron_str)
+            return (image, create_features(ron_str)) if features else image
+        if resume:
+            image = resume_scene(ron_str, image_meta['checkpoint'])
+        elif workers > 1:
+            image = create_scene_distributed(ron_str, workers, [str(x) for x in worker_command])
+        elif callback is None:
+            image = create_scene(ron_str)
+        else:
+            image = create_scene_progressive(
+                ron_str,
+                typed_scaler(samples_per_pass, int, 'samples per pass'),
+                lambda image, samples: callback(_to_pil_image(image), samples),
+            )
+
+        if features:
+            return _to_pil_image(image), create_features(ron_str)
+        return _to_pil_image(image)
 
-        for y in range(image_height):
-            for x in range(image_width):
-                pil_image.putpixel((x, y), (image[y][x][0], image[y][x][1], image[y][x][2]))
+    def render_sequence(self,
+                        output,
+                        start_frame,
+                        end_frame,
+                        image_width,
+                        image_height,
+                        samples_per_pixel,
+                        max_depth,
+                        multithreading,
+                        **kwargs):
+        """Render frames `start_frame` to `end_frame` of the scene's keyframes to numbered images.
 
-        return pil_image
+        The scene is built once and reused for every frame. Each frame is saved to `output` with
+        its last run of `#` replaced by the frame number, e.g. `frame_####.png`, or with the
+        frame number added before the extension when it has no `#`. Outputs ending in `.exr`,
+        `.pfm` or `.hdr` save the linear render. Any other keyword arguments are passed on to
+        `raytrace`. Returns the paths of the saved frames.
+        """
+        start_frame = typed_scaler(start_frame, int, 'start frame')
+        end_frame = typed_scaler(end_frame, int, 'end frame')
+        if end_frame < start_frame:
+            raise TypeError(f'Expected the end frame {end_frame} to not be before the start frame.')
+        return self.raytrace(image_width, image_height, samples_per_pixel, max_depth,
+                             multithreading, _sequence=(str(output), start_frame, end_frame),
+                             **kwargs)
@@ -0,0 +1,81 @@
+"""Texture class wrapper.
+
+Colours that vary over the surface of an object, used in place of the colour of a Diffuse or
+Metal material. Currently support Constant, Checker, PNG and JPEG Image, and Noise textures.
+"""
+
+from .util import is_vec3, typed_scaler
+
+
+class Texture():
+    """Base texture object for all `raytrace-rs` textures."""
+
+    ron_string = ''
+
+    def _to_ron(self):
+        return self.ron_string
+
+
+class ConstantTexture(Texture):
+    """A single colour over the whole surface."""
+
+    def __init__(self, colour):
+        self.colour = is_vec3(colour, 'ConstantTexture colour property')
+
+    def _to_ron(self):
+        return f'Constant(colour: {self.colour})'
+
+
+class CheckerTexture(Texture):
+    """Alternating `even` and `odd` squares, `scale` of them along each texture coordinate."""
+
+    def __init__(self, even, odd, scale=8.0):
+        self.even = is_vec3(even, 'CheckerTexture even property')
+        self.odd = is_vec3(odd, 'CheckerTexture odd property')
+        self.scale = typed_scaler(scale, float, 'CheckerTexture scale property')
+
+    def _to_ron(self):
+        return f'Checker(even: {self.even}, odd: {self.odd}, scale: {self.scale})'
+
+
+class ImageTexture(Texture):
+    """A PNG or JPEG image wrapped once over the texture coordinates."""
+
+    def __init__(self, path):
+        if not isinstance(path, str):
+            raise TypeError(f'Expected a file path for the ImageTexture got {type(path)}.')
+        if not path.lower().endswith(('.png', '.jpg', '.jpeg')):
+            raise TypeError(f'Expected a PNG or JPEG file for the ImageTexture got {path}.')
+        self.path = path
+
+    def _to_ron(self):
+        return f'Image(path: "{self.path}")'
+
+
+NOISE_PATTERNS = ('Turbulence', 'Marble', 'Wood')
+
+
+class NoiseTexture(Texture):
+    """A solid texture of Perlin noise blending from the first of `colours` to the second.
+
+    `pattern` is one of `Turbulence`, `Marble` for veins running across the x axis or `Wood` for
+    rings around the y axis, and the noise is `scale` times finer than the scene's units.
+    """
+
+    def __init__(self, pattern, colours=((1.0, 1.0, 1.0), (0.0, 0.0, 0.0)), scale=1.0,
+                 octaves=7, seed=0):
+        if pattern not in NOISE_PATTERNS:
+            raise TypeError(f'Expected one of {NOISE_PATTERNS} for the pattern got {pattern}.')
+        if not isinstance(colours, (list, tuple)) or len(colours) != 2:
+            raise TypeError('Expected two colours for the NoiseTexture to blend between.')
+        self.pattern = pattern
+        self.colours = [is_vec3(colour, 'NoiseTexture colours property') for colour in colours]
+        self.scale = typed_scaler(scale, float, 'NoiseTexture scale property')
+        self.octaves = typed_scaler(octaves, int, 'NoiseTexture octaves property')
+        if self.octaves < 1:
+            raise TypeError('NoiseTexture needs at least one octave.')
+        self.seed = typed_scaler(seed, int, 'NoiseTexture seed property')
+
+    def _to_ron(self):
+        return (f'Noise(pattern: {self.pattern}, colours: {self.colours}, scale: {self.scale}, '
+                f'octaves: {self.octaves}, seed: {self.seed})')
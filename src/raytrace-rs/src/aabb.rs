@@ -61,6 +61,11 @@ impl AABB {
         true
     }
 
+    /// The smallest box holding both boxes.
+    pub fn union(&self, other: &AABB) -> AABB {
+        AABB::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
     pub fn inside(&self, obj: HittableObject) -> bool {
         match obj {
             HittableObject::SphereObj(s) => s.get_aabb().overlaps(self),
@@ -0,0 +1,227 @@
+use crate::camera::Camera;
+use crate::configuration::{RaytracerScene, RonAdaptive};
+use crate::environment::luminance;
+use crate::framebuffer::{Framebuffer, Tile};
+use crate::hittables::Hittables;
+use crate::{crop_window, trace_sample};
+use glam::Vec3A;
+use indicatif::ProgressBar;
+use std::fs;
+use std::ops::AddAssign;
+
+/// The filtered colour splatted into a pixel along with the brightness statistics of the samples
+/// traced from inside it, which decide when it has converged.
+#[derive(Copy, Clone, Default)]
+struct PixelStats {
+    sum: Vec3A,
+    weight: f32,
+    brightness_sum: f32,
+    brightness_squared_sum: f32,
+    samples: usize,
+    converged: bool,
+}
+
+impl AddAssign for PixelStats {
+    fn add_assign(&mut self, other: PixelStats) {
+        self.sum += other.sum;
+        self.weight += other.weight;
+        self.brightness_sum += other.brightness_sum;
+        self.brightness_squared_sum += other.brightness_squared_sum;
+        self.samples += other.samples;
+        self.converged |= other.converged;
+    }
+}
+
+impl PixelStats {
+    fn splat(&mut self, colour: Vec3A, weight: f32) {
+        self.sum += colour * weight;
+        self.weight += weight;
+    }
+
+    fn add(&mut self, colour: Vec3A) {
+        // Brightness is measured after a sqrt gamma like the default one `to_rgb` applies so the
+        // threshold means the same thing in dark and bright parts of the image.
+        let brightness = luminance(colour).max(0.0).sqrt();
+        self.brightness_sum += brightness;
+        self.brightness_squared_sum += brightness * brightness;
+        self.samples += 1;
+    }
+
+    fn standard_error(&self) -> f32 {
+        let n = self.samples as f32;
+        let mean = self.brightness_sum / n;
+        let variance = (self.brightness_squared_sum / n - mean * mean).max(0.0) * n / (n - 1.0);
+        (variance / n).sqrt()
+    }
+}
+
+/// Renders the scene in passes, giving more samples only to the pixels that are still noisy.
+pub fn render(
+    settings: &RaytracerScene,
+    adaptive: &RonAdaptive,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+    pb: &ProgressBar,
+) -> Framebuffer<Vec3A> {
+    let width = settings.image_width as usize;
+    let height = settings.image_height as usize;
+    let max_samples = adaptive.max_samples.max(2);
+    let pass_samples = adaptive.min_samples.clamp(2, max_samples);
+    let passes = max_samples.div_ceil(pass_samples);
+    pb.set_length((tiles.len() * passes) as u64);
+
+    let mut stats = Framebuffer::new(width, height, PixelStats::default());
+    for _ in 0..passes {
+        stats.render_splatted(
+            tiles,
+            settings.filter.margin(),
+            crop_window(settings),
+            settings.multithreading,
+            pb,
+            |x, y, splat| {
+                let pixel = *splat.get_mut(x as isize, y as isize).unwrap();
+                if pixel.converged {
+                    return;
+                }
+                let coord = [x as f32, y as f32];
+                let end = (pixel.samples + pass_samples).min(max_samples);
+                for sample in pixel.samples..end {
+                    let (colour, offset) =
+                        trace_sample(settings, &coord, sample, max_samples, camera, world);
+                    splat.get_mut(x as isize, y as isize).unwrap().add(colour);
+                    settings
+                        .filter
+                        .splat((x, y), offset, colour, splat, PixelStats::splat);
+                }
+                let pixel = splat.get_mut(x as isize, y as isize).unwrap();
+                pixel.converged =
+                    pixel.samples >= max_samples || pixel.standard_error() <= adaptive.threshold;
+            },
+        );
+        // Pixels outside the tiles, such as those outside a crop window, never get samples.
+        if stats
+            .pixels()
+            .iter()
+            .all(|pixel| pixel.converged || pixel.samples == 0)
+        {
+            break;
+        }
+    }
+    pb.set_position((tiles.len() * passes) as u64);
+
+    if let Some(path) = &adaptive.sample_map {
+        write_sample_map(path, width, height, stats.pixels(), max_samples);
+    }
+
+    stats.map(|pixel| {
+        if pixel.weight > 0.0 {
+            pixel.sum / pixel.weight
+        } else {
+            Vec3A::ZERO
+        }
+    })
+}
+
+/// Writes the samples taken by each pixel as a binary PGM with `max_samples` as white, using
+/// two bytes per pixel when the counts do not fit in one.
+fn write_sample_map(
+    path: &str,
+    width: usize,
+    height: usize,
+    stats: &[PixelStats],
+    max_samples: usize,
+) {
+    let max_value = max_samples.min(u16::MAX as usize);
+    let mut bytes = format!("P5\n{} {}\n{}\n", width, height, max_value).into_bytes();
+    stats.iter().for_each(|pixel| {
+        let count = pixel.samples.min(max_value) as u16;
+        if max_value < 256 {
+            bytes.push(count as u8);
+        } else {
+            bytes.extend_from_slice(&count.to_be_bytes());
+        }
+    });
+    fs::write(path, bytes).expect("failed to write sample map");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RaytracerScene;
+    use crate::create_image;
+    use crate::tests::{small_scene, temp_path};
+
+    fn read_sample_map(path: &str) -> (usize, Vec<usize>) {
+        let bytes = fs::read(path).unwrap();
+        let header = String::from_utf8_lossy(&bytes[..bytes.len().min(32)]).to_string();
+        let fields = header.split_whitespace().take(4).collect::<Vec<_>>();
+        let max_value: usize = fields[3].parse().unwrap();
+        let start = fields.iter().map(|field| field.len() + 1).sum::<usize>();
+        let counts = if max_value < 256 {
+            bytes[start..].iter().map(|count| *count as usize).collect()
+        } else {
+            bytes[start..]
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as usize)
+                .collect()
+        };
+        (max_value, counts)
+    }
+
+    #[test]
+    fn test_sample_map_matches_counts() {
+        let path = temp_path("sample_map.pgm");
+        for max_samples in [40, 1000] {
+            let counts = [2, max_samples, 17, 1, 0, max_samples / 2];
+            let stats = counts
+                .iter()
+                .map(|samples| PixelStats {
+                    samples: *samples,
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            write_sample_map(&path, 3, 2, &stats, max_samples);
+            let header = format!("P5\n3 2\n{}\n", max_samples);
+            assert!(fs::read(&path).unwrap().starts_with(header.as_bytes()));
+            assert_eq!(read_sample_map(&path), (max_samples, counts.to_vec()));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_noisy_pixels_get_more_samples() {
+        let path = temp_path("adaptive.pgm");
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 6));
+        settings.background = ron::from_str("Solid(colour: [0.5, 0.5, 0.5])").unwrap();
+        settings.adaptive = Some(RonAdaptive {
+            min_samples: 4,
+            max_samples: 32,
+            threshold: 0.0001,
+            sample_map: Some(path.clone()),
+        });
+        create_image(settings.to_ron());
+        let (max_value, counts) = read_sample_map(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(max_value, 32);
+        assert_eq!(counts.len(), 30 * 20);
+        // The corners only see the flat background, the spheres are lit noisily.
+        for corner in [0, 29, 570, 599] {
+            assert_eq!(counts[corner], 4);
+        }
+        assert!(counts.iter().filter(|count| **count == 32).count() > 20);
+        assert!(counts.iter().all(|count| (4..=32).contains(count)));
+    }
+
+    #[test]
+    fn test_adaptive_renders_are_not_checkpointed() {
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 6));
+        settings.adaptive =
+            Some(ron::from_str("(min_samples: 4, max_samples: 32, threshold: 0.01)").unwrap());
+        settings.checkpoint = Some(ron::from_str("(path: \"unused.ckpt\")").unwrap());
+        assert_eq!(
+            RaytracerScene::try_from_ron(&settings.to_ron()).unwrap_err(),
+            "adaptive renders can not be checkpointed"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::camera::CameraTracks;
+use crate::configuration::{RaytracerScene, RonCameraKeys, RonLight, RonLightType};
+use crate::conv_py_vec;
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How values change between two keyframes.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// At a constant rate from one key to the next.
+    #[default]
+    Linear,
+    /// Easing out of each key and into the next, so motion starts and stops gently.
+    Smooth,
+    /// Holding each key's value until the next key.
+    Step,
+}
+
+impl Interpolation {
+    fn ease(&self, t: f32) -> f32 {
+        match self {
+            Interpolation::Linear => t,
+            Interpolation::Smooth => t * t * (3.0 - 2.0 * t),
+            Interpolation::Step => 0.0,
+        }
+    }
+}
+
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vec3A {
+    fn lerp(self, other: Vec3A, t: f32) -> Vec3A {
+        Vec3A::lerp(self, other, t)
+    }
+}
+
+/// Values keyed at times, interpolated between the keys and held before the first key and after
+/// the last.
+#[derive(Debug, Clone, Default)]
+pub struct Track<T> {
+    keys: Vec<(f32, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Lerp> Track<T> {
+    pub fn new(keys: Vec<(f32, T)>, interpolation: Interpolation) -> Track<T> {
+        let mut keys = keys;
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Track {
+            keys,
+            interpolation,
+        }
+    }
+
+    /// The value at `time`, or `None` when nothing is keyed.
+    pub fn at(&self, time: f32) -> Option<T> {
+        let next = self.keys.iter().position(|(key_time, _)| *key_time > time);
+        match next {
+            None => self.keys.last().map(|(_, value)| *value),
+            Some(0) => Some(self.keys[0].1),
+            Some(i) => {
+                let (start, from) = self.keys[i - 1];
+                let (end, to) = self.keys[i];
+                Some(from.lerp(to, self.interpolation.ease((time - start) / (end - start))))
+            }
+        }
+    }
+
+    pub fn times(&self) -> impl Iterator<Item = f32> + '_ {
+        self.keys.iter().map(|(time, _)| *time)
+    }
+}
+
+pub fn vec3_track(keys: &[(f32, Vec<f32>)], interpolation: Interpolation) -> Track<Vec3A> {
+    Track::new(
+        keys.iter()
+            .map(|(time, value)| (*time, conv_py_vec(value.clone())))
+            .collect(),
+        interpolation,
+    )
+}
+
+/// The camera's tracks, or `None` when nothing about the camera is keyed.
+pub fn camera_tracks(keys: &RonCameraKeys, interpolation: Interpolation) -> Option<CameraTracks> {
+    if keys.camera_pos.is_empty()
+        && keys.camera_dir.is_empty()
+        && keys.camera_up.is_empty()
+        && keys.v_fov.is_empty()
+    {
+        return None;
+    }
+    Some(CameraTracks {
+        look_from: vec3_track(&keys.camera_pos, interpolation),
+        look_at: vec3_track(&keys.camera_dir, interpolation),
+        v_up: vec3_track(&keys.camera_up, interpolation),
+        v_fov: Track::new(keys.v_fov.clone(), interpolation),
+        over_shutter: false,
+    })
+}
+
+/// The scene's lights with the positions they are keyed to at the scene's frame.
+pub fn lights_at(settings: &RaytracerScene) -> Vec<RonLight> {
+    let mut lights = settings.lights.clone();
+    let animation = match &settings.animation {
+        Some(animation) => animation,
+        None => return lights,
+    };
+    for keys in &animation.lights {
+        let track = vec3_track(&keys.position, animation.interpolation);
+        let position = match track.at(settings.frame) {
+            Some(position) => position.to_array().to_vec(),
+            None => continue,
+        };
+        match lights.get_mut(keys.light) {
+            Some(RonLight::Position(light_position))
+            | Some(RonLight::Typed(RonLightType::Point {
+                position: light_position,
+                ..
+            }))
+            | Some(RonLight::Typed(RonLightType::Spot {
+                position: light_position,
+                ..
+            })) => *light_position = position,
+            Some(RonLight::Typed(RonLightType::Directional { .. })) => {
+                panic!(
+                    "directional light {} has no position to animate",
+                    keys.light
+                )
+            }
+            None => panic!("no light {} to animate", keys.light),
+        }
+    }
+    lights
+}
+
+/// `path` numbered with `frame`, replacing its last run of `#` with the frame number padded to
+/// the length of the run, or adding the frame number padded to four digits before the extension
+/// when there is no `#`.
+pub fn frame_path(path: &str, frame: i32) -> String {
+    match path.rfind('#') {
+        Some(end) => {
+            let start = path[..end].trim_end_matches('#').len();
+            format!(
+                "{}{:0width$}{}",
+                &path[..start],
+                frame,
+                &path[end + 1..],
+                width = end + 1 - start
+            )
+        }
+        None => match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(extension) => format!(
+                "{}_{:04}.{}",
+                &path[..path.len() - extension.len() - 1],
+                frame,
+                extension
+            ),
+            None => format!("{}_{:04}", path, frame),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RonKeyframes;
+    use crate::material::Material;
+    use crate::motion::Motion;
+    use crate::tests::{small_scene, temp_path};
+    use crate::triangle::Triangle;
+    use crate::{create_image, create_sequence, encode_image};
+
+    /// Renders frames `start` to `end` of the scene and reads them back, leaving no files behind.
+    fn render_sequence(
+        settings: &RaytracerScene,
+        start: i32,
+        end: i32,
+        name: &str,
+    ) -> Vec<Vec<u8>> {
+        let paths = create_sequence(settings.to_ron(), start, end, &temp_path(name)).unwrap();
+        paths
+            .iter()
+            .map(|path| {
+                let bytes = std::fs::read(path).unwrap();
+                std::fs::remove_file(path).unwrap();
+                bytes
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_animation() {
+        let keys = vec![(2.0, 4.0), (0.0, 0.0)];
+        let linear = Track::new(keys.clone(), Interpolation::Linear);
+        assert_eq!(linear.at(-1.0), Some(0.0));
+        assert_eq!(linear.at(1.0), Some(2.0));
+        assert_eq!(linear.at(3.0), Some(4.0));
+        let smooth = Track::new(keys.clone(), Interpolation::Smooth);
+        assert_eq!(smooth.at(1.0), Some(2.0));
+        assert!(smooth.at(0.5).unwrap() < 1.0);
+        assert_eq!(Track::new(keys, Interpolation::Step).at(1.9), Some(0.0));
+        assert_eq!(Track::<f32>::default().at(1.0), None);
+
+        assert_eq!(frame_path("out/frame_####.png", 7), "out/frame_0007.png");
+        assert_eq!(frame_path("out/frame_#.exr", 12), "out/frame_12.exr");
+        assert_eq!(frame_path("out/frame.ppm", 3), "out/frame_0003.ppm");
+
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 4));
+        settings.objects[0].keyframes = Some(
+            ron::from_str("(translation: [(0.0, [0.0, 0.0, 0.0]), (2.0, [-0.6, 0.0, 0.0])])")
+                .unwrap(),
+        );
+        settings.animation = Some(
+            ron::from_str(
+                "(interpolation: Smooth, camera: (v_fov: [(0.0, 90.0), (2.0, 70.0)]), lights: [(light: 0, position: [(0.0, [-1.0, 1.5, -3.5]), (2.0, [1.0, 1.5, -3.5])])])",
+            )
+            .unwrap(),
+        );
+        let frames = render_sequence(&settings, 0, 2, "animation_##.ppm");
+        assert_eq!(frames.len(), 3);
+        assert_ne!(frames[0], frames[2]);
+        let output = temp_path("animation_##.ppm");
+        assert!(create_sequence(settings.to_ron(), 2, 1, &output).is_err());
+        settings.frame = 1.0;
+        let single = create_image(settings.to_ron());
+        assert_eq!(frames[1], encode_image(&single, "ppm").unwrap());
+
+        let keyframes: RonKeyframes = ron::from_str(
+            "(pivot: [1.0, 0.0, 0.0], translation: [(0.0, [0.0, 0.0, 0.0]), (4.0, [0.0, 4.0, 0.0])], rotation: [(0.0, [0.0, 0.0, 0.0]), (4.0, [0.0, 0.0, 360.0])])",
+        )
+        .unwrap();
+        let triangle = Triangle::new(
+            Vec3A::new(2.0, 0.0, 0.0),
+            Vec3A::new(2.5, 0.5, 0.0),
+            Vec3A::new(2.0, 0.5, 0.5),
+            Material::Mirror,
+            false,
+        );
+        let bounds = triangle
+            .with_motion(Motion::from_keyframes(&keyframes))
+            .get_aabb();
+        for i in 0..=400 {
+            let time = i as f32 / 100.0;
+            // Where the first corner has been carried to at `time`, a full turn over the keys.
+            let angle = std::f32::consts::FRAC_PI_2 * time;
+            let corner = Vec3A::new(1.0 + angle.cos(), time + angle.sin(), 0.0);
+            assert!(corner.cmpge(bounds.min).all() && corner.cmple(bounds.max).all());
+        }
+    }
+
+    #[test]
+    fn test_sequence_motion_blur() {
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 4));
+        settings.objects[0].motion =
+            Some(ron::from_str("(end: (translation: [0.6, 0.0, 0.0]))").unwrap());
+        settings.camera_end = Some(
+            ron::from_str("(camera_pos: [0.3, 0.0, -3.5], camera_dir: [0.3, 0.0, 0.0], camera_up: [0.0, 1.0, 0.0])")
+                .unwrap(),
+        );
+        let still = create_image(settings.to_ron());
+        settings.shutter = Some(ron::from_str("()").unwrap());
+
+        let frames = render_sequence(&settings, 0, 1, "sequence_blur_#.ppm");
+        // Start to end motions run over the shutter of every frame, so later frames blur the same.
+        assert_eq!(frames[0], frames[1]);
+        assert_ne!(frames[1], encode_image(&still, "ppm").unwrap());
+    }
+}
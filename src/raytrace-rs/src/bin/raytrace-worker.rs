@@ -0,0 +1,14 @@
+//! Renders the tiles assigned to the scene's worker, reading the scene from stdin and writing the
+//! partial render to stdout for `create_image_distributed` to assemble.
+use std::io::{self, Read, Write};
+
+fn main() {
+    let mut ron_string = String::new();
+    io::stdin()
+        .read_to_string(&mut ron_string)
+        .expect("failed to read scene from stdin");
+    let partial = raytrace_rs::create_partial(ron_string);
+    io::stdout()
+        .write_all(&partial)
+        .expect("failed to write partial render");
+}
@@ -0,0 +1,197 @@
+//! Renders a `.ron` scene file to a PNG, PPM, OpenEXR, PFM or Radiance HDR image without Python.
+use raytrace_rs::SceneOverrides;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "\
+usage: raytrace <scene.ron | -> [options]
+
+options:
+  -o, --output <path>   image to write, .png, .ppm, .exr, .pfm or .hdr, or - for PPM on stdout
+                        (defaults to the scene path with a .png extension)
+  --width <pixels>      image width, keeping the aspect ratio unless --height is also given
+  --height <pixels>     image height, keeping the aspect ratio unless --width is also given
+  --samples <count>     samples per pixel
+  --threads <count>     render threads, 1 renders on a single thread
+  --seed <seed>         seed of the random number streams
+  --aovs                add albedo, normal and depth layers to an .exr output
+  --camera-json         save the camera's intrinsic and world to camera matrices next to the
+                        output, with a .json extension
+  --frames <start:end>  render frames start to end of an animated scene, numbering the output
+                        by replacing its last run of # with the frame number
+                        (defaults to the scene path with a _####.png suffix)
+  -h, --help            print this message";
+
+struct Options {
+    scene: String,
+    output: String,
+    threads: Option<usize>,
+    frames: Option<(i32, i32)>,
+    overrides: SceneOverrides,
+}
+
+fn value<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, String> {
+    let value = args
+        .next()
+        .ok_or_else(|| format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {} for {}", value, flag))
+}
+
+fn frames(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<(i32, i32), String> {
+    let range: String = value(args, flag)?;
+    let invalid = || {
+        format!(
+            "invalid value {} for {}, expected <start>:<end>",
+            range, flag
+        )
+    };
+    let (start, end) = range.split_once(':').ok_or_else(invalid)?;
+    let start = start.parse().map_err(|_| invalid())?;
+    let end = end.parse().map_err(|_| invalid())?;
+    if end < start {
+        return Err(format!("{} ends before it starts", flag));
+    }
+    Ok((start, end))
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut args = args;
+    let mut scene = None;
+    let mut output = None;
+    let mut threads = None;
+    let mut aovs = false;
+    let mut camera_json = false;
+    let mut frames_range = None;
+    let mut overrides = SceneOverrides::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-o" | "--output" => output = Some(value(&mut args, &arg)?),
+            "--width" => overrides.image_width = Some(value(&mut args, &arg)?),
+            "--height" => overrides.image_height = Some(value(&mut args, &arg)?),
+            "--samples" => overrides.samples_per_pixel = Some(value(&mut args, &arg)?),
+            "--threads" => threads = Some(value(&mut args, &arg)?),
+            "--seed" => overrides.seed = Some(value(&mut args, &arg)?),
+            "--aovs" => aovs = true,
+            "--camera-json" => camera_json = true,
+            "--frames" => frames_range = Some(frames(&mut args, &arg)?),
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown option {}", flag))
+            }
+            _ if scene.is_none() => scene = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let scene: String = scene.ok_or("no scene file given")?;
+    let output = match output {
+        Some(output) => output,
+        None if scene == "-" => "-".to_string(),
+        None if frames_range.is_some() => {
+            let path = Path::new(&scene);
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{}_####.png", stem))
+                .to_string_lossy()
+                .into_owned()
+        }
+        None => Path::new(&scene)
+            .with_extension("png")
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let extension = Path::new(&output)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("exr") => overrides.hdr_output = Some((output.clone(), aovs)),
+        _ if aovs => return Err("--aovs needs an .exr output".to_string()),
+        Some("pfm") | Some("hdr") => overrides.hdr_output = Some((output.clone(), false)),
+        Some("png") | Some("ppm") => {}
+        _ if output == "-" => {}
+        _ => return Err(format!("unsupported output format {}", output)),
+    }
+    if frames_range.is_some() && output == "-" {
+        return Err("--frames cannot write to stdout".to_string());
+    }
+    if camera_json {
+        if output == "-" {
+            return Err("--camera-json needs an output file".to_string());
+        }
+        overrides.camera_output = Some(
+            Path::new(&output)
+                .with_extension("json")
+                .to_string_lossy()
+                .into_owned(),
+        );
+    }
+    if threads == Some(0) {
+        return Err("--threads must be at least 1".to_string());
+    }
+    overrides.multithreading = threads.map(|threads| threads > 1);
+
+    Ok(Options {
+        scene,
+        output,
+        threads,
+        frames: frames_range,
+        overrides,
+    })
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let ron_string = if options.scene == "-" {
+        let mut ron_string = String::new();
+        io::stdin()
+            .read_to_string(&mut ron_string)
+            .map_err(|e| format!("unable to read scene from stdin: {}", e))?;
+        ron_string
+    } else {
+        std::fs::read_to_string(&options.scene)
+            .map_err(|e| format!("unable to read {}: {}", options.scene, e))?
+    };
+    if let Some(threads) = options.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|e| e.to_string())?;
+    }
+
+    let ron_string = raytrace_rs::override_scene(ron_string, &options.overrides);
+    raytrace_rs::check_scene(&ron_string)?;
+    if let Some((start, end)) = options.frames {
+        return raytrace_rs::create_sequence(ron_string, start, end, &options.output).map(|_| ());
+    }
+    let hdr = options.overrides.hdr_output.is_some();
+    let image = raytrace_rs::create_image(ron_string);
+    if hdr {
+        // The scene's HDR output has already been written by the render.
+        Ok(())
+    } else if options.output == "-" {
+        io::stdout()
+            .write_all(&raytrace_rs::encode_image(&image, "ppm")?)
+            .map_err(|e| format!("unable to write image to stdout: {}", e))
+    } else {
+        raytrace_rs::save_image(&image, &options.output)
+    }
+}
+
+fn main() {
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("raytrace: {}\n\n{}", e, USAGE);
+        process::exit(2);
+    });
+    if let Err(e) = run(options) {
+        eprintln!("raytrace: {}", e);
+        process::exit(1);
+    }
+}
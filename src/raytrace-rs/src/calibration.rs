@@ -0,0 +1,253 @@
+//! Matching the camera to calibrated real cameras with the 3x3 intrinsic and 4x4 world to camera
+//! matrices of OpenCV, which most computer vision datasets use. Their camera space has x to the
+//! right, y down the image and z forward, and pixel `(0, 0)` is the centre of the top left pixel.
+use crate::camera::{Camera, Distortion, Projection, Setup};
+use crate::configuration::RaytracerScene;
+use crate::crop_window;
+use crate::ray::Time;
+use glam::Vec3A;
+
+// The renderer spreads the centres of the pixels from 0.5 / (width - 1) across the view to
+// (width - 0.5) / (width - 1), a little past its far edge, so a view is (width - 1) / fx wide.
+
+/// The `N` by `N` matrix in `rows`, panicking with the `name` of the matrix if it is another
+/// size.
+pub fn square_matrix<const N: usize>(rows: &[Vec<f32>], name: &str) -> [[f32; N]; N] {
+    if rows.len() != N || rows.iter().any(|row| row.len() != N) {
+        panic!("the {} matrix must be {} by {}", name, N, N);
+    }
+    let mut matrix = [[0.0; N]; N];
+    for (row, values) in matrix.iter_mut().zip(rows) {
+        row.copy_from_slice(values);
+    }
+    matrix
+}
+
+/// The vertical field of view in degrees, aspect ratio and shift of the view an intrinsic
+/// `matrix` gives an image `width` by `height` pixels.
+pub fn view_from_intrinsics(
+    matrix: &[[f32; 3]; 3],
+    width: i32,
+    height: i32,
+) -> (f32, f32, (f32, f32)) {
+    if matrix[0][1] != 0.0 {
+        panic!("intrinsic matrices with skew are not supported");
+    }
+    let (fx, cx) = (matrix[0][0], matrix[0][2]);
+    let (fy, cy) = (matrix[1][1], matrix[1][2]);
+    let (width, height) = ((width - 1) as f32, (height - 1) as f32);
+    let view_width = width / fx;
+    let view_height = height / fy;
+    let shift_x = 0.5 - (cx + 0.5) / width;
+    let shift_y = 0.5 - (height + 0.5 - cy) / height;
+    (
+        2.0 * (view_height / 2.0).atan().to_degrees(),
+        view_width / view_height,
+        (shift_x, shift_y),
+    )
+}
+
+/// The intrinsic matrix of a perspective camera built from `setup` rendering an image `width`
+/// by `height` pixels.
+pub fn intrinsics(setup: &Setup, width: i32, height: i32) -> [[f32; 3]; 3] {
+    let (width, height) = ((width - 1) as f32, (height - 1) as f32);
+    let view_height = 2.0 * (setup.v_fov.to_radians() / 2.0).tan();
+    let view_width = setup.aspect_ratio * view_height;
+    let cx = (0.5 - setup.shift.0) * width - 0.5;
+    let cy = height + 0.5 - (0.5 - setup.shift.1) * height;
+    [
+        [width / view_width, 0.0, cx],
+        [0.0, height / view_height, cy],
+        [0.0, 0.0, 1.0],
+    ]
+}
+
+/// Where a camera with the world to camera `matrix` is, a point it looks at and its up direction.
+pub fn pose_from_world_to_camera(matrix: &[[f32; 4]; 4]) -> (Vec3A, Vec3A, Vec3A) {
+    let row = |i: usize| Vec3A::new(matrix[i][0], matrix[i][1], matrix[i][2]);
+    let (right, down, forward) = (row(0), row(1), row(2));
+    let position = -(right * matrix[0][3] + down * matrix[1][3] + forward * matrix[2][3]);
+    (position, position + forward, -down)
+}
+
+/// The world to camera matrix of a camera built from `setup`.
+pub fn world_to_camera(setup: &Setup) -> [[f32; 4]; 4] {
+    let w = (setup.look_from - setup.look_at).normalize();
+    let u = setup.v_up.cross(w).normalize();
+    let v = w.cross(u);
+    let row = |axis: Vec3A| [axis.x, axis.y, axis.z, -axis.dot(setup.look_from)];
+    [row(u), row(-v), row(-w), [0.0, 0.0, 0.0, 1.0]]
+}
+
+/// `value` as a JSON number, which has no way of writing infinities or NaN.
+fn number_json(value: f32) -> Result<String, String> {
+    if value.is_finite() {
+        // Adding zero writes negative zeros as 0.
+        Ok((value + 0.0).to_string())
+    } else {
+        Err(format!(
+            "the camera output can not hold the value {}",
+            value
+        ))
+    }
+}
+
+fn matrix_json<const N: usize>(matrix: &[[f32; N]; N]) -> Result<String, String> {
+    let rows = matrix
+        .iter()
+        .map(|row| {
+            let values = row
+                .iter()
+                .map(|value| number_json(*value))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(format!("[{}]", values.join(", ")))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(format!("[\n    {}\n  ]", rows.join(",\n    ")))
+}
+
+fn distortion_json(distortion: &Distortion) -> Result<String, String> {
+    Ok(format!(
+        "{{\"k1\": {}, \"k2\": {}, \"p1\": {}, \"p2\": {}, \"k3\": {}}}",
+        number_json(distortion.k1)?,
+        number_json(distortion.k2)?,
+        number_json(distortion.p1)?,
+        number_json(distortion.p2)?,
+        number_json(distortion.k3)?
+    ))
+}
+
+/// The intrinsics and extrinsics `camera` renders the scene's frame with as JSON, matching the
+/// saved image when it is cropped. Only a perspective camera without stereo has an intrinsic
+/// matrix, the others have `null`. Fails if the camera has a value JSON can not hold.
+pub fn camera_json(settings: &RaytracerScene, camera: &Camera) -> Result<String, String> {
+    let setup = camera.setup_at(Time::new(settings.frame, 0.0));
+    let (mut width, mut height) = (settings.image_width, settings.image_height);
+    let mut intrinsics = match (setup.projection, setup.stereo) {
+        (Projection::Perspective, None) => Some(intrinsics(&setup, width, height)),
+        _ => None,
+    };
+    if let (Some(crop), Some(window)) = (&settings.crop, crop_window(settings)) {
+        if !crop.full_frame {
+            width = window.width as i32;
+            height = window.height as i32;
+            if let Some(matrix) = &mut intrinsics {
+                matrix[0][2] -= window.x as f32;
+                matrix[1][2] -= window.y as f32;
+            }
+        }
+    }
+    let null_or = |json: Option<String>| json.unwrap_or_else(|| "null".to_string());
+    Ok(format!(
+        "{{\n  \"frame\": {},\n  \"width\": {},\n  \"height\": {},\n  \"intrinsics\": {},\n  \
+         \"world_to_camera\": {},\n  \"distortion\": {},\n  \"focal_distance\": {},\n  \
+         \"aperture\": {}\n}}\n",
+        number_json(settings.frame)?,
+        width,
+        height,
+        null_or(intrinsics.map(|matrix| matrix_json(&matrix)).transpose()?),
+        matrix_json(&world_to_camera(&setup))?,
+        null_or(setup.distortion.map(|d| distortion_json(&d)).transpose()?),
+        number_json(setup.focus_dist)?,
+        number_json(setup.aperature)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Aperture;
+    use crate::tests::{small_scene, temp_path};
+    use crate::{create_camera, create_features, create_objects, create_world};
+
+    #[test]
+    fn test_camera_calibration() {
+        let intrinsics = [[24.0, 0.0, 13.0], [0.0, 26.0, 11.0], [0.0, 0.0, 1.0]];
+        let mut settings = RaytracerScene::from_ron(small_scene(false, 2));
+        settings.intrinsics = Some(intrinsics.iter().map(|row| row.to_vec()).collect());
+        settings.world_to_camera = Some(vec![
+            vec![0.0, 0.0, -1.0, 1.0],
+            vec![0.0, -1.0, 0.0, 0.5],
+            vec![-1.0, 0.0, 0.0, 2.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+        let objects = create_objects(&settings);
+        let world = create_world(&settings, &objects);
+        let camera = create_camera(
+            &settings,
+            &objects,
+            &world,
+            &Aperture::new(&settings.aperture_shape),
+        );
+        let setup = camera.setup_at(Time::default());
+        // The camera sits at (2, 0.5, 1) looking down -x.
+        assert!(setup.look_from.abs_diff_eq(Vec3A::new(2.0, 0.5, 1.0), 1e-5));
+        let exported = super::intrinsics(&setup, 30, 20);
+        for (row, expected) in exported.iter().zip(&intrinsics) {
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-3);
+            }
+        }
+        let extrinsics = world_to_camera(&setup);
+
+        // A point projected into the image by the matrices is on the ray through its pixel.
+        let sees = |camera: &Camera, point: Vec3A, (x, y): (f32, f32)| {
+            let ray = camera
+                .centre_ray((x + 0.5) / 29.0, (19.5 - y) / 19.0, Time::default())
+                .unwrap();
+            let towards = (point - ray.origin()).normalize();
+            towards.abs_diff_eq(ray.direction().normalize(), 1e-4)
+        };
+        let point = Vec3A::new(-1.0, 1.0, 0.3);
+        let in_camera: Vec<f32> = extrinsics[..3]
+            .iter()
+            .map(|row| Vec3A::new(row[0], row[1], row[2]).dot(point) + row[3])
+            .collect();
+        let (x, y) = (in_camera[0] / in_camera[2], in_camera[1] / in_camera[2]);
+        let pixel = |x: f32, y: f32| (24.0 * x + 13.0, 26.0 * y + 11.0);
+        assert!(sees(&camera, point, pixel(x, y)));
+
+        let distortion = Distortion {
+            k1: -0.2,
+            k2: 0.05,
+            k3: 0.0,
+            p1: 0.01,
+            p2: -0.005,
+        };
+        let r2 = x * x + y * y;
+        let radial = 1.0 + distortion.k1 * r2 + distortion.k2 * r2 * r2;
+        let distorted = (
+            x * radial + 2.0 * distortion.p1 * x * y + distortion.p2 * (r2 + 2.0 * x * x),
+            y * radial + distortion.p1 * (r2 + 2.0 * y * y) + 2.0 * distortion.p2 * x * y,
+        );
+        let (ux, uy) = distortion.undistort(distorted.0, distorted.1);
+        assert!((ux - x).abs() < 1e-5 && (uy - y).abs() < 1e-5);
+        let camera = camera.with_distortion(distortion);
+        assert!(sees(&camera, point, pixel(distorted.0, distorted.1)));
+        assert!(camera_json(&settings, &camera)
+            .unwrap()
+            .contains("\"distortion\": {\"k1\": -0.2,"));
+        let camera = camera.with_distortion(Distortion {
+            k1: f32::NAN,
+            ..distortion
+        });
+        assert_eq!(
+            camera_json(&settings, &camera).unwrap_err(),
+            "the camera output can not hold the value NaN"
+        );
+
+        let path = temp_path("camera.json");
+        let mut settings = RaytracerScene::from_ron(small_scene(false, 2));
+        settings.camera_output = Some(path.clone());
+        settings.crop =
+            Some(ron::from_str("(x: 10.0, y: 5.0, width: 10.0, height: 10.0)").unwrap());
+        let cropped = create_features(settings.to_ron());
+        assert_eq!(cropped.2.len(), 10);
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(json.contains("\"width\": 10,"));
+        assert!(json.contains("[0, 0, 1, 3.5]"));
+        assert!(json.contains("[9.666667, 0, 4]"));
+        assert!(json.contains("\"distortion\": null"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,12 +1,316 @@
-use crate::random_f32;
-use crate::ray::Ray;
+use crate::animation::{Interpolation, Track};
+use crate::configuration::{RonPhysicalCamera, RonShutter};
+use crate::environment::{luminance, search_cdf};
+use crate::hdr::HdrImage;
+use crate::ray::{Ray, Time};
+use crate::sampler;
 use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
+use std::sync::Arc;
 
+/// How the camera maps the image onto directions in the scene.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+pub enum Projection {
+    /// A pinhole or thin lens camera seeing `v_fov` degrees from the bottom of the image to the
+    /// top.
+    #[default]
+    Perspective,
+    /// Parallel rays covering a view `height` units tall, for maps and technical drawings. The
+    /// aperture and focal distance still blur whatever is out of focus.
+    Orthographic { height: f32 },
+    /// A circular image inscribed in the height of the frame covering `fov` degrees across,
+    /// which can be more than 180. Everything outside the circle is black.
+    Fisheye {
+        #[serde(default = "half_turn")]
+        fov: f32,
+        #[serde(default)]
+        mapping: FisheyeMapping,
+    },
+    /// A full 360 degree panorama, longitude across the image and latitude up it, for
+    /// environment maps and VR. The image is usually twice as wide as it is tall.
+    Equirectangular,
+}
+
+fn half_turn() -> f32 {
+    180.0
+}
+
+/// How far from the centre of a fisheye image a direction lands for its angle off the axis.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle, keeping angles between directions even.
+    #[default]
+    Equidistant,
+    /// Distance proportional to the sine of half the angle, keeping areas of equal solid angle
+    /// equal.
+    Equisolid,
+}
+
+/// Where the two eyes of a stereo pair go in the image.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum StereoLayout {
+    /// The left eye in the left half of the image and the right eye in the right half.
+    #[default]
+    SideBySide,
+    /// The left eye in the top half of the image and the right eye in the bottom half, the usual
+    /// layout for 360 degree VR.
+    TopBottom,
+}
+
+/// The shape of the lens opening, which out of focus highlights take on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub enum ApertureShape {
+    #[default]
+    Circle,
+    /// A regular polygon of `blades` sides, like the iris of a real lens, turned `rotation`
+    /// degrees.
+    Polygon {
+        blades: u32,
+        #[serde(default)]
+        rotation: f32,
+    },
+    /// The bright parts of a `.hdr` or `.pfm` image scaled to fit the aperture, for hearts,
+    /// stars or the cat's eye shapes of real lenses.
+    Image { path: String },
+}
+
+/// Brown-Conrady lens distortion with the radial `k1`, `k2` and `k3` and tangential `p1` and `p2`
+/// coefficients of OpenCV, which map where a ray would land on an ideal image to where the lens
+/// bends it.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Default)]
+pub struct Distortion {
+    #[serde(default)]
+    pub k1: f32,
+    #[serde(default)]
+    pub k2: f32,
+    #[serde(default)]
+    pub k3: f32,
+    #[serde(default)]
+    pub p1: f32,
+    #[serde(default)]
+    pub p2: f32,
+}
+
+impl Distortion {
+    /// Where the point `(x, y)` of the distorted image, in focal lengths right of and below the
+    /// principal point, lands on the ideal image. The distortion has no closed form inverse, so
+    /// it is found by fixed point iteration like OpenCV's `undistortPoints`.
+    pub fn undistort(&self, x: f32, y: f32) -> (f32, f32) {
+        let (mut ux, mut uy) = (x, y);
+        for _ in 0..20 {
+            let r2 = ux * ux + uy * uy;
+            let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+            let dx = 2.0 * self.p1 * ux * uy + self.p2 * (r2 + 2.0 * ux * ux);
+            let dy = self.p1 * (r2 + 2.0 * uy * uy) + 2.0 * self.p2 * ux * uy;
+            ux = (x - dx) / radial;
+            uy = (y - dy) / radial;
+        }
+        (ux, uy)
+    }
+}
+
+/// An aperture shape ready to be sampled. An image is loaded once for the scene and shared by
+/// every camera built from it.
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    Polygon { blades: u32, rotation: f32 },
+    Image(Arc<ApertureImage>),
+}
+
+/// Cumulative distributions over the brightness of an aperture image, built like an
+/// environment map's.
+pub struct ApertureImage {
+    width: usize,
+    height: usize,
+    marginal_cdf: Vec<f32>,
+    conditional_cdf: Vec<f32>,
+}
+
+impl Aperture {
+    pub fn new(shape: &ApertureShape) -> Aperture {
+        match shape {
+            ApertureShape::Circle => Aperture::Circle,
+            ApertureShape::Polygon { blades, rotation } => Aperture::Polygon {
+                blades: *blades,
+                rotation: rotation.to_radians(),
+            },
+            ApertureShape::Image { path } => Aperture::Image(Arc::new(ApertureImage::new(
+                HdrImage::load(path).expect("failed to load aperture image"),
+            ))),
+        }
+    }
+
+    /// A point on the aperture scaled to fit in the unit disk.
+    fn sample(&self) -> Vec3A {
+        match self {
+            Aperture::Circle => random_in_unit_disk(),
+            Aperture::Polygon { blades, rotation } => {
+                // One number picks the wedge between the centre and a side and is reused to
+                // place the point in it, keeping the stratification of the samples.
+                let (s, t) = sampler::get_2d();
+                let sides = *blades as f32;
+                let wedge = (s * sides).floor().min(sides - 1.0);
+                let along = s * sides - wedge;
+                let corner = |i: f32| {
+                    let angle = rotation + TAU * i / sides;
+                    Vec3A::new(angle.cos(), angle.sin(), 0.0)
+                };
+                (corner(wedge) * (1.0 - t) + corner(wedge + 1.0) * t) * along.sqrt()
+            }
+            Aperture::Image(image) => image.sample(),
+        }
+    }
+}
+
+impl ApertureImage {
+    fn new(image: HdrImage) -> ApertureImage {
+        let (width, height) = (image.width, image.height);
+        let mut conditional_cdf = vec![0.0; width * height];
+        let mut marginal_cdf = vec![0.0; height];
+        let mut total_weight = 0.0;
+        for y in 0..height {
+            let mut row_weight = 0.0;
+            for x in 0..width {
+                row_weight += luminance(image.get(x, y)).max(0.0);
+                conditional_cdf[y * width + x] = row_weight;
+            }
+            if row_weight > 0.0 {
+                conditional_cdf[y * width..(y + 1) * width]
+                    .iter_mut()
+                    .for_each(|c| *c /= row_weight);
+            }
+            total_weight += row_weight;
+            marginal_cdf[y] = total_weight;
+        }
+        if total_weight <= 0.0 {
+            panic!("the aperture image has nothing bright to let light through");
+        }
+        marginal_cdf.iter_mut().for_each(|c| *c /= total_weight);
+        ApertureImage {
+            width,
+            height,
+            marginal_cdf,
+            conditional_cdf,
+        }
+    }
+
+    fn sample(&self) -> Vec3A {
+        let (s, t) = sampler::get_2d();
+        let (y, y_offset) = search_cdf(&self.marginal_cdf, s);
+        let (x, x_offset) = search_cdf(
+            &self.conditional_cdf[y * self.width..(y + 1) * self.width],
+            t,
+        );
+        // The longer side of the image spans the aperture's diameter.
+        let size = self.width.max(self.height) as f32;
+        Vec3A::new(
+            (2.0 * (x as f32 + x_offset) - self.width as f32) / size,
+            (self.height as f32 - 2.0 * (y as f32 + y_offset)) / size,
+            0.0,
+        )
+    }
+}
+
+// The exposure value at ISO 100 of the sunny 16 rule, f/16 at 1/100s, which physical cameras are
+// calibrated to so that it leaves the brightness of a render as it is.
+const SUNNY_16_EV: f32 = 14.643856;
+
+impl RonPhysicalCamera {
+    /// Vertical field of view in degrees of an image with `aspect_ratio`, which covers as much of
+    /// the sensor as it can with its shape.
+    pub fn v_fov(&self, aspect_ratio: f32) -> f32 {
+        let height = if aspect_ratio >= self.sensor_width / self.sensor_height {
+            self.sensor_width / aspect_ratio
+        } else {
+            self.sensor_height
+        };
+        2.0 * (height / (2.0 * self.focal_length)).atan().to_degrees()
+    }
+
+    /// Diameter of the lens opening in scene units.
+    pub fn aperture(&self) -> f32 {
+        self.focal_length / self.f_number / 1000.0 / self.metres_per_unit
+    }
+
+    /// Stops the render is brightened by, the difference between the exposure value of the
+    /// settings and the sunny 16 rule.
+    pub fn exposure(&self) -> f32 {
+        let ev =
+            (self.f_number * self.f_number / self.shutter_speed).log2() - (self.iso / 100.0).log2();
+        SUNNY_16_EV - ev
+    }
+
+    /// The shutter interval in frames the shutter speed gives at the frame rate, when there is
+    /// one.
+    pub fn shutter(&self) -> Option<RonShutter> {
+        self.frame_rate.map(|frame_rate| RonShutter {
+            open: 0.0,
+            close: self.shutter_speed * frame_rate,
+        })
+    }
+}
+
+// Shirley's concentric mapping of the unit square onto the unit disk, which keeps the
+// stratification of the lens samples.
 fn random_in_unit_disk() -> Vec3A {
-    loop {
-        let p = Vec3A::new(random_f32(-1.0, 1.0), random_f32(-1.0, 1.0), 0.0);
-        if p.length_squared() < 1.0 {
-            return p;
+    let (s, t) = sampler::get_2d();
+    let (a, b) = (2.0 * s - 1.0, 2.0 * t - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3A::ZERO;
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, 2.0 * FRAC_PI_4 - FRAC_PI_4 * (a / b))
+    };
+    Vec3A::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+/// What a camera is built from, kept so a moving camera can be rebuilt part way along its motion.
+#[derive(Debug, Copy, Clone)]
+pub struct Setup {
+    pub look_from: Vec3A,
+    pub look_at: Vec3A,
+    pub v_up: Vec3A,
+    pub v_fov: f32,
+    pub aspect_ratio: f32,
+    pub aperature: f32,
+    pub focus_dist: f32,
+    pub projection: Projection,
+    pub stereo: Option<(f32, StereoLayout)>,
+    /// How far the centre of the view is moved right and up, in view widths and heights.
+    pub shift: (f32, f32),
+    pub distortion: Option<Distortion>,
+}
+
+/// Keyed values a camera moves through over time. Anything left unkeyed stays as the camera was
+/// built.
+#[derive(Debug, Clone, Default)]
+pub struct CameraTracks {
+    pub look_from: Track<Vec3A>,
+    pub look_at: Track<Vec3A>,
+    pub v_up: Track<Vec3A>,
+    pub v_fov: Track<f32>,
+    /// Whether the keys are times within the shutter of every frame rather than of the
+    /// animation.
+    pub over_shutter: bool,
+}
+
+impl CameraTracks {
+    fn setup_at(&self, setup: &Setup, time: Time) -> Setup {
+        let time = if self.over_shutter {
+            time.shutter
+        } else {
+            time.animation()
+        };
+        Setup {
+            look_from: self.look_from.at(time).unwrap_or(setup.look_from),
+            look_at: self.look_at.at(time).unwrap_or(setup.look_at),
+            v_up: self.v_up.at(time).unwrap_or(setup.v_up),
+            v_fov: self.v_fov.at(time).unwrap_or(setup.v_fov),
+            ..*setup
         }
     }
 }
@@ -21,6 +325,9 @@ pub struct Camera {
     u: Vec3A,
     v: Vec3A,
     lens_radius: f32,
+    setup: Setup,
+    tracks: Option<CameraTracks>,
+    aperture: Aperture,
 }
 
 impl Camera {
@@ -33,6 +340,41 @@ impl Camera {
         aperature: f32,
         focus_dist: f32,
     ) -> Camera {
+        Camera::from_setup(Setup {
+            look_from,
+            look_at,
+            v_up,
+            v_fov,
+            aspect_ratio,
+            aperature,
+            focus_dist,
+            projection: Projection::Perspective,
+            stereo: None,
+            shift: (0.0, 0.0),
+            distortion: None,
+        })
+    }
+
+    fn from_setup(setup: Setup) -> Camera {
+        let Setup {
+            look_from,
+            look_at,
+            v_up,
+            v_fov,
+            aspect_ratio,
+            aperature,
+            focus_dist,
+            projection,
+            stereo,
+            shift,
+            ..
+        } = setup;
+        // Each eye of a stereo pair gets half the image.
+        let aspect_ratio = match stereo {
+            Some((_, StereoLayout::SideBySide)) => aspect_ratio / 2.0,
+            Some((_, StereoLayout::TopBottom)) => aspect_ratio * 2.0,
+            None => aspect_ratio,
+        };
         let theta = (v_fov * std::f32::consts::PI) / 180.0;
         let h = (theta / 2.0).tan();
 
@@ -45,9 +387,30 @@ impl Camera {
         let v = w.cross(u);
 
         let _origin = look_from;
-        let _horizontal = u * focus_dist * _viewport_width;
-        let _vertical = v * focus_dist * _viewport_height;
-        let _lower_left_corner = _origin - _horizontal * 0.5 - _vertical * 0.5 - w * focus_dist;
+        let (_horizontal, _vertical, _lower_left_corner) = match projection {
+            // The view lies on the plane through the camera rather than the focal plane.
+            Projection::Orthographic { height } => {
+                let horizontal = u * height * aspect_ratio;
+                let vertical = v * height;
+                (
+                    horizontal,
+                    vertical,
+                    _origin - horizontal * (0.5 - shift.0) - vertical * (0.5 - shift.1),
+                )
+            }
+            _ => {
+                let horizontal = u * focus_dist * _viewport_width;
+                let vertical = v * focus_dist * _viewport_height;
+                (
+                    horizontal,
+                    vertical,
+                    _origin
+                        - horizontal * (0.5 - shift.0)
+                        - vertical * (0.5 - shift.1)
+                        - w * focus_dist,
+                )
+            }
+        };
 
         let lens_radius = aperature / 2.0;
 
@@ -60,17 +423,378 @@ impl Camera {
             u,
             v,
             lens_radius,
+            setup,
+            tracks: None,
+            aperture: Aperture::Circle,
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = random_in_unit_disk() * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
-        Ray::new(
-            self.origin + offset,
-            ((self.lower_left_corner + ((self.horizontal * s) + (self.vertical * t)))
-                - self.origin)
-                - offset,
-        )
+    /// Maps the image with `projection` rather than the default perspective.
+    pub fn with_projection(self, projection: Projection) -> Camera {
+        let setup = Setup {
+            projection,
+            ..self.setup
+        };
+        self.rebuild(setup)
+    }
+
+    /// Renders a stereo pair of eyes `eye_separation` apart, laid out in the image by `layout`.
+    /// Perspective eyes converge on the focal plane, the others look in parallel. Panoramic
+    /// eyes are offset across each ray's direction so the pair stays stereo all the way around.
+    pub fn with_stereo(self, eye_separation: f32, layout: StereoLayout) -> Camera {
+        let setup = Setup {
+            stereo: Some((eye_separation, layout)),
+            ..self.setup
+        };
+        self.rebuild(setup)
+    }
+
+    /// Gives the lens opening the shape of `aperture` rather than a circle.
+    pub fn with_aperture(mut self, aperture: Aperture) -> Camera {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Moves the centre of the view `shift_x` view widths right and `shift_y` view heights up
+    /// without turning the camera, like the shift of a tilt-shift lens or an off centre principal
+    /// point.
+    pub fn with_lens_shift(self, shift_x: f32, shift_y: f32) -> Camera {
+        let setup = Setup {
+            shift: (shift_x, shift_y),
+            ..self.setup
+        };
+        self.rebuild(setup)
+    }
+
+    /// Bends perspective rays by `distortion` like a real lens.
+    pub fn with_distortion(self, distortion: Distortion) -> Camera {
+        let setup = Setup {
+            distortion: Some(distortion),
+            ..self.setup
+        };
+        self.rebuild(setup)
+    }
+
+    /// Focuses the lens `focus_dist` along the line of sight instead.
+    pub fn with_focus_distance(self, focus_dist: f32) -> Camera {
+        let setup = Setup {
+            focus_dist,
+            ..self.setup
+        };
+        self.rebuild(setup)
+    }
+
+    fn rebuild(self, setup: Setup) -> Camera {
+        Camera {
+            tracks: self.tracks,
+            aperture: self.aperture,
+            ..Camera::from_setup(setup)
+        }
+    }
+
+    /// Moves the camera over the shutter interval of every frame from where it was built at
+    /// time 0 to `look_from`, looking at `look_at`, at time 1.
+    pub fn with_motion(self, look_from: Vec3A, look_at: Vec3A, v_up: Vec3A) -> Camera {
+        let keys = |start: Vec3A, end: Vec3A| {
+            Track::new(vec![(0.0, start), (1.0, end)], Interpolation::Linear)
+        };
+        let tracks = CameraTracks {
+            look_from: keys(self.setup.look_from, look_from),
+            look_at: keys(self.setup.look_at, look_at),
+            v_up: keys(self.setup.v_up, v_up),
+            v_fov: Track::default(),
+            over_shutter: true,
+        };
+        self.with_tracks(tracks)
+    }
+
+    /// Moves the camera through `tracks`, rebuilding it at the time of each ray.
+    pub fn with_tracks(mut self, tracks: CameraTracks) -> Camera {
+        self.tracks = Some(tracks);
+        self
+    }
+
+    /// The ray through `(s, t)`, from the bottom left of the image at `(0, 0)` to the top right
+    /// at `(1, 1)`, or `None` for the parts of a fisheye image outside its circle.
+    pub fn get_ray(&self, s: f32, t: f32, time: Time) -> Option<Ray> {
+        match &self.tracks {
+            Some(tracks) => {
+                let setup = tracks.setup_at(&self.setup, time);
+                Camera::from_setup(setup).project(&self.aperture, s, t, time)
+            }
+            None => self.project(&self.aperture, s, t, time),
+        }
+    }
+
+    /// The ray through `(s, t)` from the centre of the lens, as a pinhole camera would see it.
+    pub fn centre_ray(&self, s: f32, t: f32, time: Time) -> Option<Ray> {
+        Camera::from_setup(Setup {
+            aperature: 0.0,
+            ..self.setup_at(time)
+        })
+        .project(&Aperture::Circle, s, t, time)
+    }
+
+    /// Where the camera is at `time`.
+    pub fn position(&self, time: Time) -> Vec3A {
+        self.setup_at(time).look_from
+    }
+
+    /// How far `point` is along the line of sight at `time`, the focal distance that brings it
+    /// into focus.
+    pub fn depth_of(&self, point: Vec3A, time: Time) -> f32 {
+        let setup = self.setup_at(time);
+        (point - setup.look_from).dot((setup.look_at - setup.look_from).normalize())
+    }
+
+    /// What the camera is built from at `time`, after any motion.
+    pub fn setup_at(&self, time: Time) -> Setup {
+        match &self.tracks {
+            Some(tracks) => tracks.setup_at(&self.setup, time),
+            None => self.setup,
+        }
+    }
+
+    fn project(&self, aperture: &Aperture, s: f32, t: f32, time: Time) -> Option<Ray> {
+        // Which half of the image the point is in picks the eye, -1 for the left and 1 for the
+        // right, and where it is in that half its point in the eye's image.
+        let (s, t, eye) = match self.setup.stereo {
+            Some((_, StereoLayout::SideBySide)) if s < 0.5 => (2.0 * s, t, -1.0),
+            Some((_, StereoLayout::SideBySide)) => (2.0 * s - 1.0, t, 1.0),
+            Some((_, StereoLayout::TopBottom)) if t >= 0.5 => (s, 2.0 * t - 1.0, -1.0),
+            Some((_, StereoLayout::TopBottom)) => (s, 2.0 * t, 1.0),
+            None => (s, t, 0.0),
+        };
+        let half_separation = self
+            .setup
+            .stereo
+            .map_or(0.0, |(separation, _)| separation / 2.0);
+        let eye_offset = self.u * eye * half_separation;
+
+        match self.setup.projection {
+            Projection::Perspective => {
+                let rd = aperture.sample() * self.lens_radius;
+                let offset = self.u * rd.x + self.v * rd.y + eye_offset;
+                let mut target = (self.lower_left_corner
+                    + ((self.horizontal * s) + (self.vertical * t)))
+                    - self.origin;
+                if let Some(distortion) = &self.setup.distortion {
+                    // The distortion works on image coordinates running right and down.
+                    let depth = self.setup.focus_dist;
+                    let (x, y) = distortion
+                        .undistort(target.dot(self.u) / depth, -target.dot(self.v) / depth);
+                    target = (self.u * x - self.v * y - self.w) * depth;
+                }
+                Some(Ray::new_at(self.origin + offset, target - offset, time))
+            }
+            Projection::Orthographic { .. } => {
+                let rd = aperture.sample() * self.lens_radius;
+                let offset = self.u * rd.x + self.v * rd.y;
+                let origin =
+                    self.lower_left_corner + self.horizontal * s + self.vertical * t + eye_offset;
+                Some(Ray::new_at(
+                    origin + offset,
+                    -self.w * self.setup.focus_dist - offset,
+                    time,
+                ))
+            }
+            Projection::Fisheye { fov, mapping } => {
+                let aspect_ratio = self.horizontal.length() / self.vertical.length();
+                let x = (2.0 * s - 1.0) * aspect_ratio;
+                let y = 2.0 * t - 1.0;
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let half_fov = (fov / 2.0).to_radians();
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (half_fov / 2.0).sin()).asin(),
+                };
+                let across = if r > 0.0 {
+                    (self.u * x + self.v * y) / r
+                } else {
+                    Vec3A::ZERO
+                };
+                Some(Ray::new_at(
+                    self.origin + eye_offset,
+                    -self.w * theta.cos() + across * theta.sin(),
+                    time,
+                ))
+            }
+            Projection::Equirectangular => {
+                let longitude = (s - 0.5) * TAU;
+                let latitude = (t - 0.5) * PI;
+                let level = self.u * longitude.sin() - self.w * longitude.cos();
+                // Right of the level direction, so each eye sits where it would with the head
+                // turned to face it.
+                let right = self.u * longitude.cos() + self.w * longitude.sin();
+                Some(Ray::new_at(
+                    self.origin + right * eye * half_separation,
+                    level * latitude.cos() + self.v * latitude.sin(),
+                    time,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RaytracerScene;
+    use crate::create_image;
+    use crate::tests::{small_scene, temp_path};
+
+    #[test]
+    fn test_camera_projections() {
+        let camera = || {
+            Camera::new(
+                Vec3A::ZERO,
+                Vec3A::new(0.0, 0.0, -1.0),
+                Vec3A::Y,
+                90.0,
+                2.0,
+                0.0,
+                1.0,
+            )
+        };
+        let ray = |camera: &Camera, s: f32, t: f32| camera.get_ray(s, t, Time::default()).unwrap();
+        let close = |a: Vec3A, b: Vec3A| a.abs_diff_eq(b, 1e-5);
+
+        let orthographic = camera().with_projection(Projection::Orthographic { height: 2.0 });
+        let corner = ray(&orthographic, 0.0, 1.0);
+        assert!(close(corner.origin(), Vec3A::new(-2.0, 1.0, 0.0)));
+        assert!(close(corner.direction().normalize(), Vec3A::NEG_Z));
+
+        let fisheye = camera().with_projection(Projection::Fisheye {
+            fov: 180.0,
+            mapping: FisheyeMapping::Equidistant,
+        });
+        assert!(close(ray(&fisheye, 0.5, 0.5).direction(), Vec3A::NEG_Z));
+        assert!(close(ray(&fisheye, 0.5, 1.0).direction(), Vec3A::Y));
+        assert!(close(ray(&fisheye, 0.75, 0.5).direction(), Vec3A::X));
+        assert!(fisheye.get_ray(0.0, 0.0, Time::default()).is_none());
+        let equisolid = camera().with_projection(Projection::Fisheye {
+            fov: 180.0,
+            mapping: FisheyeMapping::Equisolid,
+        });
+        assert!(close(ray(&equisolid, 0.5, 1.0).direction(), Vec3A::Y));
+        let halfway = ray(&equisolid, 0.5, 0.75).direction();
+        assert!(halfway.y > 0.0 && halfway.angle_between(Vec3A::NEG_Z) < FRAC_PI_4);
+
+        let panorama = camera().with_projection(Projection::Equirectangular);
+        assert!(close(ray(&panorama, 0.5, 0.5).direction(), Vec3A::NEG_Z));
+        assert!(close(ray(&panorama, 0.75, 0.5).direction(), Vec3A::X));
+        assert!(close(ray(&panorama, 0.0, 0.5).direction(), Vec3A::Z));
+        assert!(close(ray(&panorama, 0.3, 1.0).direction(), Vec3A::Y));
+
+        let side_by_side = camera().with_stereo(0.2, StereoLayout::SideBySide);
+        let (left, right) = (ray(&side_by_side, 0.25, 0.5), ray(&side_by_side, 0.75, 0.5));
+        assert!(close(left.origin(), Vec3A::new(-0.1, 0.0, 0.0)));
+        assert!(close(right.origin(), Vec3A::new(0.1, 0.0, 0.0)));
+        // Both eyes look at the same point on the focal plane.
+        assert!(close(left.at(1.0), right.at(1.0)));
+        let over_under = camera()
+            .with_projection(Projection::Equirectangular)
+            .with_stereo(0.2, StereoLayout::TopBottom);
+        let behind = ray(&over_under, 0.0, 0.75);
+        assert!(close(behind.origin(), Vec3A::new(0.1, 0.0, 0.0)));
+        assert!(close(behind.direction(), Vec3A::Z));
+
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+        settings.projection = ron::from_str("Fisheye(fov: 200.0)").unwrap();
+        assert_eq!(
+            settings.projection,
+            Projection::Fisheye {
+                fov: 200.0,
+                mapping: FisheyeMapping::Equidistant
+            }
+        );
+        settings.stereo = Some(ron::from_str("(eye_separation: 0.1, layout: TopBottom)").unwrap());
+        let image = create_image(settings.to_ron());
+        assert_eq!(image[0][0], vec![0, 0, 0]);
+        assert_ne!(image[5][15], vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_physical_camera() {
+        let physical: RonPhysicalCamera =
+            ron::from_str("(focal_length: 50.0, f_number: 16.0)").unwrap();
+        assert!((physical.v_fov(1.5) - 26.99).abs() < 0.01);
+        // A wider image crops the top and bottom of the sensor instead.
+        assert!(physical.v_fov(3.0) < physical.v_fov(1.5));
+        assert!((physical.aperture() - 0.003125).abs() < 1e-6);
+        // Sunny 16 gives the exposure of an unscaled render.
+        assert!(physical.exposure().abs() < 1e-3);
+        assert!(physical.shutter().is_none());
+
+        let square = Camera::new(Vec3A::ZERO, Vec3A::NEG_Z, Vec3A::Y, 90.0, 1.0, 2.0, 1.0)
+            .with_aperture(Aperture::new(&ApertureShape::Polygon {
+                blades: 4,
+                rotation: 45.0,
+            }));
+        let origins: Vec<Vec3A> = (0..1000)
+            .map(|_| square.get_ray(0.5, 0.5, Time::default()).unwrap().origin())
+            .collect();
+        let half_side = std::f32::consts::FRAC_1_SQRT_2 + 1e-5;
+        assert!(origins
+            .iter()
+            .all(|o| o.x.abs() <= half_side && o.y.abs() <= half_side));
+        // Points reach into the corners of the square, outside the inscribed circle.
+        assert!(origins.iter().any(|o| o.length() > 0.8));
+
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+        settings.physical_camera = Some(
+            ron::from_str(
+                "(focal_length: 24.0, f_number: 2.8, shutter_speed: 0.02, frame_rate: Some(25.0))",
+            )
+            .unwrap(),
+        );
+        settings.aperture_shape = ron::from_str("Polygon(blades: 6)").unwrap();
+        let shutter = settings.physical_camera.as_ref().unwrap().shutter();
+        assert_eq!(shutter.unwrap().close, 0.5);
+        let image = create_image(settings.to_ron());
+        assert_eq!(image.len(), 20);
+    }
+
+    #[test]
+    fn test_aperture_image() {
+        let path = temp_path("aperture.pfm");
+        let mut pixels = vec![Vec3A::ZERO; 16];
+        pixels[3] = Vec3A::ONE;
+        let image = HdrImage {
+            width: 4,
+            height: 4,
+            pixels,
+        };
+        image.save(&path).unwrap();
+        let aperture = Aperture::new(&ApertureShape::Image { path: path.clone() });
+        std::fs::remove_file(&path).unwrap();
+
+        // Every camera built from the aperture shares the image loaded for it.
+        let shared = aperture.clone();
+        assert!(matches!(
+            (&aperture, &shared),
+            (Aperture::Image(a), Aperture::Image(b)) if Arc::ptr_eq(a, b)
+        ));
+        // Only the top right pixel lets light through.
+        for _ in 0..100 {
+            let point = shared.sample();
+            assert!((0.5..=1.0).contains(&point.x) && (0.5..=1.0).contains(&point.y));
+        }
+    }
+
+    #[test]
+    fn test_aperture_validation() {
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+        settings.aperture_shape = ApertureShape::Polygon {
+            blades: 2,
+            rotation: 0.0,
+        };
+        assert_eq!(
+            RaytracerScene::try_from_ron(&settings.to_ron()).unwrap_err(),
+            "an aperture needs at least 3 blades, not 2"
+        );
     }
 }
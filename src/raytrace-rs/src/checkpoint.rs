@@ -0,0 +1,287 @@
+use crate::camera::Camera;
+use crate::configuration::{RaytracerScene, RonCheckpoint};
+use crate::framebuffer::{Framebuffer, Tile};
+use crate::hittables::Hittables;
+use crate::progressive::{self, Accumulated};
+use glam::Vec3A;
+use indicatif::ProgressBar;
+use std::convert::TryInto;
+use std::fs;
+use std::time::Instant;
+
+const MAGIC: &[u8; 8] = b"RTCKPT2\n";
+
+/// The float accumulation buffer of a partly rendered image along with what it was rendered from.
+pub struct Checkpoint {
+    pub width: usize,
+    pub height: usize,
+    pub scene_hash: u64,
+    /// Seeds of every render summed into this checkpoint.
+    pub seeds: Vec<u64>,
+    pub pixels: Vec<Accumulated>,
+}
+
+/// FNV-1a hash of the scene with every setting that doesn't change the radiance a sample sees
+/// cleared, so renders with different sample counts, seeds, crops, outputs or threading can be
+/// combined.
+pub fn scene_hash(settings: &RaytracerScene) -> u64 {
+    let mut scene = settings.clone();
+    scene.multithreading = false;
+    scene.samples_per_pixel = 0;
+    scene.seed = 0;
+    scene.adaptive = None;
+    scene.tile_size = 0;
+    scene.tile_order = Default::default();
+    scene.checkpoint = None;
+    scene.worker = None;
+    scene.crop = None;
+    scene.denoise = None;
+    scene.output = Default::default();
+    scene.hdr_output = None;
+    scene
+        .to_ron()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or("truncated checkpoint")?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        self.take().map(f32::from_le_bytes)
+    }
+}
+
+impl Checkpoint {
+    pub fn new(settings: &RaytracerScene, accumulated: &Framebuffer<Accumulated>) -> Self {
+        Checkpoint {
+            width: settings.image_width as usize,
+            height: settings.image_height as usize,
+            scene_hash: scene_hash(settings),
+            seeds: vec![settings.seed],
+            pixels: accumulated.pixels().to_vec(),
+        }
+    }
+
+    pub fn to_framebuffer(&self) -> Framebuffer<Accumulated> {
+        Framebuffer::from_pixels(self.width, self.pixels.clone())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(self.width as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.scene_hash.to_le_bytes());
+        bytes.extend_from_slice(&(self.seeds.len() as u32).to_le_bytes());
+        self.seeds
+            .iter()
+            .for_each(|seed| bytes.extend_from_slice(&seed.to_le_bytes()));
+        self.pixels.iter().for_each(|pixel| {
+            bytes.extend_from_slice(&pixel.sum.x.to_le_bytes());
+            bytes.extend_from_slice(&pixel.sum.y.to_le_bytes());
+            bytes.extend_from_slice(&pixel.sum.z.to_le_bytes());
+            bytes.extend_from_slice(&pixel.weight.to_le_bytes());
+            bytes.extend_from_slice(&(pixel.samples as u32).to_le_bytes());
+        });
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Checkpoint, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        match &reader.take::<8>()? {
+            magic if magic == MAGIC => {}
+            b"RTCKPT1\n" => {
+                return Err("checkpoint was saved by an older version of the raytracer".to_string())
+            }
+            _ => return Err("not a raytracer checkpoint".to_string()),
+        }
+        let width = reader.u32()? as usize;
+        let height = reader.u32()? as usize;
+        let scene_hash = reader.u64()?;
+        let seeds = (0..reader.u32()?)
+            .map(|_| reader.u64())
+            .collect::<Result<Vec<_>, _>>()?;
+        let pixels = (0..width * height)
+            .map(|_| {
+                Ok(Accumulated {
+                    sum: Vec3A::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                    weight: reader.f32()?,
+                    samples: reader.u32()? as usize,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Checkpoint {
+            width,
+            height,
+            scene_hash,
+            seeds,
+            pixels,
+        })
+    }
+
+    /// Writes the checkpoint next to `path` and renames it into place, so a crash part way
+    /// through never leaves a corrupt checkpoint behind.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let temporary = format!("{}.tmp", path);
+        fs::write(&temporary, self.to_bytes())
+            .map_err(|e| format!("unable to write {}: {}", path, e))?;
+        fs::rename(&temporary, path).map_err(|e| format!("unable to write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Checkpoint, String> {
+        let bytes = fs::read(path).map_err(|e| format!("unable to read {}: {}", path, e))?;
+        Checkpoint::from_bytes(&bytes).map_err(|e| format!("unable to read {}: {}", path, e))
+    }
+
+    /// Sums checkpoints of the same scene rendered with different seeds into one with the
+    /// samples of all of them.
+    pub fn merge(checkpoints: Vec<Checkpoint>) -> Result<Checkpoint, String> {
+        let mut checkpoints = checkpoints.into_iter();
+        let mut merged = checkpoints.next().ok_or("no checkpoints to merge")?;
+        for checkpoint in checkpoints {
+            if (checkpoint.width, checkpoint.height, checkpoint.scene_hash)
+                != (merged.width, merged.height, merged.scene_hash)
+            {
+                return Err("checkpoints were rendered from different scenes".to_string());
+            }
+            if checkpoint
+                .seeds
+                .iter()
+                .any(|seed| merged.seeds.contains(seed))
+            {
+                return Err("checkpoints share a seed so their samples would repeat".to_string());
+            }
+            merged.seeds.extend(checkpoint.seeds);
+            merged
+                .pixels
+                .iter_mut()
+                .zip(checkpoint.pixels)
+                .for_each(|(pixel, other)| *pixel += other);
+        }
+        Ok(merged)
+    }
+
+    /// Checks this checkpoint can be continued by rendering `settings`.
+    pub fn check_resumable(&self, settings: &RaytracerScene) -> Result<(), String> {
+        if (self.width, self.height)
+            != (
+                settings.image_width as usize,
+                settings.image_height as usize,
+            )
+            || self.scene_hash != scene_hash(settings)
+        {
+            return Err("checkpoint was rendered from a different scene".to_string());
+        }
+        if self.seeds != [settings.seed] {
+            return Err("checkpoint was rendered with a different seed".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Renders the rest of the samples for `accumulated` in passes, saving a checkpoint whenever
+/// `interval` seconds have gone by since the last one and once more at the end.
+pub fn render(
+    settings: &RaytracerScene,
+    checkpoint: &RonCheckpoint,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+    pb: &ProgressBar,
+    mut accumulated: Framebuffer<Accumulated>,
+) -> Framebuffer<Vec3A> {
+    let save = |accumulated: &Framebuffer<Accumulated>| {
+        Checkpoint::new(settings, accumulated)
+            .save(&checkpoint.path)
+            .expect("failed to save checkpoint");
+    };
+    let mut last_save = Instant::now();
+    progressive::render_passes(
+        settings,
+        tiles,
+        camera,
+        world,
+        pb,
+        &mut accumulated,
+        checkpoint.samples_per_pass,
+        |accumulated, _| {
+            if last_save.elapsed().as_secs_f32() >= checkpoint.interval {
+                save(accumulated);
+                last_save = Instant::now();
+            }
+            true
+        },
+    );
+    save(&accumulated);
+
+    progressive::to_colours(&accumulated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{small_scene, temp_path};
+    use crate::{create_image, merge_checkpoints, resume_image};
+
+    #[test]
+    fn test_checkpoint_resume_and_merge() {
+        let checkpointed = |seed: u64, samples: usize, path: &str| {
+            let mut settings = RaytracerScene::from_ron(small_scene(true, seed));
+            settings.samples_per_pixel = samples;
+            settings.checkpoint = Some(RonCheckpoint {
+                path: path.to_string(),
+                interval: 0.0,
+                samples_per_pass: 2,
+            });
+            settings.to_ron()
+        };
+        let (resume, merge, merged) = (
+            temp_path("resume.ckpt"),
+            temp_path("merge.ckpt"),
+            temp_path("merged.ckpt"),
+        );
+
+        create_image(checkpointed(3, 4, &resume));
+        let resumed = resume_image(small_scene(true, 3), &resume);
+        assert_eq!(create_image(small_scene(true, 3)), resumed);
+
+        create_image(checkpointed(4, 8, &merge));
+        let image = merge_checkpoints(&[resume.clone(), merge.clone()], Some(&merged), "()");
+        let checkpoint = Checkpoint::load(&merged).unwrap();
+        assert_eq!(checkpoint.seeds, vec![3, 4]);
+        assert!(checkpoint.pixels.iter().all(|pixel| pixel.samples == 16));
+        assert_eq!(image.len(), 20);
+        assert!(Checkpoint::merge(vec![
+            Checkpoint::load(&resume).unwrap(),
+            Checkpoint::load(&resume).unwrap(),
+        ])
+        .is_err());
+
+        for path in [resume, merge, merged] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::material::Material;
 use crate::random_f32;
+use crate::texture::Texture;
 use glam::Vec3A;
 
 pub struct ColourMap {
@@ -10,6 +12,7 @@ pub struct ColourMap {
 pub struct ColourData {
     pub cutoff: f32,
     pub colour: Vec3A,
+    pub emission: f32,
 }
 
 impl ColourMap {
@@ -22,15 +25,20 @@ impl ColourMap {
         }
     }
 
-    pub fn to_colour(&self, value: f32) -> Vec3A {
+    /// Bands with an emission strength become emissive, e.g. for glowing lava at low heights.
+    pub fn to_material(&self, value: f32) -> Material {
         let val = (random_f32(-self.fuzz, self.fuzz) + value)
             .min(1.0)
             .max(0.0);
         for i in 0..self.colour_vec.len() {
             if val > self.colour_vec[i].cutoff {
-                return self.colour_vec[i].colour;
+                return if self.colour_vec[i].emission > 0.0 {
+                    Material::Emissive(self.colour_vec[i].colour, self.colour_vec[i].emission)
+                } else {
+                    Material::Lambertian(Texture::Constant(self.colour_vec[i].colour))
+                };
             }
         }
-        return self.default_colour;
+        Material::Lambertian(Texture::Constant(self.default_colour))
     }
 }
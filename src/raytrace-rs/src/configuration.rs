@@ -1,8 +1,15 @@
+use crate::animation::Interpolation;
+use crate::camera::{ApertureShape, Distortion, Projection, StereoLayout};
+use crate::filter::Filter;
+use crate::framebuffer::TileOrder;
+use crate::integrator::Integrator;
+use crate::sampler::Sampler;
+use crate::tonemap::{ToneMap, Transfer};
 use ron::from_str;
 use ron::ser::{to_string_pretty, PrettyConfig};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RaytracerScene {
     pub multithreading: bool,
     pub aspect_ratio: f32,
@@ -17,12 +24,75 @@ pub struct RaytracerScene {
     pub camera_dir: Vec<f32>,
     pub camera_up: Vec<f32>,
     pub objects: Vec<RonObject>,
-    pub lights: Vec<Vec<f32>>,
+    pub lights: Vec<RonLight>,
+    #[serde(default)]
     pub has_terrain: usize,
+    #[serde(default)]
     pub terrain: RonTerrain,
+    #[serde(default)]
+    pub integrator: Integrator,
+    #[serde(default)]
+    pub background: RonBackground,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub sampler: Sampler,
+    #[serde(default)]
+    pub adaptive: Option<RonAdaptive>,
+    #[serde(default = "default_tile_size")]
+    pub tile_size: usize,
+    #[serde(default)]
+    pub tile_order: TileOrder,
+    #[serde(default)]
+    pub checkpoint: Option<RonCheckpoint>,
+    #[serde(default)]
+    pub worker: Option<RonWorker>,
+    #[serde(default)]
+    pub crop: Option<RonCrop>,
+    #[serde(default)]
+    pub denoise: Option<RonDenoise>,
+    #[serde(default)]
+    pub output: RonOutput,
+    #[serde(default)]
+    pub hdr_output: Option<RonHdrOutput>,
+    #[serde(default)]
+    pub filter: RonFilter,
+    #[serde(default)]
+    pub shutter: Option<RonShutter>,
+    #[serde(default)]
+    pub camera_end: Option<RonCameraEnd>,
+    #[serde(default)]
+    pub frame: f32,
+    #[serde(default)]
+    pub animation: Option<RonAnimation>,
+    #[serde(default)]
+    pub projection: Projection,
+    #[serde(default)]
+    pub stereo: Option<RonStereo>,
+    #[serde(default)]
+    pub physical_camera: Option<RonPhysicalCamera>,
+    #[serde(default)]
+    pub aperture_shape: ApertureShape,
+    #[serde(default)]
+    pub autofocus: Option<RonAutofocus>,
+    #[serde(default)]
+    pub look_at: Option<RonLookAt>,
+    /// A 3x3 intrinsic matrix in pixels, `[[fx, 0, cx], [0, fy, cy], [0, 0, 1]]`, replacing
+    /// `v_fov` and `aspect_ratio` for the camera.
+    #[serde(default)]
+    pub intrinsics: Option<Vec<Vec<f32>>>,
+    /// A 4x4 world to camera matrix with x right, y down and z forward, replacing `camera_pos`,
+    /// `camera_dir` and `camera_up`.
+    #[serde(default)]
+    pub world_to_camera: Option<Vec<Vec<f32>>>,
+    #[serde(default)]
+    pub distortion: Option<Distortion>,
+    /// Where to save the intrinsics and extrinsics the frame was rendered with as JSON.
+    #[serde(default)]
+    pub camera_output: Option<String>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct RonTerrain {
     pub p1: Vec<f32>,
     pub p2: Vec<f32>,
@@ -36,17 +106,526 @@ pub struct RonTerrain {
     pub fuzz: f32,
     pub map_cutoff: Vec<f32>,
     pub map_value: Vec<Vec<f32>>,
+    #[serde(default)]
+    pub map_emission: Vec<f32>,
     pub erosion_factor: f64,
     pub rain_factor: f64,
     pub drops_per_point: usize,
 }
 
+/// A light as written in the scene file. Bare positions are still accepted so older scenes load
+/// as white point lights without falloff.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum RonLight {
+    Position(Vec<f32>),
+    Typed(RonLightType),
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum RonLightType {
+    Point {
+        position: Vec<f32>,
+        #[serde(default = "white")]
+        colour: Vec<f32>,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "enabled")]
+        falloff: bool,
+    },
+    /// Light arriving from infinitely far away travelling along `direction`, such as the sun.
+    /// A non zero `angular_diameter` in degrees gives the light a disc that casts soft shadows.
+    Directional {
+        direction: Vec<f32>,
+        #[serde(default = "white")]
+        colour: Vec<f32>,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        angular_diameter: f32,
+    },
+    /// Point light restricted to a cone of `cone_angle` degrees around `direction`, fading out
+    /// over the outer `soft_edge` degrees.
+    Spot {
+        position: Vec<f32>,
+        direction: Vec<f32>,
+        cone_angle: f32,
+        #[serde(default)]
+        soft_edge: f32,
+        #[serde(default = "white")]
+        colour: Vec<f32>,
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+}
+
+/// What rays that miss every object see. `Image` wraps an equirectangular Radiance `.hdr` or
+/// `.pfm` file around the scene, rotated by `rotation` degrees about the up axis.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum RonBackground {
+    Solid {
+        colour: Vec<f32>,
+    },
+    Gradient {
+        bottom: Vec<f32>,
+        top: Vec<f32>,
+    },
+    Image {
+        path: String,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default)]
+        rotation: f32,
+    },
+    /// Analytic daylight sky for a sun at `sun_elevation` degrees above the horizon and
+    /// `sun_azimuth` degrees clockwise from +z towards +x, or placed from `time_of_day` when
+    /// given. Unless `sun` is false the sun is also added as a directional light.
+    Sky {
+        #[serde(default = "default_sun_elevation")]
+        sun_elevation: f32,
+        #[serde(default)]
+        sun_azimuth: f32,
+        #[serde(default = "default_turbidity")]
+        turbidity: f32,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "enabled")]
+        sun: bool,
+        #[serde(default = "default_sun_diameter")]
+        sun_angular_diameter: f32,
+        #[serde(default)]
+        time_of_day: Option<RonTimeOfDay>,
+    },
+}
+
+/// A place and local time to derive the sun position from, with `hour` as a decimal 24 hour
+/// clock in a timezone `utc_offset` hours ahead of UTC.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonTimeOfDay {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: f32,
+    #[serde(default)]
+    pub utc_offset: f32,
+}
+
+/// Renders in passes of `min_samples`, only revisiting pixels whose estimated error is above
+/// `threshold` until they reach `max_samples`. Replaces `samples_per_pixel` when set. The error
+/// is the standard error of the pixel's displayed brightness on a 0 to 1 scale. `sample_map`
+/// names a 16 bit PGM file to write the number of samples each pixel took to.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonAdaptive {
+    pub min_samples: usize,
+    pub max_samples: usize,
+    pub threshold: f32,
+    #[serde(default)]
+    pub sample_map: Option<String>,
+}
+
+/// Renders in passes of `samples_per_pass`, saving the float accumulation buffer to `path`
+/// whenever `interval` seconds have passed since the last save so the render can be resumed.
+/// Adaptive renders can not be checkpointed.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonCheckpoint {
+    pub path: String,
+    #[serde(default = "default_checkpoint_interval")]
+    pub interval: f32,
+    #[serde(default = "default_samples_per_pass")]
+    pub samples_per_pass: usize,
+}
+
+/// Renders every `count`th tile starting from tile `index`, so `count` workers given the indices
+/// 0 to `count - 1` cover the image between them. The partial float buffer is saved to `output`
+/// to be assembled with the others. Workers can not render adaptively or checkpoint.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonWorker {
+    pub index: usize,
+    pub count: usize,
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+/// A region of the image to render, given in pixels from the top left or as fractions of the
+/// image size when `normalized`. The camera still frames the whole image. Only the region is
+/// returned unless `full_frame` is set, which returns the whole image with the rest black.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonCrop {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub normalized: bool,
+    #[serde(default)]
+    pub full_frame: bool,
+}
+
+/// Runs the edge-avoiding denoiser over the finished image for `iterations` passes, each twice
+/// as wide as the last. The sigmas set how much the illumination, normals and relative depth of
+/// two pixels may differ before they stop being blurred together.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonDenoise {
+    #[serde(default = "default_denoise_iterations")]
+    pub iterations: usize,
+    #[serde(default = "default_colour_sigma")]
+    pub colour_sigma: f32,
+    #[serde(default = "default_normal_sigma")]
+    pub normal_sigma: f32,
+    #[serde(default = "default_depth_sigma")]
+    pub depth_sigma: f32,
+}
+
+/// How the mean radiance of each pixel becomes 8 bit colour. The radiance is scaled by
+/// `exposure` stops, brought into range by `tone_map` and encoded with `transfer`. `dither` adds
+/// up to one level of triangular noise before rounding, hiding banding in smooth gradients.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct RonOutput {
+    #[serde(default)]
+    pub exposure: f32,
+    #[serde(default)]
+    pub tone_map: ToneMap,
+    #[serde(default)]
+    pub transfer: Transfer,
+    #[serde(default)]
+    pub dither: bool,
+}
+
+/// The reconstruction filter samples are splatted into the pixels around them with, reaching
+/// `radius` pixels from each sample or the filter's usual radius when none is given.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RonFilter {
+    #[serde(default)]
+    pub kind: Filter,
+    #[serde(default)]
+    pub radius: Option<f32>,
+}
+
+/// A file to save the linear radiance of the finished render to, before exposure and tone
+/// mapping. The format comes from the extension of `path`, `.exr`, `.pfm` or `.hdr`. With `aovs`
+/// an OpenEXR file also gets `albedo`, `normal` and `depth` layers.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonHdrOutput {
+    pub path: String,
+    #[serde(default)]
+    pub aovs: bool,
+}
+
+/// Spreads each pixel's samples over times from `open` to `close` after the scene's frame,
+/// blurring whatever moves in between. Moving objects and cameras are at the start of their
+/// motion at time 0 and the end at time 1. Without a shutter every ray is traced at the frame.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RonShutter {
+    #[serde(default)]
+    pub open: f32,
+    #[serde(default = "one")]
+    pub close: f32,
+}
+
+/// A camera given by the settings of a real one, a lens of `focal_length` millimetres at
+/// `f_number` on a sensor `sensor_width` by `sensor_height` millimetres, with scene units of
+/// `metres_per_unit` metres. It replaces the scene's `v_fov` and `aperture` and adds to the
+/// output's exposure, which is unchanged by f/16 at 1/100s and ISO 100. Given a `frame_rate` the
+/// `shutter_speed` in seconds also blurs motion when the scene has no shutter of its own.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RonPhysicalCamera {
+    pub focal_length: f32,
+    pub f_number: f32,
+    #[serde(default = "default_sensor_width")]
+    pub sensor_width: f32,
+    #[serde(default = "default_sensor_height")]
+    pub sensor_height: f32,
+    #[serde(default = "default_shutter_speed")]
+    pub shutter_speed: f32,
+    #[serde(default = "default_iso")]
+    pub iso: f32,
+    #[serde(default = "one")]
+    pub metres_per_unit: f32,
+    #[serde(default)]
+    pub frame_rate: Option<f32>,
+}
+
+/// Sets the focal distance from what the camera sees once the scene is built, replacing
+/// `focal_distance`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RonAutofocus {
+    /// Focuses on the first surface seen through the point `x`, `y` of the image, from 0 at the
+    /// left and top to 1 at the right and bottom.
+    Screen { x: f32, y: f32 },
+    /// Focuses on the objects called `name`, where a ray from the camera towards the middle of
+    /// their bounds first meets them.
+    Object { name: String },
+}
+
+/// Points the camera at the middle of the bounds of some of the scene and moves it along its
+/// line of sight until they fill the view. Without autofocus the focal distance becomes the
+/// distance to that middle.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RonLookAt {
+    /// Frames every object in the scene, including the terrain.
+    Scene,
+    /// Frames the objects called `name`.
+    Object { name: String },
+}
+
+/// Renders both eyes of a stereo pair into one image, `eye_separation` apart in scene units.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RonStereo {
+    pub eye_separation: f32,
+    #[serde(default)]
+    pub layout: StereoLayout,
+}
+
+/// Where the camera has moved to by time 1 of the shutter, given like the scene's own camera
+/// which is where it starts at time 0, the same in every frame.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonCameraEnd {
+    pub camera_pos: Vec<f32>,
+    pub camera_dir: Vec<f32>,
+    pub camera_up: Vec<f32>,
+}
+
+/// Keyframes of the camera and of the positions of lights, given as `(frame, value)` pairs.
+/// Frame `n` of an animation is at time `n`, so moving things are blurred over the shutter
+/// interval after each frame. Values hold still before their first key and after their last.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonAnimation {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub camera: RonCameraKeys,
+    #[serde(default)]
+    pub lights: Vec<RonLightKeys>,
+}
+
+/// Keyframes of the camera's position, the point it looks at, its up direction and vertical
+/// field of view. Anything left unkeyed stays as the scene's camera has it.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct RonCameraKeys {
+    #[serde(default)]
+    pub camera_pos: Vec<(f32, Vec<f32>)>,
+    #[serde(default)]
+    pub camera_dir: Vec<(f32, Vec<f32>)>,
+    #[serde(default)]
+    pub camera_up: Vec<(f32, Vec<f32>)>,
+    #[serde(default)]
+    pub v_fov: Vec<(f32, f32)>,
+}
+
+/// Keyframes of the position of the scene's `light`th light. Lights move from frame to frame but
+/// hold still within one.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonLightKeys {
+    pub light: usize,
+    pub position: Vec<(f32, Vec<f32>)>,
+}
+
+/// Keyframes of an object's transform as `(frame, value)` pairs, applied to the object as it is
+/// given in the scene like a `RonMotion`. Rotations are degrees about the x, y and z axes, which
+/// are interpolated separately so a key can turn the object more than once.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonKeyframes {
+    #[serde(default = "origin")]
+    pub pivot: Vec<f32>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub translation: Vec<(f32, Vec<f32>)>,
+    #[serde(default)]
+    pub rotation: Vec<(f32, Vec<f32>)>,
+    #[serde(default)]
+    pub scale: Vec<(f32, f32)>,
+}
+
+/// Moves an object from `start` at time 0 to `end` at time 1 of the shutter, the same in every
+/// frame. Both transforms are applied to the object as it is given in the scene, scaling and
+/// rotating it about `pivot` before translating it.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonMotion {
+    #[serde(default = "origin")]
+    pub pivot: Vec<f32>,
+    #[serde(default)]
+    pub start: RonTransform,
+    pub end: RonTransform,
+}
+
+/// A `translation`, a `rotation` of degrees about the x, y and z axes in turn and a uniform
+/// `scale`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RonTransform {
+    #[serde(default = "origin")]
+    pub translation: Vec<f32>,
+    #[serde(default = "origin")]
+    pub rotation: Vec<f32>,
+    #[serde(default = "one")]
+    pub scale: f32,
+}
+
+impl Default for RonTransform {
+    fn default() -> Self {
+        RonTransform {
+            translation: origin(),
+            rotation: origin(),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Default for RonBackground {
+    fn default() -> Self {
+        RonBackground::Gradient {
+            bottom: vec![1.0, 1.0, 1.0],
+            top: vec![0.68, 0.8, 1.0],
+        }
+    }
+}
+
+fn white() -> Vec<f32> {
+    vec![1.0, 1.0, 1.0]
+}
+
+fn origin() -> Vec<f32> {
+    vec![0.0, 0.0, 0.0]
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_sun_elevation() -> f32 {
+    45.0
+}
+
+fn default_turbidity() -> f32 {
+    3.0
+}
+
+fn default_sun_diameter() -> f32 {
+    0.53
+}
+
+fn default_tile_size() -> usize {
+    32
+}
+
+fn default_checkpoint_interval() -> f32 {
+    300.0
+}
+
+fn default_samples_per_pass() -> usize {
+    4
+}
+
+// A full frame 35mm sensor.
+fn default_sensor_width() -> f32 {
+    36.0
+}
+
+fn default_sensor_height() -> f32 {
+    24.0
+}
+
+fn default_shutter_speed() -> f32 {
+    0.01
+}
+
+fn default_iso() -> f32 {
+    100.0
+}
+
+fn default_denoise_iterations() -> usize {
+    3
+}
+
+fn default_colour_sigma() -> f32 {
+    0.5
+}
+
+fn default_normal_sigma() -> f32 {
+    0.3
+}
+
+fn default_depth_sigma() -> f32 {
+    0.03
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct RonObject {
     pub objtype: String,
     pub vectors: Vec<Vec<f32>>,
     pub scalars: Vec<f32>,
     pub material: Vec<String>,
+    #[serde(default)]
+    pub motion: Option<RonMotion>,
+    #[serde(default)]
+    pub keyframes: Option<RonKeyframes>,
+    /// Lets autofocus and `look_at` find the object. Several objects can share a name.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Replaces the colour of a `Lambertian` or `Metal` material.
+    #[serde(default)]
+    pub texture: Option<RonTexture>,
+    /// The `[u, v]` texture coordinates of a triangle's three points, which otherwise run from
+    /// `[0, 0]` at the first point to `[1, 0]` at the second and `[0, 1]` at the third.
+    #[serde(default)]
+    pub uvs: Option<Vec<Vec<f32>>>,
+}
+
+/// A colour that varies over an object's surface. `Checker` alternates `even` and `odd` squares,
+/// `scale` of them along each texture coordinate, and `Image` wraps a PNG or JPEG file once over
+/// the texture coordinates. `Noise` is a solid texture of the point on the object, blending from
+/// the first of its two `colours` to the second through a `pattern` of Perlin noise `scale`
+/// times finer than the scene's units.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RonTexture {
+    Constant {
+        colour: Vec<f32>,
+    },
+    Checker {
+        even: Vec<f32>,
+        odd: Vec<f32>,
+        #[serde(default = "default_checker_scale")]
+        scale: f32,
+    },
+    Image {
+        path: String,
+    },
+    Noise {
+        pattern: NoisePattern,
+        colours: Vec<Vec<f32>>,
+        #[serde(default = "one")]
+        scale: f32,
+        #[serde(default = "default_noise_octaves")]
+        octaves: usize,
+        #[serde(default)]
+        seed: u32,
+    },
+}
+
+fn default_checker_scale() -> f32 {
+    8.0
+}
+
+fn default_noise_octaves() -> usize {
+    7
+}
+
+/// How a `Noise` texture turns turbulence, the sum of `octaves` of Perlin noise each twice as
+/// fine and half as strong as the last, into a blend between its colours.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum NoisePattern {
+    /// The turbulence itself, cloudy and smoky.
+    Turbulence,
+    /// Veins of the second colour through the first, running across the x axis.
+    Marble,
+    /// Rings of the second colour around the y axis.
+    Wood,
 }
 
 #[allow(dead_code)]
@@ -58,6 +637,49 @@ impl RaytracerScene {
     }
 
     pub fn from_ron(ron_string: String) -> RaytracerScene {
-        from_str(&ron_string).expect("deserialization failed")
+        RaytracerScene::try_from_ron(&ron_string).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Parses a scene, rejecting settings that can not be used together.
+    pub fn try_from_ron(ron_string: &str) -> Result<RaytracerScene, String> {
+        let scene: RaytracerScene =
+            from_str(ron_string).map_err(|e| format!("deserialization failed: {}", e))?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for obj in &self.objects {
+            let material = obj.material.first().map_or("", String::as_str);
+            if obj.texture.is_some() && material != "Lambertian" && material != "Metal" {
+                return Err(format!(
+                    "only Lambertian and Metal materials can have a texture, not {}",
+                    material
+                ));
+            }
+        }
+        if self.adaptive.is_some() && self.checkpoint.is_some() {
+            return Err("adaptive renders can not be checkpointed".to_string());
+        }
+        if let ApertureShape::Polygon { blades, .. } = self.aperture_shape {
+            if blades < 3 {
+                return Err(format!(
+                    "an aperture needs at least 3 blades, not {}",
+                    blades
+                ));
+            }
+        }
+        if let Some(worker) = &self.worker {
+            if worker.index >= worker.count {
+                return Err(format!(
+                    "worker {} is not one of the {} workers",
+                    worker.index, worker.count
+                ));
+            }
+            if self.adaptive.is_some() || self.checkpoint.is_some() {
+                return Err("a worker can not render adaptively or checkpoint".to_string());
+            }
+        }
+        Ok(())
     }
 }
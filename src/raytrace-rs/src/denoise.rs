@@ -0,0 +1,209 @@
+use crate::configuration::RonDenoise;
+use crate::environment::luminance;
+use crate::hittables::Hittables;
+use crate::material::{sample_bsdf, Material};
+use crate::ray::Ray;
+use glam::Vec3A;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+// B3 spline weights of the 5x5 a-trous kernel.
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+const MIN_ALBEDO: f32 = 0.01;
+const MAX_SPECULAR_BOUNCES: usize = 4;
+
+/// What a camera ray sees, averaged over a pixel's samples to guide the denoiser. A depth of zero
+/// means the ray escaped to the environment.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Features {
+    pub albedo: Vec3A,
+    pub normal: Vec3A,
+    pub depth: f32,
+    pub samples: usize,
+}
+
+impl Features {
+    /// Follows `ray` through mirrors, glass and metal to the first diffuse or emissive surface,
+    /// so reflections and refractions get the edges of what they show rather than one flat
+    /// albedo across the whole object.
+    pub fn add(&mut self, ray: Ray, world: &Hittables) {
+        let mut ray = ray;
+        let mut throughput = Vec3A::ONE;
+        let mut depth = 0.0;
+        let mut normal = Vec3A::ZERO;
+        let mut albedo = Vec3A::ONE;
+        for bounce in 0..=MAX_SPECULAR_BOUNCES {
+            let rec = match world.hit(ray, 0.001, f32::INFINITY) {
+                Some(rec) => rec,
+                None => {
+                    albedo = world.environment.radiance(ray.direction().normalize());
+                    break;
+                }
+            };
+            depth += rec.t * ray.direction().length();
+            if bounce == 0 {
+                normal = rec.normal;
+            }
+            match rec.material {
+                Material::Lambertian(texture) => {
+                    albedo = texture.value(&rec, &world.textures);
+                    normal = rec.normal;
+                    break;
+                }
+                Material::Emissive(colour, _) => {
+                    albedo = colour;
+                    normal = rec.normal;
+                    break;
+                }
+                _ => match sample_bsdf(ray, rec, &rec.material, &world.textures) {
+                    Some(sample) => {
+                        throughput *= sample.weight;
+                        ray = sample.ray;
+                    }
+                    None => break,
+                },
+            }
+        }
+        self.albedo += throughput * albedo;
+        self.normal += normal;
+        self.depth += depth;
+        self.samples += 1;
+    }
+
+    /// The mean of the features added so far, with the normal renormalised.
+    pub fn resolve(&self) -> Features {
+        if self.samples == 0 {
+            return *self;
+        }
+        let samples = self.samples as f32;
+        Features {
+            albedo: self.albedo / samples,
+            normal: self.normal.normalize_or_zero(),
+            depth: self.depth / samples,
+            samples: self.samples,
+        }
+    }
+}
+
+/// How alike the geometry under two pixels is, from 0 for unrelated surfaces to 1.
+fn geometry_weight(a: &Features, b: &Features, settings: &RonDenoise) -> f32 {
+    match (a.depth > 0.0, b.depth > 0.0) {
+        (false, false) => 1.0,
+        (true, true) => {
+            let normal = (1.0 - a.normal.dot(b.normal).max(0.0)) / settings.normal_sigma.powi(2);
+            let depth = (a.depth - b.depth).abs() / (settings.depth_sigma * a.depth.max(b.depth));
+            (-normal - depth).exp()
+        }
+        _ => 0.0,
+    }
+}
+
+/// Edge-avoiding a-trous wavelet filter (Dammertz et al. 2010). The colour is divided by the
+/// albedo so texture and material detail is kept out of the blur, then smoothed over a kernel
+/// that doubles its spacing each iteration, with weights that fall off across changes in
+/// illumination, normal and depth. The colour tolerance halves every iteration as the noise
+/// left in the image drops.
+pub fn denoise(
+    colours: &[Vec3A],
+    features: &[Features],
+    width: usize,
+    settings: &RonDenoise,
+) -> Vec<Vec3A> {
+    let height = colours.len() / width.max(1);
+    let albedo = |f: &Features| f.albedo.max(Vec3A::splat(MIN_ALBEDO));
+    let mut illumination: Vec<Vec3A> = colours
+        .iter()
+        .zip(features)
+        .map(|(colour, f)| *colour / albedo(f))
+        .collect();
+
+    for iteration in 0..settings.iterations {
+        let step = 1 << iteration;
+        let colour_variance = settings.colour_sigma.powi(2) / (1 << iteration) as f32;
+        let source = &illumination;
+        illumination = (0..width * height)
+            .into_par_iter()
+            .map(|centre| {
+                let (x, y) = (centre % width, centre / width);
+                let centre_luminance = luminance(source[centre]);
+                let mut sum = Vec3A::ZERO;
+                let mut total_weight = 0.0;
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    let sy = y as isize + (j as isize - 2) * step;
+                    if sy < 0 || sy >= height as isize {
+                        continue;
+                    }
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        let sx = x as isize + (i as isize - 2) * step;
+                        if sx < 0 || sx >= width as isize {
+                            continue;
+                        }
+                        let sample = sy as usize * width + sx as usize;
+                        let difference = luminance(source[sample]) - centre_luminance;
+                        let weight = kx
+                            * ky
+                            * (-difference * difference / colour_variance).exp()
+                            * geometry_weight(&features[centre], &features[sample], settings);
+                        sum += source[sample] * weight;
+                        total_weight += weight;
+                    }
+                }
+                if total_weight > 0.0 {
+                    sum / total_weight
+                } else {
+                    source[centre]
+                }
+            })
+            .collect();
+    }
+
+    illumination
+        .iter()
+        .zip(features)
+        .map(|(light, f)| *light * albedo(f))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::RaytracerScene;
+    use crate::tests::small_scene;
+    use crate::tonemap::Transfer;
+    use crate::{create_features, create_image, denoise_image};
+
+    #[test]
+    fn test_denoiser() {
+        let error = |a: &Vec<Vec<Vec<u8>>>, b: &Vec<Vec<Vec<u8>>>| {
+            a.iter()
+                .flatten()
+                .flatten()
+                .zip(b.iter().flatten().flatten())
+                .map(|(x, y)| (*x as f32 - *y as f32).powi(2))
+                .sum::<f32>()
+        };
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 1));
+        settings.samples_per_pixel = 256;
+        let reference = create_image(settings.to_ron());
+        let noisy = create_image(small_scene(true, 1));
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 1));
+        settings.denoise = Some(ron::from_str("()").unwrap());
+        let denoised = create_image(settings.to_ron());
+        assert!(error(&denoised, &reference) < error(&noisy, &reference));
+
+        let (albedo, normal, depth) = create_features(small_scene(true, 1));
+        let denoised = denoise_image(&noisy, &albedo, &normal, &depth, "()", "()");
+        assert!(error(&denoised, &reference) < error(&noisy, &reference));
+
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 1));
+        settings.output.transfer = Transfer::Srgb;
+        let srgb = create_image(settings.to_ron());
+        let unchanged = denoise_image(
+            &srgb,
+            &albedo,
+            &normal,
+            &depth,
+            "(iterations: 0)",
+            "(transfer: Srgb)",
+        );
+        assert_eq!(unchanged, srgb);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::camera::Camera;
+use crate::checkpoint::Checkpoint;
+use crate::configuration::{RaytracerScene, RonWorker};
+use crate::crop_window;
+use crate::framebuffer::{Framebuffer, Tile};
+use crate::hittables::Hittables;
+use crate::progressive::{self, Accumulated};
+use indicatif::ProgressBar;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Every `count`th tile from `index`. Interleaving rather than splitting the image into bands
+/// keeps the expensive centre tiles of a spiral order spread across all the workers.
+pub fn assigned_tiles(tiles: &[Tile], worker: &RonWorker) -> Vec<Tile> {
+    tiles
+        .iter()
+        .skip(worker.index)
+        .step_by(worker.count.max(1))
+        .copied()
+        .collect()
+}
+
+/// Renders the tiles assigned to `worker` into a partial buffer, leaving every other pixel
+/// without samples of its own. Pixels next to the tiles can still have samples splatted into
+/// them by the filter.
+pub fn render(
+    settings: &RaytracerScene,
+    worker: &RonWorker,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+    pb: &ProgressBar,
+) -> Checkpoint {
+    let tiles = assigned_tiles(tiles, worker);
+    let mut partial = Framebuffer::new(
+        settings.image_width as usize,
+        settings.image_height as usize,
+        Accumulated::default(),
+    );
+    pb.set_length(tiles.len() as u64);
+    partial.render_splatted(
+        &tiles,
+        settings.filter.margin(),
+        crop_window(settings),
+        settings.multithreading,
+        pb,
+        |x, y, splat| {
+            progressive::splat_samples(
+                settings,
+                (x, y),
+                0..settings.samples_per_pixel,
+                settings.samples_per_pixel,
+                camera,
+                world,
+                splat,
+            )
+        },
+    );
+
+    Checkpoint::new(settings, &partial)
+}
+
+/// Combines the partial buffers of workers that rendered the same scene and seed into the whole
+/// image, checking no pixel was rendered twice.
+pub fn assemble(partials: Vec<Checkpoint>) -> Result<Checkpoint, String> {
+    let mut partials = partials.into_iter();
+    let mut assembled = partials.next().ok_or("no partial renders to assemble")?;
+    for partial in partials {
+        if (
+            partial.width,
+            partial.height,
+            partial.scene_hash,
+            &partial.seeds,
+        ) != (
+            assembled.width,
+            assembled.height,
+            assembled.scene_hash,
+            &assembled.seeds,
+        ) {
+            return Err("partial renders were rendered from different scenes".to_string());
+        }
+        for (pixel, other) in assembled.pixels.iter_mut().zip(partial.pixels) {
+            if pixel.samples > 0 && other.samples > 0 {
+                return Err("partial renders overlap".to_string());
+            }
+            *pixel += other;
+        }
+    }
+    Ok(assembled)
+}
+
+/// Spawns `workers` copies of `command`, writing each the scene with its share of the tiles on
+/// stdin and reading its partial buffer back from stdout.
+pub fn coordinate(
+    settings: &RaytracerScene,
+    workers: usize,
+    command: &[String],
+) -> Result<Vec<Checkpoint>, String> {
+    let (program, args) = command.split_first().ok_or("no worker command given")?;
+    let children = (0..workers)
+        .map(|index| {
+            let mut scene = settings.clone();
+            scene.worker = Some(RonWorker {
+                index,
+                count: workers,
+                output: None,
+            });
+            let mut child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("unable to start worker {}: {}", program, e))?;
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(scene.to_ron().as_bytes())
+                .map_err(|e| format!("unable to send scene to worker {}: {}", index, e))?;
+            Ok(child)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    children
+        .into_iter()
+        .enumerate()
+        .map(|(index, child)| {
+            let output = child
+                .wait_with_output()
+                .map_err(|e| format!("worker {} failed: {}", index, e))?;
+            if !output.status.success() {
+                return Err(format!("worker {} failed: {}", index, output.status));
+            }
+            Checkpoint::from_bytes(&output.stdout)
+                .map_err(|e| format!("worker {} sent an invalid partial render: {}", index, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::Checkpoint;
+    use crate::configuration::{RaytracerScene, RonWorker};
+    use crate::tests::{small_scene, temp_path};
+    use crate::{assemble_partials, create_image, create_partial};
+
+    #[test]
+    fn test_partial_renders_assemble() {
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 5));
+        let partials = (0..3)
+            .map(|index| {
+                let mut worker = settings.clone();
+                worker.tile_size = 8;
+                worker.worker = Some(RonWorker {
+                    index,
+                    count: 3,
+                    output: None,
+                });
+                Checkpoint::from_bytes(&create_partial(worker.to_ron())).unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(partials
+            .iter()
+            .all(|partial| partial.pixels.iter().any(|pixel| pixel.samples == 0)));
+        let paths = partials
+            .iter()
+            .enumerate()
+            .map(|(index, partial)| {
+                let path = temp_path(&format!("partial_{}.ckpt", index));
+                partial.save(&path).unwrap();
+                path
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            create_image(small_scene(true, 5)),
+            assemble_partials(&paths, "()")
+        );
+        let output = "(exposure: 0.5, tone_map: Aces, transfer: Srgb, dither: true)";
+        settings.output = ron::from_str(output).unwrap();
+        assert_eq!(
+            create_image(settings.to_ron()),
+            assemble_partials(&paths, output)
+        );
+        for path in paths {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_worker_validation() {
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+        let check = |settings: &RaytracerScene| RaytracerScene::try_from_ron(&settings.to_ron());
+        settings.worker = Some(ron::from_str("(index: 2, count: 2)").unwrap());
+        assert_eq!(
+            check(&settings).unwrap_err(),
+            "worker 2 is not one of the 2 workers"
+        );
+
+        settings.worker = Some(ron::from_str("(index: 1, count: 2)").unwrap());
+        assert!(check(&settings).is_ok());
+        settings.adaptive =
+            Some(ron::from_str("(min_samples: 4, max_samples: 32, threshold: 0.01)").unwrap());
+        assert_eq!(
+            check(&settings).unwrap_err(),
+            "a worker can not render adaptively or checkpoint"
+        );
+        settings.adaptive = None;
+        settings.checkpoint = Some(ron::from_str("(path: \"unused.ckpt\")").unwrap());
+        assert_eq!(
+            check(&settings).unwrap_err(),
+            "a worker can not render adaptively or checkpoint"
+        );
+    }
+}
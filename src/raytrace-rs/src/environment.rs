@@ -0,0 +1,317 @@
+use crate::configuration::RonBackground;
+use crate::hdr::HdrImage;
+use crate::hittables::{directional_light, Light};
+use crate::sampler;
+use crate::sky::{solar_position, sun_direction, PreethamSky};
+use crate::{conv_py_vec, random_on_unit_sphere};
+use glam::Vec3A;
+use std::f32::consts::PI;
+
+/// Radiance arriving from infinitely far away along rays that miss every object.
+pub enum Environment {
+    Solid(Vec3A),
+    Gradient {
+        bottom: Vec3A,
+        top: Vec3A,
+    },
+    Map(EnvironmentMap),
+    Sky {
+        sky: PreethamSky,
+        sun: Option<Light>,
+    },
+}
+
+/// An equirectangular HDR image wrapped around the scene with +y up, importance sampled by
+/// luminance so bright features such as the sun in a studio HDRI act as real light sources.
+pub struct EnvironmentMap {
+    image: HdrImage,
+    intensity: f32,
+    rotation: f32,
+    weights: Vec<f32>,
+    total_weight: f32,
+    marginal_cdf: Vec<f32>,
+    conditional_cdf: Vec<f32>,
+}
+
+fn uniform_sphere_pdf() -> f32 {
+    1.0 / (4.0 * PI)
+}
+
+pub fn luminance(colour: Vec3A) -> f32 {
+    colour.dot(Vec3A::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Index of the first entry in a cumulative distribution greater than `u`, along with where `u`
+/// falls within that entry so the same number can also place the sample inside it.
+pub fn search_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    let idx = cdf.partition_point(|c| *c <= u).min(cdf.len() - 1);
+    let start = if idx == 0 { 0.0 } else { cdf[idx - 1] };
+    let width = cdf[idx] - start;
+    let offset = if width > 0.0 {
+        ((u - start) / width).clamp(0.0, 0.9999)
+    } else {
+        0.5
+    };
+    (idx, offset)
+}
+
+impl Environment {
+    pub fn new(background: &RonBackground) -> Self {
+        match background {
+            RonBackground::Solid { colour } => Environment::Solid(conv_py_vec(colour.clone())),
+            RonBackground::Gradient { bottom, top } => Environment::Gradient {
+                bottom: conv_py_vec(bottom.clone()),
+                top: conv_py_vec(top.clone()),
+            },
+            RonBackground::Image {
+                path,
+                intensity,
+                rotation,
+            } => Environment::Map(EnvironmentMap::new(
+                HdrImage::load(path).expect("failed to load environment map"),
+                *intensity,
+                rotation.to_radians(),
+            )),
+            RonBackground::Sky {
+                sun_elevation,
+                sun_azimuth,
+                turbidity,
+                intensity,
+                sun,
+                sun_angular_diameter,
+                time_of_day,
+            } => {
+                let (elevation, azimuth) = match time_of_day {
+                    Some(time) => solar_position(time),
+                    None => (*sun_elevation, *sun_azimuth),
+                };
+                let direction = sun_direction(elevation, azimuth);
+                Environment::Sky {
+                    sky: PreethamSky::new(direction, *turbidity, *intensity),
+                    sun: if *sun && elevation > 0.0 {
+                        Some(directional_light(
+                            -direction,
+                            PreethamSky::sun_irradiance(direction, *turbidity, *intensity),
+                            *sun_angular_diameter,
+                        ))
+                    } else {
+                        None
+                    },
+                }
+            }
+        }
+    }
+
+    /// The directional light standing in for the sun of a sky background.
+    pub fn sun(&self) -> Option<Light> {
+        match self {
+            Environment::Sky { sun, .. } => *sun,
+            _ => None,
+        }
+    }
+
+    pub fn radiance(&self, direction: Vec3A) -> Vec3A {
+        match self {
+            Environment::Solid(colour) => *colour,
+            Environment::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.normalize().y + 1.0);
+                *bottom * (1.0 - t) + *top * t
+            }
+            Environment::Map(map) => map.radiance(direction),
+            Environment::Sky { sky, .. } => sky.radiance(direction),
+        }
+    }
+
+    /// Samples a direction towards the environment returning it with its solid angle pdf.
+    pub fn sample(&self) -> (Vec3A, f32) {
+        match self {
+            Environment::Map(map) => map.sample(),
+            _ => (random_on_unit_sphere(), uniform_sphere_pdf()),
+        }
+    }
+
+    pub fn pdf(&self, direction: Vec3A) -> f32 {
+        match self {
+            Environment::Map(map) => map.pdf(direction),
+            _ => uniform_sphere_pdf(),
+        }
+    }
+}
+
+impl EnvironmentMap {
+    pub fn new(image: HdrImage, intensity: f32, rotation: f32) -> Self {
+        let (width, height) = (image.width, image.height);
+        let mut weights = vec![0.0; width * height];
+        let mut conditional_cdf = vec![0.0; width * height];
+        let mut marginal_cdf = vec![0.0; height];
+        let mut total_weight = 0.0;
+        for y in 0..height {
+            // Rows near the poles cover less of the sphere so are weighted down by sin(theta).
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            let mut row_weight = 0.0;
+            for x in 0..width {
+                let weight = luminance(image.get(x, y)).max(0.0) * sin_theta;
+                weights[y * width + x] = weight;
+                row_weight += weight;
+                conditional_cdf[y * width + x] = row_weight;
+            }
+            if row_weight > 0.0 {
+                conditional_cdf[y * width..(y + 1) * width]
+                    .iter_mut()
+                    .for_each(|c| *c /= row_weight);
+            }
+            total_weight += row_weight;
+            marginal_cdf[y] = total_weight;
+        }
+        if total_weight > 0.0 {
+            marginal_cdf.iter_mut().for_each(|c| *c /= total_weight);
+        }
+
+        EnvironmentMap {
+            image,
+            intensity,
+            rotation,
+            weights,
+            total_weight,
+            marginal_cdf,
+            conditional_cdf,
+        }
+    }
+
+    fn to_pixel(&self, direction: Vec3A) -> (usize, usize, f32) {
+        let d = direction.normalize();
+        let phi = d.z.atan2(d.x) + self.rotation;
+        let u = (phi / (2.0 * PI)).rem_euclid(1.0);
+        // Unlike the arc cosine of `d.y` this stays accurate next to the poles.
+        let sin_theta = d.x.hypot(d.z);
+        let theta = sin_theta.atan2(d.y);
+        let v = theta / PI;
+        let x = ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f32) as usize).min(self.image.height - 1);
+        (x, y, sin_theta)
+    }
+
+    pub fn radiance(&self, direction: Vec3A) -> Vec3A {
+        let (x, y, _) = self.to_pixel(direction);
+        self.image.get(x, y) * self.intensity
+    }
+
+    pub fn sample(&self) -> (Vec3A, f32) {
+        if self.total_weight <= 0.0 {
+            return (random_on_unit_sphere(), uniform_sphere_pdf());
+        }
+        let width = self.image.width;
+        let (s, t) = sampler::get_2d();
+        let (y, y_offset) = search_cdf(&self.marginal_cdf, s);
+        let (x, x_offset) = search_cdf(&self.conditional_cdf[y * width..(y + 1) * width], t);
+
+        let u = (x as f32 + x_offset) / width as f32;
+        let v = (y as f32 + y_offset) / self.image.height as f32;
+        let phi = u * 2.0 * PI - self.rotation;
+        let theta = v * PI;
+        let direction = Vec3A::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        );
+        (direction, self.pdf(direction))
+    }
+
+    pub fn pdf(&self, direction: Vec3A) -> f32 {
+        if self.total_weight <= 0.0 {
+            return uniform_sphere_pdf();
+        }
+        let (x, y, sin_theta) = self.to_pixel(direction);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let pixel_probability = self.weights[y * self.image.width + x] / self.total_weight;
+        let pixels = (self.image.width * self.image.height) as f32;
+        pixel_probability * pixels / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Sampler;
+
+    fn sky(settings: &str) -> Environment {
+        Environment::new(&ron::from_str(&format!("Sky({})", settings)).unwrap())
+    }
+
+    /// A dim gradient with one bright pixel, like a studio HDRI with a lamp in it.
+    fn test_map() -> EnvironmentMap {
+        let (width, height) = (16, 8);
+        let mut pixels: Vec<Vec3A> = (0..width * height)
+            .map(|i| Vec3A::splat(0.1 + (i % width) as f32 * 0.05))
+            .collect();
+        pixels[2 * width + 5] = Vec3A::new(40.0, 30.0, 20.0);
+        EnvironmentMap::new(
+            HdrImage {
+                width,
+                height,
+                pixels,
+            },
+            1.0,
+            0.3,
+        )
+    }
+
+    #[test]
+    fn test_sampled_pdf_matches_pdf() {
+        let map = test_map();
+        let count = 20000;
+        let mut bright = 0;
+        for index in 0..count {
+            sampler::start_sample(Sampler::Random, 4, 0, index, count);
+            let (direction, pdf) = map.sample();
+            assert!(pdf > 0.0);
+            assert!((pdf - map.pdf(direction)).abs() <= 1e-4 * pdf);
+            let (x, y, _) = map.to_pixel(direction);
+            if (x, y) == (5, 2) {
+                bright += 1;
+            }
+        }
+        // Directions are chosen in proportion to each pixel's share of the weights.
+        let expected = map.weights[2 * 16 + 5] / map.total_weight;
+        assert!((bright as f32 / count as f32 - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pdf_integrates_to_one() {
+        let map = test_map();
+        let (rows, columns) = (256, 512);
+        let (d_theta, d_phi) = (PI / rows as f32, 2.0 * PI / columns as f32);
+        let mut total = 0.0;
+        for row in 0..rows {
+            let theta = (row as f32 + 0.5) * d_theta;
+            for column in 0..columns {
+                let phi = (column as f32 + 0.5) * d_phi;
+                let direction = Vec3A::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                total += map.pdf(direction) * theta.sin() * d_theta * d_phi;
+            }
+        }
+        assert!((total - 1.0).abs() < 0.01, "{}", total);
+    }
+
+    #[test]
+    fn test_sun_is_disabled_below_the_horizon() {
+        assert!(sky("sun_elevation: 10.0").sun().is_some());
+        assert!(sky("sun_elevation: -2.0").sun().is_none());
+        assert!(sky("sun_elevation: 10.0, sun: false").sun().is_none());
+
+        let time = |hour| {
+            format!(
+                "time_of_day: Some((latitude: 51.48, longitude: 0.0, year: 2020, month: 6, day: 20, hour: {:.1}))",
+                hour
+            )
+        };
+        assert!(sky(&time(12.0)).sun().is_some());
+        assert!(sky(&time(0.0)).sun().is_none());
+    }
+}
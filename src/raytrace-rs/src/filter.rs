@@ -0,0 +1,169 @@
+use crate::configuration::RonFilter;
+use crate::framebuffer::Splat;
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// The shape of the reconstruction filter samples are weighted by when they are splatted into
+/// the pixels around them.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// Equal weight for every sample inside the pixel, the sharpest filter but the one that
+    /// aliases most.
+    #[default]
+    Box,
+    /// Weight falling linearly to zero at the radius.
+    Tent,
+    /// A gaussian shifted down to reach zero at the radius, soft with little aliasing.
+    Gaussian,
+    /// The Mitchell-Netravali cubic with B = C = 1/3, which keeps edges crisp at the cost of faint
+    /// ringing from its negative lobes.
+    Mitchell,
+    /// A sinc windowed by a wider sinc, with one lobe per pixel of radius, the sharpest of the
+    /// smooth filters but prone to ringing.
+    Lanczos,
+}
+
+impl Filter {
+    /// The radius in pixels the filter is usually used at.
+    pub fn default_radius(&self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+            Filter::Lanczos => 3.0,
+        }
+    }
+
+    /// The weight of a sample `x` pixels from a pixel's centre along one axis.
+    fn weight(&self, x: f32, radius: f32) -> f32 {
+        let x = x.abs();
+        if x > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => radius - x,
+            Filter::Gaussian => {
+                // Matches a standard deviation of half a pixel at the default radius.
+                let alpha = 2.0 * (1.5 / radius).powi(2);
+                (-alpha * x * x).exp() - (-alpha * radius * radius).exp()
+            }
+            Filter::Mitchell => {
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                // The cubic is defined over [0, 2).
+                let x = 2.0 * x / radius;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * B - 6.0 * C) * x * x * x
+                        + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+                        + (6.0 - 2.0 * B))
+                        / 6.0
+                } else {
+                    ((-B - 6.0 * C) * x * x * x
+                        + (6.0 * B + 30.0 * C) * x * x
+                        + (-12.0 * B - 48.0 * C) * x
+                        + (8.0 * B + 24.0 * C))
+                        / 6.0
+                }
+            }
+            Filter::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl RonFilter {
+    pub fn radius(&self) -> f32 {
+        self.radius
+            .unwrap_or_else(|| self.kind.default_radius())
+            .max(0.5)
+    }
+
+    /// How many pixels past the one a sample was taken in the filter can reach.
+    pub fn margin(&self) -> usize {
+        (self.radius() - 0.5).ceil() as usize
+    }
+
+    /// Adds `colour`, sampled `offset` pixels right of and below the centre of pixel `(x, y)`, to
+    /// every pixel of `splat` whose centre is inside the filter, calling `add` with the pixel, the
+    /// colour and its weight.
+    pub fn splat<T, F>(
+        &self,
+        (x, y): (usize, usize),
+        offset: [f32; 2],
+        colour: Vec3A,
+        splat: &mut Splat<T>,
+        mut add: F,
+    ) where
+        F: FnMut(&mut T, Vec3A, f32),
+    {
+        let radius = self.radius();
+        let margin = self.margin() as isize;
+        for dy in -margin..=margin {
+            let weight_y = self.kind.weight(offset[1] - dy as f32, radius);
+            if weight_y == 0.0 {
+                continue;
+            }
+            for dx in -margin..=margin {
+                let weight = weight_y * self.kind.weight(offset[0] - dx as f32, radius);
+                if weight == 0.0 {
+                    continue;
+                }
+                if let Some(pixel) = splat.get_mut(x as isize + dx, y as isize + dy) {
+                    add(pixel, colour, weight);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RaytracerScene;
+    use crate::create_image;
+    use crate::tests::small_scene;
+
+    #[test]
+    fn test_reconstruction_filters() {
+        let filtered = |multithreading: bool, filter: RonFilter| {
+            let mut settings = RaytracerScene::from_ron(small_scene(multithreading, 6));
+            settings.tile_size = 8;
+            settings.filter = filter;
+            create_image(settings.to_ron())
+        };
+        let mean = |image: &Vec<Vec<Vec<u8>>>| {
+            image
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|v| *v as f32)
+                .sum::<f32>()
+                / (30 * 20 * 3) as f32
+        };
+
+        let reference = filtered(true, RonFilter::default());
+        let filter = |kind: Filter, radius: Option<f32>| RonFilter { kind, radius };
+        assert_eq!(reference, filtered(true, filter(Filter::Box, None)));
+        for filter in [
+            filter(Filter::Tent, None),
+            filter(Filter::Gaussian, None),
+            filter(Filter::Mitchell, None),
+            filter(Filter::Lanczos, Some(2.0)),
+        ] {
+            let image = filtered(true, filter);
+            assert_eq!(image, filtered(false, filter));
+            assert_ne!(image, reference);
+            assert!((mean(&image) - mean(&reference)).abs() < 4.0);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Aiming and focusing the camera at the scene once its objects have been built.
+use crate::aabb::AABB;
+use crate::camera::{Camera, Projection};
+use crate::configuration::{RaytracerScene, RonAutofocus, RonLookAt};
+use crate::hittables::{HittableObject, Hittables};
+use crate::ray::{Ray, Time};
+use glam::Vec3A;
+
+/// Where the camera ends up after framing part of the scene.
+pub struct Framing {
+    pub look_from: Vec3A,
+    pub look_at: Vec3A,
+    pub focal_distance: f32,
+    pub projection: Projection,
+}
+
+/// The built objects of the scene's objects called `name`. They come after the terrain's
+/// triangles, one for each object in the scene.
+fn named<'a>(
+    settings: &RaytracerScene,
+    objects: &'a [HittableObject],
+    name: &str,
+) -> Vec<&'a HittableObject> {
+    let first = objects.len() - settings.objects.len();
+    let named: Vec<_> = settings
+        .objects
+        .iter()
+        .zip(&objects[first..])
+        .filter(|(obj, _)| obj.name.as_deref() == Some(name))
+        .map(|(_, built)| built)
+        .collect();
+    if named.is_empty() {
+        panic!("the scene has no object called {}", name);
+    }
+    named
+}
+
+fn bounds<'a>(objects: impl IntoIterator<Item = &'a HittableObject>) -> Option<AABB> {
+    objects
+        .into_iter()
+        .map(|obj| obj.get_aabb())
+        .reduce(|a, b| a.union(&b))
+}
+
+/// Moves the camera of `framing` along the line from it to the middle of what `target` picks, or
+/// along its current line of sight when it is already there, until a sphere around their bounds
+/// just fits in the narrower of the vertical and horizontal fields of view. An orthographic view
+/// is sized to fit the sphere instead.
+pub fn frame(
+    settings: &RaytracerScene,
+    objects: &[HittableObject],
+    target: &RonLookAt,
+    framing: Framing,
+    v_fov: f32,
+    aspect_ratio: f32,
+) -> Framing {
+    let bounds = match target {
+        RonLookAt::Scene => bounds(objects),
+        RonLookAt::Object { name } => bounds(named(settings, objects, name)),
+    }
+    .expect("the scene has no objects to look at");
+    let centre = (bounds.min + bounds.max) / 2.0;
+    let radius = (bounds.max - bounds.min).length() / 2.0;
+
+    let direction = (centre - framing.look_from)
+        .try_normalize()
+        .or_else(|| (framing.look_at - framing.look_from).try_normalize())
+        .unwrap_or(Vec3A::NEG_Z);
+    let half_v_fov = (v_fov / 2.0).to_radians();
+    let half_h_fov = (half_v_fov.tan() * aspect_ratio).atan();
+    let distance = radius / half_v_fov.min(half_h_fov).sin();
+    let projection = match framing.projection {
+        Projection::Orthographic { .. } => Projection::Orthographic {
+            height: 2.0 * radius * (1.0 / aspect_ratio).max(1.0),
+        },
+        projection => projection,
+    };
+
+    Framing {
+        look_from: centre - direction * distance,
+        look_at: centre,
+        focal_distance: distance,
+        projection,
+    }
+}
+
+/// The focal distance that brings what `target` picks into focus at `time`, or `None` when the
+/// camera sees nothing there.
+pub fn autofocus(
+    settings: &RaytracerScene,
+    objects: &[HittableObject],
+    world: &Hittables,
+    camera: &Camera,
+    target: &RonAutofocus,
+    time: Time,
+) -> Option<f32> {
+    let point = match target {
+        // The image's rows run down from the top while the camera's run up from the bottom.
+        RonAutofocus::Screen { x, y } => camera
+            .centre_ray(*x, 1.0 - *y, time)
+            .and_then(|ray| world.hit(ray, 0.001, f32::INFINITY))
+            .map(|rec| rec.p),
+        RonAutofocus::Object { name } => {
+            let named = named(settings, objects, name);
+            let bounds = bounds(named.iter().copied())?;
+            let centre = (bounds.min + bounds.max) / 2.0;
+            let origin = camera.position(time);
+            let ray = Ray::new_at(origin, centre - origin, time);
+            // Objects the ray misses, like a ring around its middle, are focused at the middle.
+            let hit = named
+                .iter()
+                .filter_map(|obj| obj.hit(ray, 0.001, f32::INFINITY))
+                .min_by(|a, b| a.t.total_cmp(&b.t));
+            Some(hit.map_or(centre, |rec| rec.p))
+        }
+    };
+    point
+        .map(|point| camera.depth_of(point, time))
+        .filter(|depth| *depth > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Aperture;
+    use crate::tests::small_scene;
+    use crate::{create_camera, create_image, create_objects, create_world};
+
+    #[test]
+    fn test_autofocus_and_look_at() {
+        let mut settings = RaytracerScene::from_ron(small_scene(false, 2));
+        settings.objects[0].name = Some("metal".to_string());
+        let objects = create_objects(&settings);
+        let world = create_world(&settings, &objects);
+        let camera = create_camera(
+            &settings,
+            &objects,
+            &world,
+            &Aperture::new(&settings.aperture_shape),
+        );
+        let focus = |target: RonAutofocus| {
+            autofocus(
+                &settings,
+                &objects,
+                &world,
+                &camera,
+                &target,
+                Time::default(),
+            )
+        };
+
+        // The metal sphere's middle is 2 units in front of the camera and its radius is 0.5.
+        let on_sphere = |depth: Option<f32>| depth.is_some_and(|d| d > 1.5 && d < 2.0);
+        assert!(on_sphere(focus(RonAutofocus::Object {
+            name: "metal".to_string()
+        })));
+        // Left of the middle of the image, since the camera looks down +z.
+        assert!(on_sphere(focus(RonAutofocus::Screen { x: 0.4, y: 0.5 })));
+        assert_eq!(focus(RonAutofocus::Screen { x: 0.5, y: 0.5 }), None);
+
+        let camera_pos = Vec3A::new(0.0, 0.0, -3.5);
+        let frame = |target: RonLookAt| {
+            let unframed = Framing {
+                look_from: camera_pos,
+                look_at: Vec3A::ZERO,
+                focal_distance: 3.5,
+                projection: settings.projection,
+            };
+            super::frame(&settings, &objects, &target, unframed, 90.0, 1.5)
+        };
+        let framing = frame(RonLookAt::Scene);
+        let bounds = objects
+            .iter()
+            .map(|obj| obj.get_aabb())
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        assert!(framing
+            .look_at
+            .abs_diff_eq((bounds.min + bounds.max) / 2.0, 1e-5));
+        let back = framing.look_from - framing.look_at;
+        assert!((back.length() - framing.focal_distance).abs() < 1e-4);
+        // The camera stays on its line to the middle of the scene.
+        assert!(back
+            .normalize()
+            .abs_diff_eq((camera_pos - framing.look_at).normalize(), 1e-5));
+        let sphere = frame(RonLookAt::Object {
+            name: "metal".to_string(),
+        });
+        assert!(sphere.look_at.abs_diff_eq(Vec3A::new(0.6, 0.0, -1.5), 1e-5));
+
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+        settings.autofocus = Some(RonAutofocus::Screen { x: 0.4, y: 0.5 });
+        settings.look_at = Some(RonLookAt::Scene);
+        let image = create_image(settings.to_ron());
+        assert_eq!(image.len(), 20);
+    }
+}
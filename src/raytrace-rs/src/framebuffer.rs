@@ -0,0 +1,324 @@
+use indicatif::ProgressBar;
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// The order tiles are handed out to workers in.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// Row by row from the top left.
+    Scanline,
+    /// Outwards from the centre of the image, where the subject usually is.
+    #[default]
+    Spiral,
+    /// Along a Z-order curve, keeping neighbouring tiles close together in time.
+    Morton,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+fn morton_code(x: usize, y: usize) -> u64 {
+    let spread = |v: usize| {
+        let mut v = v as u64 & 0xffff_ffff;
+        v = (v | (v << 16)) & 0x0000_ffff_0000_ffff;
+        v = (v | (v << 8)) & 0x00ff_00ff_00ff_00ff;
+        v = (v | (v << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+        (v | (v << 1)) & 0x5555_5555_5555_5555
+    };
+    spread(x) | (spread(y) << 1)
+}
+
+/// Splits a `width` by `height` image into tiles of at most `tile_size` pixels square.
+pub fn create_tiles(width: usize, height: usize, tile_size: usize, order: TileOrder) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let columns = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+    let mut tiles: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|ty| (0..columns).map(move |tx| (tx, ty)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let centre_x = (columns as f32 - 1.0) / 2.0;
+            let centre_y = (rows as f32 - 1.0) / 2.0;
+            let key = |&(tx, ty): &(usize, usize)| {
+                let dx = tx as f32 - centre_x;
+                let dy = ty as f32 - centre_y;
+                // Rings of tiles around the centre, each walked clockwise.
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+            tiles.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        }
+        TileOrder::Morton => tiles.sort_by_key(|&(tx, ty)| morton_code(tx, ty)),
+    }
+
+    tiles
+        .into_iter()
+        .map(|(tx, ty)| Tile {
+            x: tx * tile_size,
+            y: ty * tile_size,
+            width: tile_size.min(width - tx * tile_size),
+            height: tile_size.min(height - ty * tile_size),
+        })
+        .collect()
+}
+
+impl Tile {
+    /// The part of this tile inside `window`, if any.
+    pub fn intersect(&self, window: &Tile) -> Option<Tile> {
+        let x = self.x.max(window.x);
+        let y = self.y.max(window.y);
+        let right = (self.x + self.width).min(window.x + window.width);
+        let bottom = (self.y + self.height).min(window.y + window.height);
+        (right > x && bottom > y).then(|| Tile {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+}
+
+/// A worker's copy of a tile grown by a margin on every side, so samples near the tile's edges can
+/// be splatted into the pixels around them. The tile's own pixels hold their render state while
+/// the margin collects what is added to the neighbouring tiles' pixels.
+pub struct Splat<T> {
+    tile: Tile,
+    margin: usize,
+    // The part of the image splats may land in.
+    window: Tile,
+    pixels: Vec<T>,
+}
+
+impl<T> Splat<T> {
+    fn index(&self, x: isize, y: isize) -> Option<usize> {
+        let margin = self.margin as isize;
+        let left = self.tile.x as isize - margin;
+        let top = self.tile.y as isize - margin;
+        let width = self.tile.width as isize + 2 * margin;
+        let height = self.tile.height as isize + 2 * margin;
+        let window = &self.window;
+        let inside = x >= left.max(window.x as isize)
+            && y >= top.max(window.y as isize)
+            && x < (left + width).min((window.x + window.width) as isize)
+            && y < (top + height).min((window.y + window.height) as isize);
+        inside.then(|| ((y - top) * width + x - left) as usize)
+    }
+
+    /// The pixel at `(x, y)` in the image, if it is inside the tile or its margin and the window
+    /// being rendered.
+    pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+        self.index(x, y).map(move |i| &mut self.pixels[i])
+    }
+}
+
+/// Per pixel render state stored row by row from the top left. Workers render into their own
+/// copy of a tile which is written back once every tile is done, so no two threads ever share
+/// a pixel.
+pub struct Framebuffer<T> {
+    width: usize,
+    pixels: Vec<T>,
+}
+
+impl<T: Copy + Send + Sync> Framebuffer<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Framebuffer {
+            width,
+            pixels: vec![value; width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, pixels: Vec<T>) -> Self {
+        Framebuffer { width, pixels }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn pixels(&self) -> &[T] {
+        &self.pixels
+    }
+
+    pub fn map<U: Copy + Send + Sync, F: Fn(&T) -> U>(&self, f: F) -> Framebuffer<U> {
+        Framebuffer {
+            width: self.width,
+            pixels: self.pixels.iter().map(f).collect(),
+        }
+    }
+
+    fn read_tile(&self, tile: &Tile) -> Vec<T> {
+        (tile.y..tile.y + tile.height)
+            .flat_map(|y| {
+                let start = y * self.width + tile.x;
+                self.pixels[start..start + tile.width].iter().copied()
+            })
+            .collect()
+    }
+
+    fn write_tile(&mut self, tile: &Tile, buffer: &[T]) {
+        buffer
+            .chunks(tile.width)
+            .enumerate()
+            .for_each(|(row, values)| {
+                let start = (tile.y + row) * self.width + tile.x;
+                self.pixels[start..start + tile.width].copy_from_slice(values);
+            });
+    }
+
+    /// Calls `shade` with the coordinates and state of every pixel, handing out `tiles` in
+    /// order to as many threads as rayon provides when `multithreading` is set.
+    pub fn render<F>(&mut self, tiles: &[Tile], multithreading: bool, pb: &ProgressBar, shade: F)
+    where
+        F: Fn(usize, usize, &mut T) + Sync,
+    {
+        let render_tile = |tile: &Tile| {
+            let mut buffer = self.read_tile(tile);
+            buffer.iter_mut().enumerate().for_each(|(i, pixel)| {
+                shade(tile.x + i % tile.width, tile.y + i / tile.width, pixel)
+            });
+            pb.inc(1);
+            buffer
+        };
+        let rendered: Vec<(&Tile, Vec<T>)> = if multithreading {
+            tiles
+                .iter()
+                .par_bridge()
+                .map(|tile| (tile, render_tile(tile)))
+                .collect()
+        } else {
+            tiles.iter().map(|tile| (tile, render_tile(tile))).collect()
+        };
+        rendered
+            .into_iter()
+            .for_each(|(tile, buffer)| self.write_tile(tile, &buffer));
+    }
+
+    /// Like `render`, but `shade` is handed a `Splat` of the tile so it can add to pixels up to
+    /// `margin` pixels outside it, though never outside `window` when only part of the image is
+    /// rendered. Each tile's own pixels are written back first and then what every tile added
+    /// around it is summed in, always in the order of `tiles` so the result is the same however
+    /// many threads there are.
+    pub fn render_splatted<F>(
+        &mut self,
+        tiles: &[Tile],
+        margin: usize,
+        window: Option<Tile>,
+        multithreading: bool,
+        pb: &ProgressBar,
+        shade: F,
+    ) where
+        T: AddAssign + Default,
+        F: Fn(usize, usize, &mut Splat<T>) + Sync,
+    {
+        let window = window.unwrap_or(Tile {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.pixels.len() / self.width.max(1),
+        });
+        let render_tile = |tile: &Tile| {
+            let width = tile.width + 2 * margin;
+            let mut splat = Splat {
+                tile: *tile,
+                margin,
+                window,
+                pixels: vec![T::default(); width * (tile.height + 2 * margin)],
+            };
+            let own = self.read_tile(tile);
+            own.chunks(tile.width)
+                .enumerate()
+                .for_each(|(row, values)| {
+                    let start = (row + margin) * width + margin;
+                    splat.pixels[start..start + tile.width].copy_from_slice(values);
+                });
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    shade(x, y, &mut splat);
+                }
+            }
+            pb.inc(1);
+            splat
+        };
+        let mut rendered: Vec<(usize, Splat<T>)> = if multithreading {
+            tiles
+                .iter()
+                .enumerate()
+                .par_bridge()
+                .map(|(i, tile)| (i, render_tile(tile)))
+                .collect()
+        } else {
+            tiles
+                .iter()
+                .enumerate()
+                .map(|(i, tile)| (i, render_tile(tile)))
+                .collect()
+        };
+        rendered.sort_by_key(|(i, _)| *i);
+
+        for (_, splat) in &rendered {
+            let tile = &splat.tile;
+            let own = (0..tile.height)
+                .flat_map(|row| {
+                    let start = (row + margin) * (tile.width + 2 * margin) + margin;
+                    splat.pixels[start..start + tile.width].iter().copied()
+                })
+                .collect::<Vec<_>>();
+            self.write_tile(tile, &own);
+        }
+        if margin == 0 {
+            return;
+        }
+        for (_, splat) in &rendered {
+            let tile = &splat.tile;
+            let left = tile.x as isize - margin as isize;
+            let top = tile.y as isize - margin as isize;
+            for y in top..top + (tile.height + 2 * margin) as isize {
+                for x in left..left + (tile.width + 2 * margin) as isize {
+                    let inside_tile = x >= tile.x as isize
+                        && y >= tile.y as isize
+                        && x < (tile.x + tile.width) as isize
+                        && y < (tile.y + tile.height) as isize;
+                    if let (false, Some(i)) = (inside_tile, splat.index(x, y)) {
+                        self.pixels[y as usize * self.width + x as usize] += splat.pixels[i];
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn to_rows<U, F: Fn(&T) -> U>(&self, f: F) -> Vec<Vec<U>> {
+        self.pixels
+            .chunks(self.width)
+            .map(|row| row.iter().map(&f).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_image() {
+        for order in [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Morton] {
+            let mut covered = vec![0; 70 * 45];
+            for tile in create_tiles(70, 45, 16, order) {
+                for y in tile.y..tile.y + tile.height {
+                    for x in tile.x..tile.x + tile.width {
+                        covered[y * 70 + x] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|count| *count == 1));
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use glam::Vec3A;
+use std::fs;
+use std::path::Path;
+
+/// A linear float RGB image stored row by row from the top left corner.
+pub struct HdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3A>,
+}
+
+impl HdrImage {
+    /// Loads a Radiance `.hdr` or `.pfm` file, picking the format from the file extension.
+    pub fn load(path: &str) -> Result<HdrImage, String> {
+        let bytes = fs::read(path).map_err(|e| format!("unable to read {}: {}", path, e))?;
+        match extension(path).as_deref() {
+            Some("pfm") => read_pfm(&bytes),
+            Some("hdr") | Some("pic") => read_hdr(&bytes),
+            _ => Err(format!("unsupported HDR image format for {}", path)),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Vec3A {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Saves the image as OpenEXR, PFM or Radiance `.hdr`, picking the format from the file
+    /// extension.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let bytes = match extension(path).as_deref() {
+            Some("exr") => write_exr(
+                self.width,
+                self.height,
+                &vec3_channels("", ["R", "G", "B"], &self.pixels),
+            ),
+            Some("pfm") => write_pfm(self),
+            Some("hdr") | Some("pic") => write_hdr(self),
+            _ => return Err(format!("unsupported HDR image format for {}", path)),
+        };
+        fs::write(path, bytes).map_err(|e| format!("unable to write {}: {}", path, e))
+    }
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+}
+
+fn read_line(bytes: &[u8], pos: &mut usize) -> Result<String, String> {
+    let start = *pos;
+    while *pos < bytes.len() && bytes[*pos] != b'\n' {
+        *pos += 1;
+    }
+    if *pos >= bytes.len() {
+        return Err("unexpected end of file in header".to_string());
+    }
+    let line = String::from_utf8_lossy(&bytes[start..*pos])
+        .trim()
+        .to_string();
+    *pos += 1;
+    Ok(line)
+}
+
+fn rgbe_to_rgb(rgbe: [u8; 4]) -> Vec3A {
+    if rgbe[3] == 0 {
+        return Vec3A::ZERO;
+    }
+    let scale = 2.0f32.powi(rgbe[3] as i32 - 136);
+    Vec3A::new(rgbe[0] as f32, rgbe[1] as f32, rgbe[2] as f32) * scale
+}
+
+fn read_hdr(bytes: &[u8]) -> Result<HdrImage, String> {
+    let mut pos = 0;
+    if !read_line(bytes, &mut pos)?.starts_with("#?") {
+        return Err("missing Radiance HDR signature".to_string());
+    }
+    loop {
+        let line = read_line(bytes, &mut pos)?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(format!("unsupported Radiance HDR {}", line));
+        }
+    }
+    let resolution = read_line(bytes, &mut pos)?;
+    let parts: Vec<&str> = resolution.split_whitespace().collect();
+    if parts.len() != 4 || parts[0] != "-Y" || parts[2] != "+X" {
+        return Err(format!(
+            "unsupported Radiance HDR orientation {}",
+            resolution
+        ));
+    }
+    let height = parts[1].parse::<usize>().map_err(|e| e.to_string())?;
+    let width = parts[3].parse::<usize>().map_err(|e| e.to_string())?;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let remaining = &bytes[pos.min(bytes.len())..];
+        let is_rle = (8..0x8000).contains(&width)
+            && remaining.len() >= 4
+            && remaining[0] == 2
+            && remaining[1] == 2
+            && remaining[2] & 0x80 == 0;
+        if is_rle {
+            if ((remaining[2] as usize) << 8 | remaining[3] as usize) != width {
+                return Err("Radiance HDR scanline width mismatch".to_string());
+            }
+            pos += 4;
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = *bytes.get(pos).ok_or("truncated Radiance HDR data")? as usize;
+                    pos += 1;
+                    if count > 128 {
+                        let run = count - 128;
+                        let value = *bytes.get(pos).ok_or("truncated Radiance HDR data")?;
+                        pos += 1;
+                        if x + run > width {
+                            return Err("Radiance HDR run overflows scanline".to_string());
+                        }
+                        scanline[x..x + run]
+                            .iter_mut()
+                            .for_each(|px| px[channel] = value);
+                        x += run;
+                    } else {
+                        if count == 0 || x + count > width || pos + count > bytes.len() {
+                            return Err("invalid Radiance HDR run".to_string());
+                        }
+                        for i in 0..count {
+                            scanline[x + i][channel] = bytes[pos + i];
+                        }
+                        pos += count;
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            if pos + width * 4 > bytes.len() {
+                return Err("truncated Radiance HDR data".to_string());
+            }
+            for (x, px) in scanline.iter_mut().enumerate() {
+                px.copy_from_slice(&bytes[pos + x * 4..pos + x * 4 + 4]);
+            }
+            pos += width * 4;
+        }
+        pixels.extend(scanline.iter().map(|rgbe| rgbe_to_rgb(*rgbe)));
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn read_pfm(bytes: &[u8]) -> Result<HdrImage, String> {
+    let mut pos = 0;
+    let channels = match read_line(bytes, &mut pos)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        other => return Err(format!("unsupported PFM type {}", other)),
+    };
+    let size = read_line(bytes, &mut pos)?;
+    let parts: Vec<&str> = size.split_whitespace().collect();
+    if parts.len() != 2 {
+        return Err(format!("invalid PFM size {}", size));
+    }
+    let width = parts[0].parse::<usize>().map_err(|e| e.to_string())?;
+    let height = parts[1].parse::<usize>().map_err(|e| e.to_string())?;
+    let scale = read_line(bytes, &mut pos)?
+        .parse::<f32>()
+        .map_err(|e| e.to_string())?;
+    let little_endian = scale < 0.0;
+
+    let data = &bytes[pos..];
+    if data.len() < width * height * channels * 4 {
+        return Err("truncated PFM data".to_string());
+    }
+    let value = |i: usize| {
+        let raw = [
+            data[i * 4],
+            data[i * 4 + 1],
+            data[i * 4 + 2],
+            data[i * 4 + 3],
+        ];
+        if little_endian {
+            f32::from_le_bytes(raw)
+        } else {
+            f32::from_be_bytes(raw)
+        }
+    };
+
+    // PFM scanlines are stored from the bottom of the image up.
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let i = (y * width + x) * channels;
+            pixels.push(if channels == 3 {
+                Vec3A::new(value(i), value(i + 1), value(i + 2))
+            } else {
+                Vec3A::splat(value(i))
+            });
+        }
+    }
+
+    Ok(HdrImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// A named plane of float values, one per pixel, for an OpenEXR file. Layers are written as
+/// channels sharing a `layer.` prefix.
+pub type Channel = (String, Vec<f32>);
+
+/// The three components of `pixels` as channels called `names`, prefixed with `layer` unless it
+/// is empty.
+pub fn vec3_channels(layer: &str, names: [&str; 3], pixels: &[Vec3A]) -> Vec<Channel> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let name = if layer.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", layer, name)
+            };
+            (name, pixels.iter().map(|pixel| pixel[i]).collect())
+        })
+        .collect()
+}
+
+fn write_attribute(bytes: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(kind.as_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+/// Saves `channels` to an OpenEXR file, the only format here that holds more than RGB.
+pub fn save_exr(
+    path: &str,
+    width: usize,
+    height: usize,
+    channels: &[Channel],
+) -> Result<(), String> {
+    if extension(path).as_deref() != Some("exr") {
+        return Err(format!("{} must be an OpenEXR file to hold layers", path));
+    }
+    fs::write(path, write_exr(width, height, channels))
+        .map_err(|e| format!("unable to write {}: {}", path, e))
+}
+
+/// An uncompressed single part scanline OpenEXR file of 32 bit float `channels`.
+pub fn write_exr(width: usize, height: usize, channels: &[Channel]) -> Vec<u8> {
+    const FLOAT: i32 = 2;
+    // OpenEXR requires the channel list and the data in every scanline sorted by name.
+    let mut channels: Vec<&Channel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut bytes = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    let mut list = Vec::new();
+    for (name, _) in &channels {
+        list.extend_from_slice(name.as_bytes());
+        list.push(0);
+        list.extend_from_slice(&FLOAT.to_le_bytes());
+        // pLinear and three reserved bytes, then the x and y sampling.
+        list.extend_from_slice(&[0; 4]);
+        list.extend_from_slice(&1i32.to_le_bytes());
+        list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    list.push(0);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    write_attribute(&mut bytes, "channels", "chlist", &list);
+    write_attribute(&mut bytes, "compression", "compression", &[0]);
+    write_attribute(&mut bytes, "dataWindow", "box2i", &window);
+    write_attribute(&mut bytes, "displayWindow", "box2i", &window);
+    write_attribute(&mut bytes, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut bytes,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    write_attribute(&mut bytes, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut bytes,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    bytes.push(0);
+
+    let line_size = channels.len() * width * 4;
+    let table_end = bytes.len() + height * 8;
+    for y in 0..height {
+        let offset = table_end + y * (line_size + 8);
+        bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+    }
+    for y in 0..height {
+        bytes.extend_from_slice(&(y as i32).to_le_bytes());
+        bytes.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for (_, values) in &channels {
+            values[y * width..(y + 1) * width]
+                .iter()
+                .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        }
+    }
+    bytes
+}
+
+fn write_pfm(image: &HdrImage) -> Vec<u8> {
+    // A negative scale marks the data as little endian.
+    let mut bytes = format!("PF\n{} {}\n-1.0\n", image.width, image.height).into_bytes();
+    for y in (0..image.height).rev() {
+        for pixel in &image.pixels[y * image.width..(y + 1) * image.width] {
+            pixel
+                .to_array()
+                .iter()
+                .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        }
+    }
+    bytes
+}
+
+fn rgb_to_rgbe(rgb: Vec3A) -> [u8; 4] {
+    let max = rgb.max_element();
+    if max < 1e-32 {
+        return [0; 4];
+    }
+    // The shared exponent puts the largest channel's mantissa in [128, 256).
+    let exponent = max.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0f32.powi(exponent);
+    let channel = |value: f32| (value.max(0.0) * scale).min(255.0) as u8;
+    [
+        channel(rgb.x),
+        channel(rgb.y),
+        channel(rgb.z),
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+fn write_hdr(image: &HdrImage) -> Vec<u8> {
+    let mut bytes = format!(
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height, image.width
+    )
+    .into_bytes();
+    for row in image.pixels.chunks(image.width.max(1)) {
+        let rgbe: Vec<[u8; 4]> = row.iter().map(|pixel| rgb_to_rgbe(*pixel)).collect();
+        // Flat scanlines can be mistaken for run length encoded ones, so any width the run length
+        // encoding supports is written with it, as runs of literal values.
+        if (8..0x8000).contains(&image.width) {
+            bytes.extend_from_slice(&[2, 2, (image.width >> 8) as u8, image.width as u8]);
+            for channel in 0..4 {
+                for run in rgbe.chunks(128) {
+                    bytes.push(run.len() as u8);
+                    bytes.extend(run.iter().map(|px| px[channel]));
+                }
+            }
+        } else {
+            rgbe.iter().for_each(|px| bytes.extend_from_slice(px));
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_path;
+
+    fn test_image(width: usize, height: usize) -> HdrImage {
+        HdrImage {
+            width,
+            height,
+            pixels: (0..width * height)
+                .map(|i| {
+                    let i = i as f32;
+                    Vec3A::new(0.01 * i, (0.37 * i).sin().abs() * 50.0, 1.0 / (i + 1.0))
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_hdr_round_trip() {
+        // Eight or more pixels across are run length encoded, fewer are not.
+        for width in [20, 5] {
+            let image = test_image(width, 3);
+            let read = read_hdr(&write_hdr(&image)).unwrap();
+            assert_eq!((read.width, read.height), (width, 3));
+            for (read, pixel) in read.pixels.iter().zip(&image.pixels) {
+                // The channels share the exponent of the brightest one, which has 8 bits.
+                let tolerance = pixel.max_element() / 128.0;
+                assert!((*read - *pixel).abs().max_element() <= tolerance);
+            }
+        }
+
+        // Runs of repeated values, which are not written but found in files from other tools.
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 10\n".to_vec();
+        bytes.extend_from_slice(&[2, 2, 0, 10]);
+        for value in [128, 64, 0, 129] {
+            bytes.extend_from_slice(&[128 + 6, value, 4, value, value, value, value]);
+        }
+        let read = read_hdr(&bytes).unwrap();
+        assert!(read.pixels.iter().all(|p| *p == Vec3A::new(1.0, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn test_pfm_round_trip() {
+        let image = test_image(7, 4);
+        let read = read_pfm(&write_pfm(&image)).unwrap();
+        assert_eq!((read.width, read.height), (7, 4));
+        assert_eq!(read.pixels, image.pixels);
+
+        let path = temp_path("round_trip.pfm");
+        image.save(&path).unwrap();
+        assert_eq!(HdrImage::load(&path).unwrap().pixels, image.pixels);
+        fs::remove_file(&path).unwrap();
+    }
+}
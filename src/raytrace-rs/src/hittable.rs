@@ -1,5 +1,5 @@
 use crate::material::Material;
-use crate::ray::Ray;
+use crate::ray::{Ray, Time};
 use glam::Vec3A;
 
 #[derive(Debug, Copy, Clone)]
@@ -9,6 +9,16 @@ pub struct HitRecord {
     pub t: f32,
     pub material: Material,
     pub front_face: bool,
+    /// Solid angle pdf of sampling this point as a light from the ray origin, zero unless the
+    /// material is emissive.
+    pub light_pdf: f32,
+    /// Time of the ray that found the hit, carried on to the rays leaving it.
+    pub time: Time,
+    /// Texture coordinates of the hit on the object's surface.
+    pub uv: (f32, f32),
+    /// The hit point on the object at its rest position, where solid textures are looked up so
+    /// that they move with the object.
+    pub rest_p: Vec3A,
 }
 
 #[allow(dead_code)]
@@ -32,6 +42,15 @@ impl HitRecord {
         self.normal = r.normal;
         self.front_face = r.front_face;
         self.material = r.material;
+        self.light_pdf = r.light_pdf;
+        self.time = r.time;
+        self.uv = r.uv;
+        self.rest_p = r.rest_p;
+    }
+
+    /// A ray leaving the hit point along `direction` at the same time as the ray that found it.
+    pub fn spawn_ray(&self, direction: Vec3A) -> Ray {
+        Ray::new_at(self.p, direction, self.time)
     }
 }
 
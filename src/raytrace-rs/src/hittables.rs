@@ -1,19 +1,224 @@
-use crate::hittable::HitRecord;
+use crate::aabb::AABB;
+use crate::configuration::{RonLight, RonLightType};
+use crate::environment::Environment;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
 use crate::octree::OcTree;
-use crate::ray::Ray;
+use crate::random_in_cone;
+use crate::ray::{Ray, Time};
+use crate::sampler;
+use crate::texture::Textures;
 use crate::Sphere;
 use crate::Triangle;
 use glam::Vec3A;
+use std::f32::consts::PI;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum HittableObject {
     SphereObj(Sphere),
     TriangleObj(Triangle),
 }
 
+impl HittableObject {
+    pub fn get_material(&self) -> Material {
+        match self {
+            HittableObject::SphereObj(s) => s.get_material(),
+            HittableObject::TriangleObj(t) => t.get_material(),
+        }
+    }
+
+    pub fn get_aabb(&self) -> AABB {
+        match self {
+            HittableObject::SphereObj(s) => s.get_aabb(),
+            HittableObject::TriangleObj(t) => t.get_aabb(),
+        }
+    }
+
+    pub fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        match self {
+            HittableObject::SphereObj(s) => s.hit(ray, t_min, t_max),
+            HittableObject::TriangleObj(t) => t.hit(ray, t_min, t_max),
+        }
+    }
+
+    fn sample_from(&self, origin: Vec3A, time: Time) -> Option<(HitRecord, f32)> {
+        match self {
+            HittableObject::SphereObj(s) => s.sample_from(origin, time),
+            HittableObject::TriangleObj(t) => t.sample_from(origin, time),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Light {
+    Point {
+        position: Vec3A,
+        radiance: Vec3A,
+        falloff: bool,
+    },
+    Directional {
+        direction: Vec3A,
+        radiance: Vec3A,
+        cos_theta_max: f32,
+    },
+    Spot {
+        position: Vec3A,
+        direction: Vec3A,
+        radiance: Vec3A,
+        cos_outer: f32,
+        cos_inner: f32,
+    },
+}
+
+/// Light arriving at a point: the unit direction towards the light, how far a shadow ray has to
+/// travel to reach it and the radiance it delivers.
+#[derive(Debug, Copy, Clone)]
+pub struct LightSample {
+    pub direction: Vec3A,
+    pub distance: f32,
+    pub radiance: Vec3A,
+}
+
+impl Light {
+    pub fn illuminate(&self, p: Vec3A) -> Option<LightSample> {
+        match *self {
+            Light::Point {
+                position,
+                radiance,
+                falloff,
+            } => {
+                let to_light = position - p;
+                let distance = to_light.length();
+                Some(LightSample {
+                    direction: to_light / distance,
+                    distance,
+                    radiance: if falloff {
+                        radiance / (distance * distance)
+                    } else {
+                        radiance
+                    },
+                })
+            }
+            Light::Directional {
+                direction,
+                radiance,
+                cos_theta_max,
+            } => Some(LightSample {
+                direction: if cos_theta_max < 1.0 {
+                    random_in_cone(-direction, cos_theta_max)
+                } else {
+                    -direction
+                },
+                distance: f32::INFINITY,
+                radiance,
+            }),
+            Light::Spot {
+                position,
+                direction,
+                radiance,
+                cos_outer,
+                cos_inner,
+            } => {
+                let to_light = position - p;
+                let distance = to_light.length();
+                let cosine = direction.dot(-to_light / distance);
+                if cosine <= cos_outer {
+                    return None;
+                }
+                let edge = if cosine >= cos_inner {
+                    1.0
+                } else {
+                    let t = (cosine - cos_outer) / (cos_inner - cos_outer);
+                    t * t * (3.0 - 2.0 * t)
+                };
+                Some(LightSample {
+                    direction: to_light / distance,
+                    distance,
+                    radiance: radiance * edge / (distance * distance),
+                })
+            }
+        }
+    }
+}
+
+impl Light {
+    /// Radiance seen looking along `direction` straight at a light with a visible disc.
+    /// Only paths that did not sample the light directly should add this.
+    pub fn visible_radiance(&self, direction: Vec3A) -> Vec3A {
+        match *self {
+            Light::Directional {
+                direction: light_direction,
+                radiance,
+                cos_theta_max,
+            } if cos_theta_max < 1.0
+                && direction.normalize().dot(-light_direction) >= cos_theta_max =>
+            {
+                radiance / (2.0 * PI * (1.0 - cos_theta_max))
+            }
+            _ => Vec3A::ZERO,
+        }
+    }
+}
+
+pub fn directional_light(direction: Vec3A, radiance: Vec3A, angular_diameter: f32) -> Light {
+    Light::Directional {
+        direction: direction.normalize(),
+        radiance,
+        cos_theta_max: (angular_diameter * 0.5).to_radians().cos(),
+    }
+}
+
+fn parse_light(light: &RonLight) -> Light {
+    match light {
+        RonLight::Position(position) => Light::Point {
+            position: conv_py_vec(position.clone()),
+            radiance: Vec3A::new(1.0, 1.0, 1.0),
+            falloff: false,
+        },
+        RonLight::Typed(RonLightType::Point {
+            position,
+            colour,
+            intensity,
+            falloff,
+        }) => Light::Point {
+            position: conv_py_vec(position.clone()),
+            radiance: conv_py_vec(colour.clone()) * *intensity,
+            falloff: *falloff,
+        },
+        RonLight::Typed(RonLightType::Directional {
+            direction,
+            colour,
+            intensity,
+            angular_diameter,
+        }) => directional_light(
+            conv_py_vec(direction.clone()),
+            conv_py_vec(colour.clone()) * *intensity,
+            *angular_diameter,
+        ),
+        RonLight::Typed(RonLightType::Spot {
+            position,
+            direction,
+            cone_angle,
+            soft_edge,
+            colour,
+            intensity,
+        }) => Light::Spot {
+            position: conv_py_vec(position.clone()),
+            direction: conv_py_vec(direction.clone()).normalize(),
+            radiance: conv_py_vec(colour.clone()) * *intensity,
+            cos_outer: cone_angle.to_radians().cos(),
+            cos_inner: (cone_angle - soft_edge.min(*cone_angle)).to_radians().cos(),
+        },
+    }
+}
+
 pub struct Hittables<'a> {
-    pub lights: Vec<Vec3A>,
+    pub lights: Vec<Light>,
+    pub environment: Environment,
+    area_lights: Vec<&'a HittableObject>,
     hittables: OcTree<'a>,
+    /// The patterns the textures of the objects' materials refer to.
+    pub textures: Textures,
 }
 
 fn conv_py_vec(vector: Vec<f32>) -> Vec3A {
@@ -22,19 +227,60 @@ fn conv_py_vec(vector: Vec<f32>) -> Vec3A {
 
 #[allow(dead_code)]
 impl<'a> Hittables<'a> {
-    pub fn new<'b>(lights: &[Vec<f32>], objects: &'b Vec<HittableObject>) -> Self
+    pub fn new<'b>(
+        lights: &[RonLight],
+        objects: &'b Vec<HittableObject>,
+        environment: Environment,
+        textures: Textures,
+    ) -> Self
     where
         'b: 'a,
     {
-        let mut _lights = vec![];
-        lights.iter().for_each(|obj| {
-            _lights.push(conv_py_vec(obj.clone()));
-        });
-
         Self {
-            lights: _lights,
+            lights: lights
+                .iter()
+                .map(parse_light)
+                .chain(environment.sun())
+                .collect(),
+            environment,
+            area_lights: objects
+                .iter()
+                .filter(|obj| obj.get_material().is_emissive())
+                .collect(),
             hittables: OcTree::new(objects),
+            textures,
+        }
+    }
+
+    /// Replaces the lights, keeping the environment's sun, so the world can be reused for another
+    /// frame of an animation without rebuilding its octree.
+    pub fn set_lights(&mut self, lights: &[RonLight]) {
+        self.lights = lights
+            .iter()
+            .map(parse_light)
+            .chain(self.environment.sun())
+            .collect();
+    }
+
+    /// Picks one emissive object uniformly and samples a point on it as seen from `origin` at
+    /// `time`, returning the point and its pdf including the chance of picking that object.
+    pub fn sample_area_light(&self, origin: Vec3A, time: Time) -> Option<(HitRecord, f32)> {
+        if self.area_lights.is_empty() {
+            return None;
+        }
+        let count = self.area_lights.len();
+        let idx = ((sampler::get_1d() * count as f32) as usize).min(count - 1);
+        self.area_lights[idx]
+            .sample_from(origin, time)
+            .map(|(rec, pdf)| (rec, pdf / count as f32))
+    }
+
+    /// Pdf `sample_area_light` would have chosen the emissive point in `rec` with.
+    pub fn area_light_pdf(&self, rec: &HitRecord) -> f32 {
+        if self.area_lights.is_empty() {
+            return 0.0;
         }
+        rec.light_pdf / self.area_lights.len() as f32
     }
 
     pub fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
@@ -0,0 +1,38 @@
+use image::codecs::pnm::{PnmSubtype, SampleEncoding};
+use image::{ImageOutputFormat, RgbImage};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Encodes rows of 8 bit RGB pixels as `png` or binary `ppm`.
+pub fn encode(image: &[Vec<Vec<u8>>], format: &str) -> Result<Vec<u8>, String> {
+    let format = match format.to_lowercase().as_str() {
+        "png" => ImageOutputFormat::Png,
+        "ppm" => ImageOutputFormat::Pnm(PnmSubtype::Pixmap(SampleEncoding::Binary)),
+        _ => return Err(format!("unsupported image format {}", format)),
+    };
+    let mut bytes = Cursor::new(vec![]);
+    to_rgb_image(image)
+        .write_to(&mut bytes, format)
+        .map_err(|e| format!("unable to encode image: {}", e))?;
+    Ok(bytes.into_inner())
+}
+
+/// Saves rows of 8 bit RGB pixels as PNG or binary PPM, picking the format from the file
+/// extension.
+pub fn save(image: &[Vec<Vec<u8>>], path: &str) -> Result<(), String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let bytes = encode(image, extension).map_err(|e| format!("{} for {}", e, path))?;
+    fs::write(path, bytes).map_err(|e| format!("unable to write {}: {}", path, e))
+}
+
+fn to_rgb_image(image: &[Vec<Vec<u8>>]) -> RgbImage {
+    let (width, height) = (image.first().map_or(0, |row| row.len()), image.len());
+    RgbImage::from_fn(width as u32, height as u32, |x, y| {
+        let pixel = &image[y as usize][x as usize];
+        image::Rgb([pixel[0], pixel[1], pixel[2]])
+    })
+}
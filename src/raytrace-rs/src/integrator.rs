@@ -0,0 +1,337 @@
+use crate::hittable::HitRecord;
+use crate::hittables::Hittables;
+use crate::material::{eval_bsdf, sample_bsdf};
+use crate::random_unit_vec3;
+use crate::ray::Ray;
+use crate::sampler;
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+use std::intrinsics::fdiv_fast;
+
+const SHADOW_EPSILON: f32 = 0.001;
+const RUSSIAN_ROULETTE_DEPTH: i32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Unidirectional path tracer with next-event estimation and multiple importance sampling.
+    #[default]
+    PathTracer,
+    /// The original recursive tracer that darkens bounces by a per light shadow factor.
+    Legacy,
+}
+
+fn power_heuristic(f_pdf: f32, g_pdf: f32) -> f32 {
+    let f = f_pdf * f_pdf;
+    let g = g_pdf * g_pdf;
+    if f + g == 0.0 {
+        return 0.0;
+    }
+    f / (f + g)
+}
+
+fn occluded(world: &Hittables, rec: &HitRecord, direction: Vec3A, max_dist: f32) -> bool {
+    world
+        .hit(rec.spawn_ray(direction), SHADOW_EPSILON, max_dist)
+        .is_some()
+}
+
+/// Direct lighting at a diffuse hit point from every scene light plus one sample each of the
+/// emissive geometry and the environment.
+fn sample_lights(world: &Hittables, rec: &HitRecord) -> Vec3A {
+    let lights = world.lights.iter().fold(Vec3A::ZERO, |acc, light| {
+        let sample = match light.illuminate(rec.p) {
+            Some(sample) => sample,
+            None => return acc,
+        };
+        let (f, _) = eval_bsdf(rec, sample.direction, &rec.material, &world.textures);
+        if f == Vec3A::ZERO
+            || occluded(
+                world,
+                rec,
+                sample.direction,
+                sample.distance - SHADOW_EPSILON,
+            )
+        {
+            acc
+        } else {
+            // Point, spot and directional lights are delta distributions so BSDF sampling can
+            // never find them and no MIS weight is needed.
+            acc + f * sample.radiance
+        }
+    });
+
+    let area_light = match world.sample_area_light(rec.p, rec.time) {
+        Some((light_rec, light_pdf)) => {
+            let to_light = light_rec.p - rec.p;
+            let dist = to_light.length();
+            let direction = to_light / dist;
+            let (f, bsdf_pdf) = eval_bsdf(rec, direction, &rec.material, &world.textures);
+            if f == Vec3A::ZERO || occluded(world, rec, direction, dist * (1.0 - SHADOW_EPSILON)) {
+                Vec3A::ZERO
+            } else {
+                f * light_rec.material.emitted()
+                    * unsafe { fdiv_fast(power_heuristic(light_pdf, bsdf_pdf), light_pdf) }
+            }
+        }
+        None => Vec3A::ZERO,
+    };
+
+    let environment = {
+        let (direction, light_pdf) = world.environment.sample();
+        let (f, bsdf_pdf) = eval_bsdf(rec, direction, &rec.material, &world.textures);
+        if f == Vec3A::ZERO || light_pdf <= 0.0 || occluded(world, rec, direction, f32::INFINITY) {
+            Vec3A::ZERO
+        } else {
+            f * world.environment.radiance(direction)
+                * unsafe { fdiv_fast(power_heuristic(light_pdf, bsdf_pdf), light_pdf) }
+        }
+    };
+
+    lights + area_light + environment
+}
+
+pub fn path_trace(ray: Ray, world: &Hittables, max_depth: i32) -> Vec3A {
+    let mut radiance = Vec3A::ZERO;
+    let mut throughput = Vec3A::ONE;
+    let mut ray = ray;
+    // Pdf of the BSDF sample that produced `ray`, zero when it came from the camera or a
+    // specular bounce so emission found along it is counted in full.
+    let mut bsdf_pdf = 0.0;
+
+    for depth in 0..max_depth {
+        sampler::start_bounce(depth);
+        let rec = match world.hit(ray, SHADOW_EPSILON, f32::INFINITY) {
+            Some(rec) => rec,
+            None => {
+                let weight = if bsdf_pdf > 0.0 {
+                    power_heuristic(bsdf_pdf, world.environment.pdf(ray.direction()))
+                } else {
+                    1.0
+                };
+                radiance += throughput * world.environment.radiance(ray.direction()) * weight;
+                if bsdf_pdf == 0.0 {
+                    radiance += throughput
+                        * world.lights.iter().fold(Vec3A::ZERO, |acc, light| {
+                            acc + light.visible_radiance(ray.direction())
+                        });
+                }
+                break;
+            }
+        };
+
+        if rec.material.is_emissive() {
+            let weight = if bsdf_pdf > 0.0 {
+                power_heuristic(bsdf_pdf, world.area_light_pdf(&rec))
+            } else {
+                1.0
+            };
+            radiance += throughput * rec.material.emitted() * weight;
+            break;
+        }
+
+        if !rec.material.is_specular() {
+            radiance += throughput * sample_lights(world, &rec);
+        }
+
+        match sample_bsdf(ray, rec, &rec.material, &world.textures) {
+            Some(sample) => {
+                throughput *= sample.weight;
+                bsdf_pdf = sample.pdf;
+                ray = sample.ray;
+            }
+            None => break,
+        }
+
+        if depth >= RUSSIAN_ROULETTE_DEPTH {
+            let survival = throughput.max_element().min(0.95);
+            if sampler::get_1d() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+    }
+
+    radiance
+}
+
+pub fn legacy_trace(ray: Ray, world: &Hittables, depth: i32) -> Vec3A {
+    let bias = 0.01;
+
+    if depth <= 0 {
+        return Vec3A::new(0.0, 0.0, 0.0);
+    }
+
+    match world.hit(ray, 0.001, f32::INFINITY) {
+        Some(hit_rec) => {
+            let color = &mut Vec3A::new(0.0, 0.0, 0.0);
+            match crate::material::scatter(ray, hit_rec, color, &hit_rec.material, &world.textures)
+            {
+                Some(result) => {
+                    (*color * legacy_trace(result, world, depth - 1))
+                        * world
+                            .lights
+                            .iter()
+                            .fold(Vec3A::new(1.0, 1.0, 1.0), |in_shadow, light| {
+                                let sample = match light.illuminate(hit_rec.p) {
+                                    Some(sample) => sample,
+                                    None => return in_shadow * Vec3A::new(0.05, 0.05, 0.05),
+                                };
+                                let point_of_intersection = hit_rec.p + (sample.direction * bias);
+                                let light_direction = sample.direction + random_unit_vec3() / 6.0;
+                                if let Some(_h) = world.hit(
+                                    Ray::new_at(
+                                        point_of_intersection,
+                                        light_direction,
+                                        hit_rec.time,
+                                    ),
+                                    0.01,
+                                    unsafe { fdiv_fast(sample.distance - bias, 2.0) },
+                                ) {
+                                    in_shadow * Vec3A::new(0.05, 0.05, 0.05)
+                                } else {
+                                    let angle_of_incedence = {
+                                        let mut a_o_i =
+                                            sample.direction.dot(hit_rec.get_normal().normalize());
+                                        if a_o_i < 0.0 {
+                                            a_o_i = 0.0;
+                                        }
+                                        a_o_i
+                                    };
+                                    in_shadow * angle_of_incedence * sample.radiance
+                                }
+                            })
+                }
+                None => hit_rec.material.emitted(),
+            }
+        }
+        None => world.environment.radiance(ray.direction()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RaytracerScene;
+    use crate::sampler::Sampler;
+    use crate::{create_objects, create_world};
+
+    fn scene(objects: &str, lights: &str, background: &str) -> RaytracerScene {
+        RaytracerScene::from_ron(format!("RaytracerScene(multithreading: false, aspect_ratio: 1.0, image_width: 1, image_height: 1, samples_per_pixel: 1, max_depth: 50, v_fov: 90.0, aperture: 0.0, focal_distance: 1.0, camera_pos: [0.0, 0.0, -3.0], camera_dir: [0.0, 0.0, 0.0], camera_up: [0.0, 1.0, 0.0], objects: [{}], lights: [{}], background: {})", objects, lights, background))
+    }
+
+    /// Averages `estimate` over `count` samples, each started like a camera sample of one pixel.
+    fn mean(count: usize, mut estimate: impl FnMut() -> Vec3A) -> Vec3A {
+        (0..count).fold(Vec3A::ZERO, |acc, index| {
+            sampler::start_sample(Sampler::Random, 1, 0, index, count);
+            acc + estimate()
+        }) / count as f32
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // Every bounce off a convex object escapes, so it reflects its albedo of the white sky.
+        let settings = scene(
+            "(objtype: \"Sphere\", vectors: [[0.0, 0.0, 0.0]], scalars: [1.0], material: [\"Lambertian\", \"0.8\", \"0.5\", \"0.2\"])",
+            "",
+            "Solid(colour: [1.0, 1.0, 1.0])",
+        );
+        let objects = create_objects(&settings);
+        let world = create_world(&settings, &objects);
+        let ray = Ray::new(Vec3A::new(0.0, 0.0, -3.0), Vec3A::new(0.1, 0.2, 1.0));
+        let radiance = mean(20000, || path_trace(ray, &world, 50));
+        assert!(
+            (radiance - Vec3A::new(0.8, 0.5, 0.2)).abs().max_element() < 0.01,
+            "{}",
+            radiance
+        );
+    }
+
+    #[test]
+    fn test_light_and_bsdf_sampling_agree() {
+        let settings = scene(
+            "(objtype: \"Sphere\", vectors: [[0.0, -100.0, 0.0]], scalars: [100.0], material: [\"Lambertian\", \"0.5\", \"0.5\", \"0.5\"]), (objtype: \"Sphere\", vectors: [[0.5, 1.0, 0.5]], scalars: [0.3], material: [\"Emissive\", \"1.0\", \"0.8\", \"0.6\", \"4.0\"])",
+            "",
+            "Solid(colour: [0.0, 0.0, 0.0])",
+        );
+        let objects = create_objects(&settings);
+        let world = create_world(&settings, &objects);
+        let ray = Ray::new(Vec3A::new(0.0, 0.5, -1.0), Vec3A::new(0.0, -0.5, 1.0));
+        let rec = world.hit(ray, SHADOW_EPSILON, f32::INFINITY).unwrap();
+
+        let light_sampled = mean(20000, || match world.sample_area_light(rec.p, rec.time) {
+            Some((light, pdf)) => {
+                let to_light = light.p - rec.p;
+                let (f, _) = eval_bsdf(&rec, to_light.normalize(), &rec.material, &world.textures);
+                f * light.material.emitted() / pdf
+            }
+            None => Vec3A::ZERO,
+        });
+        let bsdf_sampled = mean(200000, || {
+            let sample = sample_bsdf(ray, rec, &rec.material, &world.textures).unwrap();
+            match world.hit(sample.ray, SHADOW_EPSILON, f32::INFINITY) {
+                Some(hit) if hit.material.is_emissive() => sample.weight * hit.material.emitted(),
+                _ => Vec3A::ZERO,
+            }
+        });
+        assert!(light_sampled.max_element() > 0.05, "{}", light_sampled);
+        assert!(
+            ((light_sampled - bsdf_sampled) / light_sampled)
+                .abs()
+                .max_element()
+                < 0.05,
+            "{} {}",
+            light_sampled,
+            bsdf_sampled
+        );
+    }
+
+    /// Direct light reflected towards the camera from a grey floor at `point` by `light` alone.
+    fn direct_light(light: &str, point: Vec3A) -> Vec3A {
+        let settings = scene(
+            "(objtype: \"Sphere\", vectors: [[0.0, -1000.0, 0.0]], scalars: [1000.0], material: [\"Lambertian\", \"0.5\", \"0.5\", \"0.5\"])",
+            light,
+            "Solid(colour: [0.0, 0.0, 0.0])",
+        );
+        let objects = create_objects(&settings);
+        let world = create_world(&settings, &objects);
+        let origin = point + Vec3A::new(0.0, 1.0, -1.0);
+        sampler::start_sample(Sampler::Random, 1, 0, 0, 1);
+        path_trace(Ray::new(origin, point - origin), &world, 1)
+    }
+
+    #[test]
+    fn test_point_light_falloff() {
+        let at_height = |height: f32| {
+            direct_light(
+                &format!("Point(position: [0.0, {}, 0.0], intensity: 8.0)", height),
+                Vec3A::ZERO,
+            )
+        };
+        let near = at_height(1.0);
+        let far = at_height(2.0);
+        assert!(near.x > 0.0);
+        assert!((near / far - Vec3A::splat(4.0)).abs().max_element() < 0.01);
+        assert_eq!(
+            direct_light(
+                "Point(position: [0.0, 2.0, 0.0], falloff: false)",
+                Vec3A::ZERO
+            ),
+            direct_light(
+                "Point(position: [0.0, 1.0, 0.0], falloff: false)",
+                Vec3A::ZERO
+            )
+        );
+    }
+
+    #[test]
+    fn test_spot_light_cone() {
+        let spot = "Spot(position: [0.0, 2.0, 0.0], direction: [0.0, -1.0, 0.0], cone_angle: 30.0, soft_edge: 10.0, intensity: 8.0)";
+        // The cone reaches 2 tan(30) = 1.15 along the floor, fading out from 2 tan(20) = 0.73.
+        let inside = direct_light(spot, Vec3A::new(0.5, 0.0, 0.0));
+        let edge = direct_light(spot, Vec3A::new(1.0, 0.0, 0.0));
+        let outside = direct_light(spot, Vec3A::new(1.5, 0.0, 0.0));
+        assert!(inside.x > 0.0);
+        assert!(edge.x > 0.0 && edge.x < inside.x);
+        assert_eq!(outside, Vec3A::ZERO);
+    }
+}
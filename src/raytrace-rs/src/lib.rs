@@ -1,38 +1,70 @@
 #![feature(core_intrinsics, arc_unwrap_or_clone)]
-use crate::camera::Camera;
+use crate::camera::{Aperture, Camera};
+use crate::checkpoint::Checkpoint;
 use crate::colour_map::{ColourData, ColourMap};
+use crate::denoise::Features;
+use crate::environment::Environment;
+use crate::framebuffer::{Framebuffer, Tile};
+use crate::hdr::HdrImage;
 use crate::hittables::{HittableObject, Hittables};
+use crate::motion::Motion;
 use crate::noise::Noise;
 use crate::sphere::Sphere;
 use crate::terrain::Terrain;
+use crate::texture::{Texture, Textures};
 use crate::triangle::Triangle;
-use configuration::RonObject;
+use configuration::{RaytracerScene, RonHdrOutput, RonObject, RonOutput, RonTexture};
 use glam::Vec3A;
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use integrator::Integrator;
 use material::Material;
-use rand::Rng;
-use rayon::prelude::*;
+use progressive::Accumulated;
+use rng::Rng;
 use std::fmt::Write;
 use std::intrinsics::{fadd_fast, fdiv_fast, fmul_fast, fsub_fast, maxnumf32, minnumf32};
 use std::time::Instant;
 
 mod aabb;
+mod adaptive;
+mod animation;
+mod calibration;
 mod camera;
+mod checkpoint;
 mod colour_map;
 mod configuration;
+mod denoise;
+mod distributed;
+mod environment;
+mod filter;
+mod focus;
+mod framebuffer;
+mod hdr;
 mod hittable;
 mod hittables;
+mod image_file;
+mod integrator;
 mod material;
+mod motion;
 mod noise;
 mod octree;
+mod progressive;
 mod ray;
+mod rng;
+mod sampler;
+mod sky;
 mod sphere;
 mod terrain;
+mod texture;
+mod tonemap;
 mod triangle;
 
+// Stream used while building the scene, kept apart from the per pixel streams.
+const SCENE_STREAM: u64 = u64::MAX >> 1;
+// Seed of the dither noise added when quantising the output.
+const DITHER_STREAM: u64 = u64::MAX >> 2;
+
 fn random() -> f32 {
-    let mut rng = rand::thread_rng();
-    rng.gen::<f32>()
+    rng::next_f32()
 }
 fn random_f32(min: f32, max: f32) -> f32 {
     unsafe { fadd_fast(min, fmul_fast(fsub_fast(max, min), random())) }
@@ -45,182 +77,362 @@ fn random_vec3(min: f32, max: f32) -> Vec3A {
         random_f32(min, max),
     )
 }
-// Quick Diffusion
+// Uniformly distributed inside the unit ball.
 #[allow(dead_code)]
 fn random_unit_vec3() -> Vec3A {
-    let mut p: Vec3A;
-    loop {
-        p = random_vec3(-1.0, 1.0);
-        if p.length_squared() < 1.0 {
-            break;
-        }
-    }
-    p
+    uniform_sphere(random(), random()) * random().cbrt()
+}
+fn uniform_sphere(u: f32, v: f32) -> Vec3A {
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f32::consts::PI * v;
+    Vec3A::new(r * phi.cos(), r * phi.sin(), z)
+}
+fn random_on_unit_sphere() -> Vec3A {
+    let (u, v) = sampler::get_2d();
+    uniform_sphere(u, v)
+}
+// Uniformly distributed over the cone of directions within acos(cos_theta_max) of `axis`.
+fn random_in_cone(axis: Vec3A, cos_theta_max: f32) -> Vec3A {
+    let w = axis.normalize();
+    let a = if w.x.abs() > 0.9 {
+        Vec3A::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3A::new(1.0, 0.0, 0.0)
+    };
+    let v = w.cross(a).normalize();
+    let u = w.cross(v);
+
+    let (s, t) = sampler::get_2d();
+    let phi = 2.0 * std::f32::consts::PI * s;
+    let z = 1.0 + t * (cos_theta_max - 1.0);
+    let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+    u * (phi.cos() * sin_theta) + v * (phi.sin() * sin_theta) + w * z
 }
 fn clamp(val: f32, min: f32, max: f32) -> f32 {
     maxnumf32(minnumf32(val, max), min)
 }
 
-fn to_rgb(colour: Vec3A, samples_per_pixel: usize) -> Vec<u8> {
-    unsafe {
-        let scale = fdiv_fast(1.0, samples_per_pixel as f32);
-        let r = fmul_fast(256.0, clamp(fmul_fast(colour.x, scale).sqrt(), 0.0, 0.999)) as u8;
-        let g = fmul_fast(256.0, clamp(fmul_fast(colour.y, scale).sqrt(), 0.0, 0.999)) as u8;
-        let b = fmul_fast(256.0, clamp(fmul_fast(colour.z, scale).sqrt(), 0.0, 0.999)) as u8;
-
-        vec![r, g, b]
-    }
-}
-
-fn ray_color(ray: ray::Ray, world: &hittables::Hittables, depth: i32) -> Vec3A {
-    let bias = 0.01;
-
-    if depth <= 0 {
-        return Vec3A::new(0.0, 0.0, 0.0);
-    }
-
-    match world.hit(ray, 0.001, f32::INFINITY) {
-        Some(hit_rec) => {
-            let color = &mut Vec3A::new(0.0, 0.0, 0.0);
-            match material::scatter(ray, hit_rec, color, &hit_rec.material) {
-                Some(result) => {
-                    (*color * ray_color(result, world, depth - 1))
-                        * ((0..world.lights.len()).fold(
-                            Vec3A::new(1.0, 1.0, 1.0),
-                            |in_shadow, i| {
-                                let _light_direction = (world.lights[i] - hit_rec.p).normalize();
-                                let point_of_intersection = hit_rec.p + (_light_direction * bias);
-                                let light_direction = _light_direction + random_unit_vec3() / 6.0;
-                                let max_dist = (point_of_intersection - world.lights[i]).length();
-                                if let Some(_h) = world.hit(
-                                    ray::Ray::new(point_of_intersection, light_direction),
-                                    0.01,
-                                    unsafe { fdiv_fast(max_dist, 2.0) },
-                                ) {
-                                    in_shadow * Vec3A::new(0.05, 0.05, 0.05)
-                                } else {
-                                    let angle_of_incedence = {
-                                        let mut a_o_i =
-                                            _light_direction.dot(hit_rec.get_normal().normalize());
-                                        if a_o_i < 0.0 {
-                                            a_o_i = 0.0;
-                                        }
-                                        a_o_i
-                                    };
-                                    in_shadow * angle_of_incedence
-                                }
-                            },
-                        ))
-                }
-                None => Vec3A::new(0.0, 0.0, 0.0),
-            }
-        }
-        None => {
-            let unit_dir = ray.direction().normalize();
-            let t = unsafe { fmul_fast(0.5, fadd_fast(unit_dir.y, 1.0)) };
-            let one = Vec3A::new(1.0, 1.0, 1.0) * (1.0 - t);
-            let two = Vec3A::new(0.68, 0.8, 1.0) * t;
-            one + two
-        }
+/// The scene's output transform, brightened or darkened by the exposure of its physical camera.
+fn output_transform(settings: &RaytracerScene) -> RonOutput {
+    let mut output = settings.output.clone();
+    if let Some(physical) = &settings.physical_camera {
+        output.exposure += physical.exposure();
     }
+    output
 }
 
-fn create_work_list(image_width: i32, image_height: i32) -> Vec<Vec<Vec<usize>>> {
-    (0..image_height).fold(vec![], |mut work, y| {
-        work.push((0..image_width).fold(vec![], |mut row, x| {
-            row.push(vec![x as usize, y as usize]);
-            row
-        }));
-        work
-    })
+/// Converts the mean radiance of a pixel to 8 bit colour with the scene's output transform.
+/// `pixel` seeds the dither noise.
+fn to_rgb(colour: Vec3A, output: &RonOutput, pixel: usize) -> Vec<u8> {
+    let mapped = output.tone_map.apply(colour * output.exposure.exp2());
+    let mut dither = Rng::new(DITHER_STREAM, pixel as u64);
+    mapped
+        .to_array()
+        .iter()
+        .map(|value| {
+            // Clamped first, so the fast float ops below never see an infinite or NaN value.
+            let encoded = clamp(output.transfer.encode(*value), 0.0, 1.0);
+            let noise = if output.dither {
+                dither.next_f32() - dither.next_f32()
+            } else {
+                0.0
+            };
+            unsafe {
+                clamp(
+                    fadd_fast(fmul_fast(encoded, 255.0), noise).round(),
+                    0.0,
+                    255.0,
+                ) as u8
+            }
+        })
+        .collect()
+}
+
+fn to_image(output: &RonOutput, colours: &Framebuffer<Vec3A>) -> Vec<Vec<Vec<u8>>> {
+    colours
+        .pixels()
+        .chunks(colours.width().max(1))
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, colour)| to_rgb(*colour, output, y * colours.width() + x))
+                .collect()
+        })
+        .collect()
+}
+
+fn ray_color(
+    ray: ray::Ray,
+    world: &hittables::Hittables,
+    depth: i32,
+    integrator: Integrator,
+) -> Vec3A {
+    match integrator {
+        Integrator::PathTracer => integrator::path_trace(ray, world, depth),
+        Integrator::Legacy => integrator::legacy_trace(ray, world, depth),
+    }
 }
 
 fn parse_ron_material(mat: Vec<String>) -> Material {
     let material_type = &mat[0];
     match &material_type[..] {
-        "Lambertian" => Material::Lambertian(Vec3A::new(
+        "Lambertian" => Material::Lambertian(Texture::Constant(Vec3A::new(
             mat[1].parse::<f32>().unwrap(),
             mat[2].parse::<f32>().unwrap(),
             mat[3].parse::<f32>().unwrap(),
-        )),
+        ))),
         "Metal" => Material::Metal(
-            Vec3A::new(
+            Texture::Constant(Vec3A::new(
                 mat[1].parse::<f32>().unwrap(),
                 mat[2].parse::<f32>().unwrap(),
                 mat[3].parse::<f32>().unwrap(),
-            ),
+            )),
             mat[4].parse::<f32>().unwrap(),
         ),
         "Mirror" => Material::Mirror,
         "Dielectric" => Material::Dielectric(mat[1].parse::<f32>().unwrap()),
+        "Emissive" => Material::Emissive(
+            Vec3A::new(
+                mat[1].parse::<f32>().unwrap(),
+                mat[2].parse::<f32>().unwrap(),
+                mat[3].parse::<f32>().unwrap(),
+            ),
+            mat[4].parse::<f32>().unwrap(),
+        ),
         &_ => {
             panic!("Unknown material found")
         }
     }
 }
 
-fn parse_ron_sphere(obj: RonObject) -> Sphere {
-    Sphere::new(
+/// The object's material with its texture, if it has one.
+fn parse_ron_object_material(obj: &RonObject, patterned: &[&RonTexture]) -> Material {
+    let material = parse_ron_material(obj.material.clone());
+    match &obj.texture {
+        Some(texture) => material.with_texture(Texture::from_ron(texture, patterned)),
+        None => material,
+    }
+}
+
+fn parse_ron_motion(obj: &RonObject) -> Option<Motion> {
+    match (&obj.motion, &obj.keyframes) {
+        (Some(_), Some(_)) => panic!("an object can have a motion or keyframes but not both"),
+        (Some(motion), None) => Some(Motion::from_ron(motion)),
+        (None, Some(keyframes)) => Some(Motion::from_keyframes(keyframes)),
+        (None, None) => None,
+    }
+}
+
+fn parse_ron_sphere(obj: RonObject, patterned: &[&RonTexture]) -> Sphere {
+    let motion = parse_ron_motion(&obj);
+    let sphere = Sphere::new(
         conv_py_vec(obj.vectors[0].clone()),
         obj.scalars[0],
-        parse_ron_material(obj.material),
-    )
+        parse_ron_object_material(&obj, patterned),
+    );
+    match motion {
+        Some(motion) => sphere.with_motion(motion),
+        None => sphere,
+    }
 }
-fn parse_ron_triangle(obj: RonObject) -> Triangle {
-    Triangle::new(
+fn parse_ron_triangle(obj: RonObject, patterned: &[&RonTexture]) -> Triangle {
+    let motion = parse_ron_motion(&obj);
+    let triangle = Triangle::new(
         conv_py_vec(obj.vectors[0].clone()),
         conv_py_vec(obj.vectors[1].clone()),
         conv_py_vec(obj.vectors[2].clone()),
-        parse_ron_material(obj.material),
+        parse_ron_object_material(&obj, patterned),
         obj.scalars[0] != 0.0,
-    )
+    );
+    let triangle = match &obj.uvs {
+        Some(uvs) => {
+            if uvs.len() != 3 || uvs.iter().any(|uv| uv.len() != 2) {
+                panic!("a triangle needs a [u, v] texture coordinate for each of its points");
+            }
+            triangle.with_uvs([
+                (uvs[0][0], uvs[0][1]),
+                (uvs[1][0], uvs[1][1]),
+                (uvs[2][0], uvs[2][1]),
+            ])
+        }
+        None => triangle,
+    };
+    match motion {
+        Some(motion) => triangle.with_motion(motion),
+        None => triangle,
+    }
 }
-fn sample_pixel(
-    samples_per_pixel: usize,
-    coord: Vec<f32>,
-    image_width: i32,
-    image_height: i32,
-    max_depth: i32,
+/// The camera ray for one sample of the pixel at `coord`, if the camera sees anything there,
+/// starting the sample's sampler state, along with how far right of and below the pixel's centre
+/// the sample is.
+fn camera_ray(
+    settings: &RaytracerScene,
+    coord: &[f32],
+    sample: usize,
+    sample_count: usize,
     camera: &Camera,
-    world: &Hittables,
-) -> Vec3A {
+) -> (Option<ray::Ray>, [f32; 2]) {
+    let pixel = coord[1] as u64 * settings.image_width as u64 + coord[0] as u64;
+    sampler::start_sample(settings.sampler, settings.seed, pixel, sample, sample_count);
+    let (jitter_x, jitter_y) = sampler::get_2d();
+    let shutter = settings.shutter.or_else(|| {
+        settings
+            .physical_camera
+            .as_ref()
+            .and_then(|physical| physical.shutter())
+    });
+    let time = ray::Time::new(
+        settings.frame,
+        shutter.map_or(0.0, |shutter| {
+            shutter.open + (shutter.close - shutter.open) * sampler::get_1d()
+        }),
+    );
     unsafe {
-        (0..samples_per_pixel).fold(Vec3A::new(0.0, 0.0, 0.0), |pixel_color, _| {
-            let ray = {
-                let u = fdiv_fast(fadd_fast(coord[0], random()), (image_width - 1) as f32);
-                let v = fdiv_fast(
-                    fadd_fast(
-                        fsub_fast(image_height as f32, fadd_fast(coord[1], 1.0)),
-                        random(),
-                    ),
-                    (image_height - 1) as f32,
-                );
-                camera.get_ray(u, v)
-            };
-            pixel_color + ray_color(ray, world, max_depth)
-        })
+        let u = fdiv_fast(
+            fadd_fast(coord[0], jitter_x),
+            (settings.image_width - 1) as f32,
+        );
+        let v = fdiv_fast(
+            fadd_fast(
+                fsub_fast(settings.image_height as f32, fadd_fast(coord[1], 1.0)),
+                jitter_y,
+            ),
+            (settings.image_height - 1) as f32,
+        );
+        // v grows up the image while pixel rows grow down it.
+        (camera.get_ray(u, v, time), [jitter_x - 0.5, 0.5 - jitter_y])
     }
 }
 
+fn trace_sample(
+    settings: &RaytracerScene,
+    coord: &[f32],
+    sample: usize,
+    sample_count: usize,
+    camera: &Camera,
+    world: &Hittables,
+) -> (Vec3A, [f32; 2]) {
+    let (ray, offset) = camera_ray(settings, coord, sample, sample_count, camera);
+    let colour = ray.map_or(Vec3A::ZERO, |ray| {
+        ray_color(ray, world, settings.max_depth, settings.integrator)
+    });
+    (colour, offset)
+}
+
 fn conv_py_vec(vector: Vec<f32>) -> Vec3A {
     Vec3A::new(vector[0], vector[1], vector[2])
 }
 
-pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
-    let settings = configuration::RaytracerScene::from_ron(ron_string);
-    eprintln!("Loaded scene config into raytracer.\n");
-
-    let camera = camera::Camera::new(
-        conv_py_vec(settings.camera_pos.clone()),
-        conv_py_vec(settings.camera_dir.clone()),
-        conv_py_vec(settings.camera_up.clone()),
-        settings.v_fov,
-        settings.aspect_ratio,
-        settings.aperture,
-        settings.focal_distance,
-    );
+/// Builds the scene's camera with its aperture `shape`, aimed and focused at the built `objects`
+/// and `world` when the scene asks for it, and saves the camera's intrinsics and extrinsics when
+/// the scene has a camera output.
+fn create_camera(
+    settings: &RaytracerScene,
+    objects: &[HittableObject],
+    world: &Hittables,
+    shape: &Aperture,
+) -> Camera {
+    let (v_fov, aperture) = match &settings.physical_camera {
+        Some(physical) => (physical.v_fov(settings.aspect_ratio), physical.aperture()),
+        None => (settings.v_fov, settings.aperture),
+    };
+    let (v_fov, aspect_ratio, shift) = match &settings.intrinsics {
+        Some(rows) => calibration::view_from_intrinsics(
+            &calibration::square_matrix(rows, "intrinsic"),
+            settings.image_width,
+            settings.image_height,
+        ),
+        None => (v_fov, settings.aspect_ratio, (0.0, 0.0)),
+    };
+    let (look_from, look_at, v_up) = match &settings.world_to_camera {
+        Some(rows) => calibration::pose_from_world_to_camera(&calibration::square_matrix(
+            rows,
+            "world to camera",
+        )),
+        None => (
+            conv_py_vec(settings.camera_pos.clone()),
+            conv_py_vec(settings.camera_dir.clone()),
+            conv_py_vec(settings.camera_up.clone()),
+        ),
+    };
+    let framing = focus::Framing {
+        look_from,
+        look_at,
+        focal_distance: settings.focal_distance,
+        projection: settings.projection,
+    };
+    let framing = match &settings.look_at {
+        Some(target) => focus::frame(settings, objects, target, framing, v_fov, aspect_ratio),
+        None => framing,
+    };
+    let mut camera = camera::Camera::new(
+        framing.look_from,
+        framing.look_at,
+        v_up,
+        v_fov,
+        aspect_ratio,
+        aperture,
+        framing.focal_distance,
+    )
+    .with_projection(framing.projection)
+    .with_aperture(shape.clone())
+    .with_lens_shift(shift.0, shift.1);
+    if let Some(distortion) = settings.distortion {
+        camera = camera.with_distortion(distortion);
+    }
+    if let Some(stereo) = settings.stereo {
+        camera = camera.with_stereo(stereo.eye_separation, stereo.layout);
+    }
+    let tracks = settings
+        .animation
+        .as_ref()
+        .and_then(|animation| animation::camera_tracks(&animation.camera, animation.interpolation));
+    let camera = match (&settings.camera_end, tracks) {
+        (Some(_), Some(_)) => panic!("the camera can have a camera_end or keyframes but not both"),
+        (Some(end), None) => camera.with_motion(
+            conv_py_vec(end.camera_pos.clone()),
+            conv_py_vec(end.camera_dir.clone()),
+            conv_py_vec(end.camera_up.clone()),
+        ),
+        (None, Some(tracks)) => camera.with_tracks(tracks),
+        (None, None) => camera,
+    };
+    let focal_distance = settings.autofocus.as_ref().and_then(|target| {
+        focus::autofocus(
+            settings,
+            objects,
+            world,
+            &camera,
+            target,
+            ray::Time::new(settings.frame, 0.0),
+        )
+    });
+    let camera = match (focal_distance, &settings.autofocus) {
+        (Some(focal_distance), _) => camera.with_focus_distance(focal_distance),
+        (None, Some(_)) => {
+            eprintln!("Autofocus found nothing to focus on, keeping the focal distance.");
+            camera
+        }
+        (None, None) => camera,
+    };
+    // Every worker of a distributed render builds the same camera, so only the first saves it.
+    let first_worker = settings
+        .worker
+        .as_ref()
+        .is_none_or(|worker| worker.index == 0);
+    if let (Some(path), true) = (&settings.camera_output, first_worker) {
+        calibration::camera_json(settings, &camera)
+            .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()))
+            .expect("failed to save camera output");
+    }
+    camera
+}
 
+fn create_objects(settings: &RaytracerScene) -> Vec<HittableObject> {
     eprintln!("Generating Procedural Terrain.");
+    rng::seed_thread(Rng::new(settings.seed, SCENE_STREAM));
     let now_p = Instant::now();
     let mut _objects = vec![];
     if settings.has_terrain != 0 {
@@ -250,6 +462,7 @@ pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
                         settings.terrain.map_value[i][1],
                         settings.terrain.map_value[i][2],
                     ),
+                    emission: settings.terrain.map_emission.get(i).copied().unwrap_or(0.0),
                 });
             }
             _col_map
@@ -264,12 +477,17 @@ pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
             settings.terrain.magnitude,
         ));
     }
+    let patterned = texture::patterned(settings);
     settings.objects.iter().for_each(|obj| {
         match &*obj.objtype {
-            "Sphere" => _objects.push(HittableObject::SphereObj(parse_ron_sphere(obj.clone()))),
-            "Triangle" => {
-                _objects.push(HittableObject::TriangleObj(parse_ron_triangle(obj.clone())))
-            }
+            "Sphere" => _objects.push(HittableObject::SphereObj(parse_ron_sphere(
+                obj.clone(),
+                &patterned,
+            ))),
+            "Triangle" => _objects.push(HittableObject::TriangleObj(parse_ron_triangle(
+                obj.clone(),
+                &patterned,
+            ))),
             _ => panic!("unknown ron object type."),
         };
     });
@@ -282,9 +500,18 @@ pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
         "Procedural Terrain generation done.\nTime taken: {}h : {}m : {}s\n",
         hours_p, minutes_p, seconds_p
     );
+    _objects
+}
+
+fn create_world<'a>(settings: &RaytracerScene, objects: &'a Vec<HittableObject>) -> Hittables<'a> {
     eprintln!("Generating BVH.");
     let now_w = Instant::now();
-    let world = Hittables::new(&settings.lights, &_objects);
+    let world = Hittables::new(
+        &animation::lights_at(settings),
+        objects,
+        Environment::new(&settings.background),
+        Textures::new(settings),
+    );
     let mut seconds_w = now_w.elapsed().as_secs();
     let mut minutes_w = seconds_w / 60;
     seconds_w %= 60;
@@ -294,9 +521,10 @@ pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
         "BVH generation done.\nTime taken: {}h : {}m : {}s\n",
         hours_w, minutes_w, seconds_w
     );
-    eprintln!("Raytracing scene");
+    world
+}
 
-    let now = Instant::now();
+fn create_progress_bar(settings: &RaytracerScene) -> ProgressBar {
     let pb = ProgressBar::new(settings.image_height as u64);
     pb.set_style(
         ProgressStyle::with_template(
@@ -308,93 +536,691 @@ pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
         })
         .progress_chars("#>-"),
     );
+    pb
+}
 
-    let image = if settings.multithreading {
-        let image = {
-            let row = vec![vec![0, 0, 0]; settings.image_width as usize];
-            (0..settings.image_height).fold(vec![], |mut _vec, _| {
-                _vec.push(row.clone());
-                _vec
-            })
+/// The pixels inside the scene's crop window, clamped to the image.
+fn crop_window(settings: &RaytracerScene) -> Option<Tile> {
+    settings.crop.as_ref().map(|crop| {
+        let width = settings.image_width as usize;
+        let height = settings.image_height as usize;
+        let (scale_x, scale_y) = if crop.normalized {
+            (width as f32, height as f32)
+        } else {
+            (1.0, 1.0)
         };
+        let x = ((crop.x * scale_x).round().max(0.0) as usize).min(width);
+        let y = ((crop.y * scale_y).round().max(0.0) as usize).min(height);
+        let right = (((crop.x + crop.width) * scale_x).round().max(0.0) as usize).min(width);
+        let bottom = (((crop.y + crop.height) * scale_y).round().max(0.0) as usize).min(height);
+        Tile {
+            x,
+            y,
+            width: right.saturating_sub(x),
+            height: bottom.saturating_sub(y),
+        }
+    })
+}
 
-        let work_list = create_work_list(settings.image_width, settings.image_height);
-
-        work_list.par_iter().for_each(|row| {
-            row.iter().for_each(|work| {
-                let colour = to_rgb(
-                    sample_pixel(
-                        settings.samples_per_pixel,
-                        vec![work[0] as f32, work[1] as f32],
-                        settings.image_width,
-                        settings.image_height,
-                        settings.max_depth,
-                        &camera,
-                        &world,
-                    ),
-                    settings.samples_per_pixel,
-                );
-                unsafe {
-                    let raw_row = image.as_ptr() as *mut Vec<Vec<u8>>;
-                    let raw_column = (*raw_row.add(work[1])).as_ptr() as *mut Vec<u8>;
-                    let raw_pixel = (*raw_column.add(work[0])).as_ptr() as *mut u8;
-                    *raw_pixel.add(0) = colour[0];
-                    *raw_pixel.add(1) = colour[1];
-                    *raw_pixel.add(2) = colour[2];
+fn create_tiles(settings: &RaytracerScene) -> Vec<Tile> {
+    let tiles = framebuffer::create_tiles(
+        settings.image_width as usize,
+        settings.image_height as usize,
+        settings.tile_size,
+        settings.tile_order,
+    );
+    match crop_window(settings) {
+        Some(window) => tiles
+            .iter()
+            .filter_map(|tile| tile.intersect(&window))
+            .collect(),
+        None => tiles,
+    }
+}
+
+// Camera rays averaged per pixel for the denoiser's albedo, normal and depth buffers.
+const FEATURE_SAMPLES: usize = 8;
+
+/// Gathers the albedo, normal and depth seen by the camera rays of every pixel in `tiles`,
+/// following the paths of the pixel's first few samples.
+fn render_features(
+    settings: &RaytracerScene,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+) -> Framebuffer<Features> {
+    let mut features = Framebuffer::new(
+        settings.image_width as usize,
+        settings.image_height as usize,
+        Features::default(),
+    );
+    let sample_count = settings.samples_per_pixel.max(1);
+    features.render(
+        tiles,
+        settings.multithreading,
+        &ProgressBar::hidden(),
+        |x, y, pixel| {
+            let coord = [x as f32, y as f32];
+            for sample in 0..sample_count.min(FEATURE_SAMPLES) {
+                if let Some(ray) = camera_ray(settings, &coord, sample, sample_count, camera).0 {
+                    pixel.add(ray, world);
                 }
-            });
+            }
+            *pixel = pixel.resolve();
+        },
+    );
+    features
+}
 
-            pb.inc(1);
-        });
-        image.clone()
-    } else {
-        // Single Thread
-        let mut image_ = {
-            let row = (0..settings.image_width as usize).fold(vec![], |mut row, _| {
-                row.push(vec![0, 0, 0]);
-                row
-            });
-            (0..settings.image_height as usize).fold(vec![], |mut _vec, _| {
-                _vec.push(row.clone());
-                _vec
-            })
-        };
-        (0..settings.image_height).for_each(|y| {
-            (0..settings.image_width).for_each(|x| {
-                image_[y as usize][x as usize] = to_rgb(
-                    sample_pixel(
-                        settings.samples_per_pixel,
-                        vec![x as f32, y as f32],
-                        settings.image_width,
-                        settings.image_height,
-                        settings.max_depth,
-                        &camera,
-                        &world,
-                    ),
-                    settings.samples_per_pixel,
-                );
-            });
+/// Denoises an 8 bit image by undoing the transfer of `output`, filtering in linear light and
+/// encoding it again. The image already has its exposure and tone map, so only the transfer and
+/// dither of `output` are applied again.
+fn denoise_rows(
+    image: &[Vec<Vec<u8>>],
+    features: &[Features],
+    denoise: &configuration::RonDenoise,
+    output: &RonOutput,
+) -> Vec<Vec<Vec<u8>>> {
+    let width = image.first().map_or(0, |row| row.len());
+    let decode = |value: u8| output.transfer.decode(value as f32 / 255.0);
+    let colours: Vec<Vec3A> = image
+        .iter()
+        .flatten()
+        .map(|pixel| Vec3A::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])))
+        .collect();
+    let denoised = denoise::denoise(&colours, features, width, denoise);
+    let encoding = RonOutput {
+        transfer: output.transfer,
+        dither: output.dither,
+        ..Default::default()
+    };
+    to_image(&encoding, &Framebuffer::from_pixels(width, denoised))
+}
 
-            pb.inc(1);
-        });
-        image_
+/// Denoises the mean radiance of every pixel when the scene asks for it and saves it to the
+/// scene's HDR output.
+fn finish_colours(
+    settings: &RaytracerScene,
+    colours: Framebuffer<Vec3A>,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+) -> Framebuffer<Vec3A> {
+    let aovs = settings.hdr_output.as_ref().is_some_and(|hdr| hdr.aovs);
+    let features = (settings.denoise.is_some() || aovs)
+        .then(|| render_features(settings, tiles, camera, world));
+    let colours = match (&settings.denoise, &features) {
+        (Some(denoise), Some(features)) => {
+            let denoised = denoise::denoise(
+                colours.pixels(),
+                features.pixels(),
+                colours.width(),
+                denoise,
+            );
+            Framebuffer::from_pixels(colours.width(), denoised)
+        }
+        _ => colours,
+    };
+    if let Some(hdr) = &settings.hdr_output {
+        save_hdr(settings, hdr, &colours, features.as_ref().filter(|_| aovs));
+    }
+    colours
+}
+
+/// Finishes the mean radiance of every pixel, converts it to 8 bit colour and cuts out the crop
+/// window.
+fn finish_image(
+    settings: &RaytracerScene,
+    colours: Framebuffer<Vec3A>,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+) -> Vec<Vec<Vec<u8>>> {
+    let colours = finish_colours(settings, colours, tiles, camera, world);
+    crop_image(settings, to_image(&output_transform(settings), &colours))
+}
+
+/// Saves the crop window of the linear `colours` to the HDR output, with the albedo, normal and
+/// depth as extra layers when `features` are given.
+fn save_hdr(
+    settings: &RaytracerScene,
+    hdr: &RonHdrOutput,
+    colours: &Framebuffer<Vec3A>,
+    features: Option<&Framebuffer<Features>>,
+) {
+    let rows = crop_image(settings, colours.to_rows(|colour| *colour));
+    let image = HdrImage {
+        width: rows.first().map_or(0, |row| row.len()),
+        height: rows.len(),
+        pixels: rows.concat(),
     };
+    let saved = match features {
+        Some(features) => {
+            let features = crop_image(settings, features.to_rows(|f| *f)).concat();
+            let layer = |f: fn(&Features) -> Vec3A| features.iter().map(f).collect::<Vec<_>>();
+            let mut channels = hdr::vec3_channels("", ["R", "G", "B"], &image.pixels);
+            channels.extend(hdr::vec3_channels(
+                "albedo",
+                ["R", "G", "B"],
+                &layer(|f| f.albedo),
+            ));
+            channels.extend(hdr::vec3_channels(
+                "normal",
+                ["X", "Y", "Z"],
+                &layer(|f| f.normal),
+            ));
+            channels.push((
+                "depth.Z".to_string(),
+                features.iter().map(|f| f.depth).collect(),
+            ));
+            hdr::save_exr(&hdr.path, image.width, image.height, &channels)
+        }
+        None => image.save(&hdr.path),
+    };
+    saved.expect("failed to save HDR output");
+}
 
+/// Cuts the crop window out of a full frame `image` unless the scene asks for the full frame.
+fn crop_image<T: Clone>(settings: &RaytracerScene, image: Vec<Vec<T>>) -> Vec<Vec<T>> {
+    match (&settings.crop, crop_window(settings)) {
+        (Some(crop), Some(window)) if !crop.full_frame => image[window.y..window.y + window.height]
+            .iter()
+            .map(|row| row[window.x..window.x + window.width].to_vec())
+            .collect(),
+        _ => image,
+    }
+}
+
+fn print_time_taken(now: Instant) {
     let mut seconds = now.elapsed().as_secs();
     let mut minutes = seconds / 60;
     seconds %= 60;
     let hours = minutes / 60;
     minutes %= 60;
     eprintln!("Time taken: {}h : {}m : {}s", hours, minutes, seconds);
+}
+
+/// Renders the mean radiance of every pixel of `tiles` with whichever of the worker, adaptive,
+/// checkpointed or plain renderers the scene asks for.
+fn render_colours(
+    settings: &RaytracerScene,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+    pb: &ProgressBar,
+) -> Framebuffer<Vec3A> {
+    if let Some(worker) = &settings.worker {
+        let partial = distributed::render(settings, worker, tiles, camera, world, pb);
+        if let Some(output) = &worker.output {
+            partial.save(output).expect("failed to save partial render");
+        }
+        progressive::to_colours(&partial.to_framebuffer())
+    } else if let Some(adaptive) = &settings.adaptive {
+        adaptive::render(settings, adaptive, tiles, camera, world, pb)
+    } else if let Some(checkpoint) = &settings.checkpoint {
+        let accumulated = Framebuffer::new(
+            settings.image_width as usize,
+            settings.image_height as usize,
+            Accumulated::default(),
+        );
+        checkpoint::render(settings, checkpoint, tiles, camera, world, pb, accumulated)
+    } else {
+        let mut framebuffer = Framebuffer::new(
+            settings.image_width as usize,
+            settings.image_height as usize,
+            Accumulated::default(),
+        );
+        pb.set_length(tiles.len() as u64);
+        framebuffer.render_splatted(
+            tiles,
+            settings.filter.margin(),
+            crop_window(settings),
+            settings.multithreading,
+            pb,
+            |x, y, splat| {
+                progressive::splat_samples(
+                    settings,
+                    (x, y),
+                    0..settings.samples_per_pixel,
+                    settings.samples_per_pixel,
+                    camera,
+                    world,
+                    splat,
+                )
+            },
+        );
+        progressive::to_colours(&framebuffer)
+    }
+}
+
+/// Checks that a scene parses and that its settings can be used together, describing what is
+/// wrong with it when they can not.
+pub fn check_scene(ron_string: &str) -> Result<(), String> {
+    configuration::RaytracerScene::try_from_ron(ron_string).map(|_| ())
+}
+
+pub fn create_image(ron_string: String) -> Vec<Vec<Vec<u8>>> {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+
+    let objects = create_objects(&settings);
+    let world = create_world(&settings, &objects);
+    let camera = create_camera(
+        &settings,
+        &objects,
+        &world,
+        &Aperture::new(&settings.aperture_shape),
+    );
+    eprintln!("Raytracing scene");
+
+    let now = Instant::now();
+    let pb = create_progress_bar(&settings);
+    let tiles = create_tiles(&settings);
+    let image = render_colours(&settings, &tiles, &camera, &world, &pb);
+    let image = finish_image(&settings, image, &tiles, &camera, &world);
+    print_time_taken(now);
+
+    image
+}
+
+/// Renders the scene like `create_image` but returns the linear radiance of every pixel as rows
+/// of RGB floats, before the exposure, tone mapping and 8 bit encoding of the scene's output.
+pub fn create_image_hdr(ron_string: String) -> Vec<Vec<[f32; 3]>> {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+
+    let objects = create_objects(&settings);
+    let world = create_world(&settings, &objects);
+    let camera = create_camera(
+        &settings,
+        &objects,
+        &world,
+        &Aperture::new(&settings.aperture_shape),
+    );
+    eprintln!("Raytracing scene");
+
+    let now = Instant::now();
+    let pb = create_progress_bar(&settings);
+    let tiles = create_tiles(&settings);
+    let colours = render_colours(&settings, &tiles, &camera, &world, &pb);
+    let colours = finish_colours(&settings, colours, &tiles, &camera, &world);
+    print_time_taken(now);
+
+    crop_image(&settings, colours.to_rows(|colour| colour.to_array()))
+}
+
+/// Renders `samples_per_pixel` samples in passes of `samples_per_pass`, handing `callback` the
+/// image so far and the samples each pixel has after every pass. Rendering stops early when the
+/// callback returns false.
+pub fn create_image_progressive<F>(
+    ron_string: String,
+    samples_per_pass: usize,
+    mut callback: F,
+) -> Vec<Vec<Vec<u8>>>
+where
+    F: FnMut(&[Vec<Vec<u8>>], usize) -> bool,
+{
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+
+    let objects = create_objects(&settings);
+    let world = create_world(&settings, &objects);
+    let camera = create_camera(
+        &settings,
+        &objects,
+        &world,
+        &Aperture::new(&settings.aperture_shape),
+    );
+    eprintln!("Raytracing scene");
+
+    let now = Instant::now();
+    let pb = create_progress_bar(&settings);
+    let tiles = create_tiles(&settings);
+    let image = progressive::render(
+        &settings,
+        &tiles,
+        &camera,
+        &world,
+        &pb,
+        samples_per_pass,
+        |image, samples| callback(&crop_image(&settings, image.to_vec()), samples),
+    );
+    let image = finish_image(&settings, image, &tiles, &camera, &world);
+    print_time_taken(now);
+
+    image
+}
+
+/// Continues the render saved in the checkpoint at `checkpoint_path` up to `samples_per_pixel`
+/// samples. The scene and seed must match the ones the checkpoint was rendered with, apart from
+/// the sample count, threading and tiling. Progress keeps being saved to the scene's checkpoint.
+pub fn resume_image(ron_string: String, checkpoint_path: &str) -> Vec<Vec<Vec<u8>>> {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+    let saved = Checkpoint::load(checkpoint_path).expect("failed to load checkpoint");
+    saved
+        .check_resumable(&settings)
+        .expect("unable to resume checkpoint");
+
+    let objects = create_objects(&settings);
+    let world = create_world(&settings, &objects);
+    let camera = create_camera(
+        &settings,
+        &objects,
+        &world,
+        &Aperture::new(&settings.aperture_shape),
+    );
+    eprintln!("Raytracing scene");
+
+    let now = Instant::now();
+    let pb = create_progress_bar(&settings);
+    let tiles = create_tiles(&settings);
+    let checkpoint = settings
+        .checkpoint
+        .clone()
+        .unwrap_or_else(|| configuration::RonCheckpoint {
+            path: checkpoint_path.to_string(),
+            interval: 300.0,
+            samples_per_pass: 4,
+        });
+    let image = checkpoint::render(
+        &settings,
+        &checkpoint,
+        &tiles,
+        &camera,
+        &world,
+        &pb,
+        saved.to_framebuffer(),
+    );
+    let image = finish_image(&settings, image, &tiles, &camera, &world);
+    print_time_taken(now);
 
     image
 }
 
+/// Combines checkpoints of the same scene rendered with different seeds into one image,
+/// optionally saving the combined checkpoint to `output`. `output_ron` holds the scene's output
+/// transform, e.g. `(exposure: 1.0, transfer: Srgb)`.
+pub fn merge_checkpoints(
+    paths: &[String],
+    output: Option<&str>,
+    output_ron: &str,
+) -> Vec<Vec<Vec<u8>>> {
+    let transform: RonOutput = ron::from_str(output_ron).expect("deserialization failed");
+    let checkpoints = paths
+        .iter()
+        .map(|path| Checkpoint::load(path).expect("failed to load checkpoint"))
+        .collect();
+    let merged = Checkpoint::merge(checkpoints).expect("failed to merge checkpoints");
+    if let Some(path) = output {
+        merged.save(path).expect("failed to save checkpoint");
+    }
+    to_image(
+        &transform,
+        &progressive::to_colours(&merged.to_framebuffer()),
+    )
+}
+
+/// Renders the share of the tiles given by the scene's `worker`, returning the partial float
+/// buffer in the checkpoint format for a coordinator to assemble.
+pub fn create_partial(ron_string: String) -> Vec<u8> {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+    let worker = settings
+        .worker
+        .clone()
+        .expect("scene has no worker to render tiles for");
+
+    let objects = create_objects(&settings);
+    let world = create_world(&settings, &objects);
+    let camera = create_camera(
+        &settings,
+        &objects,
+        &world,
+        &Aperture::new(&settings.aperture_shape),
+    );
+    eprintln!("Raytracing scene");
+
+    let now = Instant::now();
+    let pb = create_progress_bar(&settings);
+    let tiles = create_tiles(&settings);
+    let partial = distributed::render(&settings, &worker, &tiles, &camera, &world, &pb);
+    print_time_taken(now);
+
+    partial.to_bytes()
+}
+
+/// Combines the partial renders saved by every worker of a scene into the final image.
+/// `output_ron` holds the scene's output transform, e.g. `(exposure: 1.0, transfer: Srgb)`.
+pub fn assemble_partials(paths: &[String], output_ron: &str) -> Vec<Vec<Vec<u8>>> {
+    let transform: RonOutput = ron::from_str(output_ron).expect("deserialization failed");
+    let partials = paths
+        .iter()
+        .map(|path| Checkpoint::load(path).expect("failed to load partial render"))
+        .collect();
+    let assembled = distributed::assemble(partials).expect("failed to assemble partial renders");
+    to_image(
+        &transform,
+        &progressive::to_colours(&assembled.to_framebuffer()),
+    )
+}
+
+/// Splits the render between `workers` local processes started with `command`, such as
+/// `["raytrace-worker"]`, which are sent their scene on stdin and reply with their partial
+/// render on stdout. The coordinator never loads the scene itself, so an HDR output gets no AOV
+/// layers.
+pub fn create_image_distributed(
+    ron_string: String,
+    workers: usize,
+    command: &[String],
+) -> Vec<Vec<Vec<u8>>> {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+
+    let now = Instant::now();
+    let partials = distributed::coordinate(&settings, workers.max(1), command)
+        .expect("distributed render failed");
+    let assembled = distributed::assemble(partials).expect("failed to assemble partial renders");
+    print_time_taken(now);
+
+    let colours = progressive::to_colours(&assembled.to_framebuffer());
+    if let Some(hdr) = &settings.hdr_output {
+        save_hdr(&settings, hdr, &colours, None);
+    }
+    crop_image(&settings, to_image(&output_transform(&settings), &colours))
+}
+
+/// Rows of the albedo, normal and depth of each pixel.
+pub type FeatureBuffers = (Vec<Vec<[f32; 3]>>, Vec<Vec<[f32; 3]>>, Vec<Vec<f32>>);
+
+/// Renders the albedo, normal and depth of what each pixel's camera rays see, looking through
+/// mirrors and glass to the first diffuse surface, which guide `denoise_image`. Pixels whose rays
+/// escape the scene have the environment's colour as their albedo, no normal and zero depth.
+pub fn create_features(ron_string: String) -> FeatureBuffers {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    let objects = create_objects(&settings);
+    let world = create_world(&settings, &objects);
+    let camera = create_camera(
+        &settings,
+        &objects,
+        &world,
+        &Aperture::new(&settings.aperture_shape),
+    );
+    let features = render_features(&settings, &create_tiles(&settings), &camera, &world);
+
+    (
+        crop_image(&settings, features.to_rows(|f| f.albedo.to_array())),
+        crop_image(&settings, features.to_rows(|f| f.normal.to_array())),
+        crop_image(&settings, features.to_rows(|f| f.depth)),
+    )
+}
+
+/// Runs the denoiser over a rendered image with the feature buffers from `create_features`.
+/// `denoise_ron` holds the denoiser settings, e.g. `(iterations: 5)`, and `output_ron` the output
+/// transform the image was rendered with, e.g. `(transfer: Srgb)`.
+pub fn denoise_image(
+    image: &[Vec<Vec<u8>>],
+    albedo: &[Vec<[f32; 3]>],
+    normal: &[Vec<[f32; 3]>],
+    depth: &[Vec<f32>],
+    denoise_ron: &str,
+    output_ron: &str,
+) -> Vec<Vec<Vec<u8>>> {
+    let denoise = ron::from_str(denoise_ron).expect("deserialization failed");
+    let output = ron::from_str(output_ron).expect("deserialization failed");
+    let features: Vec<Features> = albedo
+        .iter()
+        .flatten()
+        .zip(normal.iter().flatten())
+        .zip(depth.iter().flatten())
+        .map(|((albedo, normal), depth)| Features {
+            albedo: Vec3A::from_array(*albedo),
+            normal: Vec3A::from_array(*normal),
+            depth: *depth,
+            samples: 1,
+        })
+        .collect();
+    denoise_rows(image, &features, &denoise, &output)
+}
+
+/// Renders frames `start_frame` to `end_frame` of an animated scene, building its objects and
+/// octree once and moving only the camera, lights and keyframed objects between frames. Each
+/// frame is saved to `output` numbered as by `animation::frame_path`, e.g. `frame_####.png`,
+/// with outputs ending in `.exr`, `.pfm` or `.hdr` saving the linear render instead. A scene's
+/// own HDR output is numbered the same way. Returns the paths of the frames.
+pub fn create_sequence(
+    ron_string: String,
+    start_frame: i32,
+    end_frame: i32,
+    output: &str,
+) -> Result<Vec<String>, String> {
+    let settings = configuration::RaytracerScene::from_ron(ron_string);
+    eprintln!("Loaded scene config into raytracer.\n");
+    if settings.worker.is_some() {
+        return Err("a sequence cannot be rendered by a worker".to_string());
+    }
+    if end_frame < start_frame {
+        return Err(format!(
+            "the end frame {} is before the start frame {}",
+            end_frame, start_frame
+        ));
+    }
+    let linear = matches!(
+        output.rsplit('.').next(),
+        Some("exr") | Some("pfm") | Some("hdr")
+    );
+
+    let objects = create_objects(&settings);
+    let mut world = create_world(&settings, &objects);
+    let tiles = create_tiles(&settings);
+    let aperture = Aperture::new(&settings.aperture_shape);
+    let mut paths = vec![];
+    for frame in start_frame..=end_frame {
+        let path = animation::frame_path(output, frame);
+        let mut frame_settings = settings.clone();
+        frame_settings.frame = frame as f32;
+        if let Some(hdr) = &mut frame_settings.hdr_output {
+            hdr.path = animation::frame_path(&hdr.path, frame);
+        }
+        if let Some(camera_output) = &mut frame_settings.camera_output {
+            *camera_output = animation::frame_path(camera_output, frame);
+        }
+        if linear {
+            frame_settings.hdr_output = Some(RonHdrOutput {
+                path: path.clone(),
+                aovs: settings.hdr_output.as_ref().is_some_and(|hdr| hdr.aovs),
+            });
+        }
+        world.set_lights(&animation::lights_at(&frame_settings));
+        // The camera is rebuilt every frame so autofocus follows it.
+        let camera = create_camera(&frame_settings, &objects, &world, &aperture);
+        eprintln!("Raytracing frame {}", frame);
+
+        let now = Instant::now();
+        let pb = create_progress_bar(&frame_settings);
+        let image = render_colours(&frame_settings, &tiles, &camera, &world, &pb);
+        let image = finish_image(&frame_settings, image, &tiles, &camera, &world);
+        print_time_taken(now);
+        if !linear {
+            save_image(&image, &path)?;
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Encodes an image from `create_image` as `png` or `ppm`.
+pub fn encode_image(image: &[Vec<Vec<u8>>], format: &str) -> Result<Vec<u8>, String> {
+    image_file::encode(image, format)
+}
+
+/// Saves an image from `create_image` as PNG or PPM, picking the format from the extension of
+/// `path`.
+pub fn save_image(image: &[Vec<Vec<u8>>], path: &str) -> Result<(), String> {
+    image_file::save(image, path)
+}
+
+/// The settings of a scene the command line renderer can override.
+#[derive(Debug, Default, Clone)]
+pub struct SceneOverrides {
+    pub image_width: Option<i32>,
+    pub image_height: Option<i32>,
+    pub samples_per_pixel: Option<usize>,
+    pub multithreading: Option<bool>,
+    pub seed: Option<u64>,
+    /// An OpenEXR, PFM or Radiance HDR file to save the linear render to, with or without AOVs.
+    pub hdr_output: Option<(String, bool)>,
+    /// A JSON file to save the intrinsics and extrinsics of the camera to.
+    pub camera_output: Option<String>,
+}
+
+/// Applies `overrides` to a scene. Giving only one side of the image keeps the scene's aspect
+/// ratio, giving both changes it to match.
+pub fn override_scene(ron_string: String, overrides: &SceneOverrides) -> String {
+    let mut settings = configuration::RaytracerScene::from_ron(ron_string);
+    match (overrides.image_width, overrides.image_height) {
+        (Some(width), Some(height)) => {
+            settings.image_width = width;
+            settings.image_height = height;
+            settings.aspect_ratio = width as f32 / height as f32;
+        }
+        (Some(width), None) => {
+            settings.image_width = width;
+            settings.image_height = (width as f32 / settings.aspect_ratio).round().max(1.0) as i32;
+        }
+        (None, Some(height)) => {
+            settings.image_height = height;
+            settings.image_width = (height as f32 * settings.aspect_ratio).round().max(1.0) as i32;
+        }
+        (None, None) => {}
+    }
+    if let Some(samples) = overrides.samples_per_pixel {
+        settings.samples_per_pixel = samples;
+    }
+    if let Some(multithreading) = overrides.multithreading {
+        settings.multithreading = multithreading;
+    }
+    if let Some(seed) = overrides.seed {
+        settings.seed = seed;
+    }
+    if let Some((path, aovs)) = &overrides.hdr_output {
+        settings.hdr_output = Some(RonHdrOutput {
+            path: path.clone(),
+            aovs: *aovs,
+        });
+    }
+    if let Some(path) = &overrides.camera_output {
+        settings.camera_output = Some(path.clone());
+    }
+    settings.to_ron()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tonemap::{ToneMap, Transfer};
 
-    fn similarity(a: Vec<Vec<Vec<u8>>>, b: Vec<Vec<Vec<u8>>>) -> f32 {
+    pub(crate) fn similarity(a: Vec<Vec<Vec<u8>>>, b: Vec<Vec<Vec<u8>>>) -> f32 {
         let mut total_simi = 0.0;
         let mut point_simi;
         for y in 0..a.len() {
@@ -423,4 +1249,183 @@ mod tests {
         assert_eq!(simi > 0.95, true);
         Ok(())
     }
+
+    pub(crate) fn small_scene(multithreading: bool, seed: u64) -> String {
+        format!("RaytracerScene(multithreading: {}, seed: {}, aspect_ratio: 1.5, image_width: 30, image_height: 20, samples_per_pixel: 8, max_depth: 8, v_fov: 90.0, aperture: 0.1, focal_distance: 3.5, camera_pos: [0.0, 0.0, -3.5], camera_dir: [0.0, 0.0, 0.0], camera_up: [0.0, 1.0, 0.0], objects: [(objtype: \"Sphere\", vectors: [[0.6, 0.0, -1.5]], scalars: [0.5], material: [\"Metal\", \"0.7\", \"0.6\", \"0.2\", \"0.3\"]), (objtype: \"Sphere\", vectors: [[-0.7, 0.0, -1.2]], scalars: [0.5], material: [\"Lambertian\", \"0.9\", \"0.0\", \"0.8\"]), (objtype: \"Sphere\", vectors: [[0.0, 1.5, -1.0]], scalars: [0.3], material: [\"Emissive\", \"1.0\", \"1.0\", \"1.0\", \"4.0\"])], lights: [[-1.0, 1.5, -3.5]])", multithreading, seed)
+    }
+
+    /// A path in the temporary directory that no other run of the tests writes to.
+    pub(crate) fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("raytrace_{}_{}", std::process::id(), name));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_seeded_render_is_reproducible() {
+        let reference = create_image(small_scene(false, 7));
+        assert_eq!(reference, create_image(small_scene(false, 7)));
+        assert_eq!(reference, create_image(small_scene(true, 7)));
+        assert_ne!(reference, create_image(small_scene(false, 8)));
+    }
+
+    #[test]
+    fn test_crop_window() {
+        let full = create_image(small_scene(true, 2));
+        let crop = |window: &str| {
+            let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+            settings.crop = Some(ron::from_str(window).unwrap());
+            create_image(settings.to_ron())
+        };
+
+        let region = crop("(x: 5.0, y: 4.0, width: 12.0, height: 9.0)");
+        assert_eq!(region.len(), 9);
+        for (y, row) in region.iter().enumerate() {
+            assert_eq!(row[..], full[y + 4][5..17]);
+        }
+        assert_eq!(
+            region,
+            crop("(x: 0.1666667, y: 0.2, width: 0.4, height: 0.45, normalized: true)")
+        );
+
+        let frame = crop("(x: 5.0, y: 4.0, width: 12.0, height: 9.0, full_frame: true)");
+        assert_eq!(frame.len(), 20);
+        assert_eq!(frame[10][10], full[10][10]);
+        assert_eq!(frame[0][0], vec![0, 0, 0]);
+
+        // A wide filter splats samples past the edges of the window but never outside it.
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 2));
+        settings.filter.kind = crate::filter::Filter::Gaussian;
+        let mut window: configuration::RonCrop =
+            ron::from_str("(x: 5.0, y: 4.0, width: 12.0, height: 9.0)").unwrap();
+        settings.crop = Some(window.clone());
+        let region = create_image(settings.to_ron());
+        window.full_frame = true;
+        settings.crop = Some(window);
+        let frame = create_image(settings.to_ron());
+        for (y, row) in frame.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                if (4..13).contains(&y) && (5..17).contains(&x) {
+                    assert_eq!(*pixel, region[y - 4][x - 5]);
+                } else {
+                    assert_eq!(*pixel, vec![0, 0, 0]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_transform() {
+        let colour = Vec3A::new(0.04, 0.25, 0.81);
+        assert_eq!(to_rgb(colour, &RonOutput::default(), 0), vec![51, 128, 230]);
+
+        let bright = Vec3A::new(2.0, 1.0, 0.5);
+        for tone_map in [ToneMap::Reinhard, ToneMap::Filmic, ToneMap::Aces] {
+            let output = RonOutput {
+                tone_map,
+                ..Default::default()
+            };
+            let rgb = to_rgb(bright, &output, 0);
+            assert!(rgb[0] < 255 && rgb[0] > rgb[1] && rgb[1] > rgb[2]);
+        }
+
+        let exposed = RonOutput {
+            exposure: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(to_rgb(colour / 2.0, &exposed, 0), vec![51, 128, 230]);
+
+        let srgb = RonOutput {
+            transfer: Transfer::Srgb,
+            dither: true,
+            ..Default::default()
+        };
+        let mean = (0..1000)
+            .map(|pixel| to_rgb(Vec3A::splat(0.2), &srgb, pixel)[0] as f32)
+            .sum::<f32>()
+            / 1000.0;
+        assert!((mean - 255.0 * Transfer::Srgb.encode(0.2)).abs() < 0.1);
+
+        // Without dither every level comes back from the value the denoiser decodes it to.
+        let undithered = RonOutput {
+            dither: false,
+            ..srgb
+        };
+        for level in 0..=255u8 {
+            let decoded = Transfer::Srgb.decode(level as f32 / 255.0);
+            assert_eq!(to_rgb(Vec3A::splat(decoded), &undithered, 0)[0], level);
+        }
+        let unbounded = Vec3A::new(f32::INFINITY, f32::NEG_INFINITY, 1.0);
+        assert_eq!(to_rgb(unbounded, &undithered, 0), vec![255, 0, 255]);
+    }
+
+    #[test]
+    fn test_hdr_output() {
+        let linear = create_image_hdr(small_scene(true, 5));
+        let colours =
+            Framebuffer::from_pixels(30, linear.concat().into_iter().map(Vec3A::from).collect());
+        assert_eq!(
+            to_image(&RonOutput::default(), &colours),
+            create_image(small_scene(true, 5))
+        );
+
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 5));
+        for (file, aovs) in [
+            ("hdr_output.pfm", false),
+            ("hdr_output.hdr", false),
+            ("hdr_output.exr", true),
+        ] {
+            let path = temp_path(file);
+            settings.hdr_output = Some(RonHdrOutput {
+                path: path.clone(),
+                aovs,
+            });
+            create_image(settings.to_ron());
+            if aovs {
+                // Header, offset table and ten channels of floats on every scanline.
+                let bytes = std::fs::read(&path).unwrap();
+                assert_eq!(bytes[..4], [0x76, 0x2f, 0x31, 0x01]);
+                assert!(bytes.len() > 20 * (8 + 8 + 10 * 30 * 4));
+            } else {
+                let saved = HdrImage::load(&path).unwrap();
+                assert_eq!((saved.width, saved.height), (30, 20));
+                for (saved, colour) in saved.pixels.iter().zip(colours.pixels()) {
+                    assert!((*saved - *colour).abs().max_element() <= colour.max_element() / 128.0);
+                }
+            }
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_scene_overrides() {
+        let overrides = SceneOverrides {
+            image_width: Some(60),
+            samples_per_pixel: Some(2),
+            seed: Some(9),
+            ..Default::default()
+        };
+        let image = create_image(override_scene(small_scene(true, 1), &overrides));
+        assert_eq!((image.len(), image[0].len()), (40, 60));
+        let mut settings = RaytracerScene::from_ron(small_scene(true, 9));
+        settings.image_width = 60;
+        settings.image_height = 40;
+        settings.samples_per_pixel = 2;
+        assert_eq!(image, create_image(settings.to_ron()));
+
+        let ppm = encode_image(&image, "ppm").unwrap();
+        assert_eq!(ppm.len(), "P6\n60 40\n255\n".len() + 60 * 40 * 3);
+        let png = encode_image(&image, "png").unwrap();
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        // IEND is empty, so its CRC is the same in every PNG.
+        assert_eq!(png[png.len() - 8..], *b"IEND\xae\x42\x60\x82");
+        assert!(encode_image(&image, "jpg").is_err());
+    }
+
+    #[test]
+    fn test_scene_validation() {
+        assert!(check_scene(&small_scene(true, 2)).is_ok());
+        assert!(check_scene("RaytracerScene(image_width: 30)")
+            .unwrap_err()
+            .starts_with("deserialization failed"));
+    }
 }
@@ -1,25 +1,144 @@
 use crate::ray::Ray;
-use crate::{hittable::HitRecord, random_f32, random_unit_vec3};
+use crate::texture::{Texture, Textures};
+use crate::{hittable::HitRecord, random_f32, random_on_unit_sphere, random_unit_vec3};
 use glam::Vec3A;
+use std::f32::consts::FRAC_1_PI;
 use std::intrinsics::{fadd_fast, fdiv_fast, fmul_fast, fsub_fast, powf32};
 
 #[derive(Debug, Copy, Clone)]
 #[allow(dead_code)]
 pub enum Material {
-    Lambertian(Vec3A),
-    Metal(Vec3A, f32),
+    Lambertian(Texture),
+    Metal(Texture, f32),
     Dielectric(f32),
     Mirror,
+    Emissive(Vec3A, f32),
 }
 
-pub fn scatter(ray: Ray, rec: HitRecord, color: &mut Vec3A, material: &Material) -> Option<Ray> {
+/// A direction sampled from a material's BSDF.
+///
+/// `weight` is the BSDF value times the cosine term divided by `pdf`, ready to be multiplied into
+/// the path throughput. `pdf` is zero for the perfectly specular materials as their lobes are a
+/// delta distribution that light sampling can never hit.
+#[derive(Debug, Copy, Clone)]
+pub struct BsdfSample {
+    pub ray: Ray,
+    pub weight: Vec3A,
+    pub pdf: f32,
+}
+
+impl Material {
+    pub fn is_specular(&self) -> bool {
+        matches!(
+            self,
+            Material::Metal(..) | Material::Dielectric(_) | Material::Mirror
+        )
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        matches!(self, Material::Emissive(..))
+    }
+
+    /// The material with its colour replaced by `texture`. Only `Lambertian` and `Metal`
+    /// materials have a colour to replace, which scenes are checked for when they are parsed, and
+    /// the others are returned unchanged.
+    pub fn with_texture(self, texture: Texture) -> Material {
+        match self {
+            Material::Lambertian(_) => Material::Lambertian(texture),
+            Material::Metal(_, fuzz) => Material::Metal(texture, fuzz),
+            material => material,
+        }
+    }
+
+    /// Radiance leaving an emissive surface, which emits equally from both of its faces.
+    pub fn emitted(&self) -> Vec3A {
+        match self {
+            Material::Emissive(col, strength) => *col * *strength,
+            _ => Vec3A::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+pub fn sample_bsdf(
+    ray: Ray,
+    rec: HitRecord,
+    material: &Material,
+    textures: &Textures,
+) -> Option<BsdfSample> {
+    match material {
+        Material::Lambertian(texture) => {
+            let direction = {
+                let d = rec.normal + random_on_unit_sphere();
+                if d.length_squared() < 1e-8 {
+                    rec.normal
+                } else {
+                    d.normalize()
+                }
+            };
+            let pdf = unsafe { fmul_fast(direction.dot(rec.normal), FRAC_1_PI) };
+            if pdf <= 0.0 {
+                return None;
+            }
+            Some(BsdfSample {
+                ray: rec.spawn_ray(direction),
+                weight: texture.value(&rec, textures),
+                pdf,
+            })
+        }
+        _ => {
+            let color = &mut Vec3A::new(0.0, 0.0, 0.0);
+            scatter(ray, rec, color, material, textures).map(|scattered| BsdfSample {
+                ray: scattered,
+                weight: *color,
+                pdf: 0.0,
+            })
+        }
+    }
+}
+
+/// Evaluates the BSDF for light arriving from `direction`, returning the BSDF value times the
+/// cosine term and the pdf `sample_bsdf` would have chosen that direction with.
+pub fn eval_bsdf(
+    rec: &HitRecord,
+    direction: Vec3A,
+    material: &Material,
+    textures: &Textures,
+) -> (Vec3A, f32) {
+    match material {
+        Material::Lambertian(texture) => {
+            let cosine = direction.dot(rec.normal);
+            if cosine <= 0.0 {
+                (Vec3A::new(0.0, 0.0, 0.0), 0.0)
+            } else {
+                unsafe {
+                    let pdf = fmul_fast(cosine, FRAC_1_PI);
+                    (texture.value(rec, textures) * pdf, pdf)
+                }
+            }
+        }
+        _ => (Vec3A::new(0.0, 0.0, 0.0), 0.0),
+    }
+}
+
+pub fn scatter(
+    ray: Ray,
+    rec: HitRecord,
+    color: &mut Vec3A,
+    material: &Material,
+    textures: &Textures,
+) -> Option<Ray> {
     match material {
-        Material::Lambertian(col) => lambertian_scatter(ray, rec, color, col),
-        Material::Metal(col, fuzz) => metal_scatter(ray, rec, color, col, *fuzz),
+        Material::Lambertian(texture) => {
+            lambertian_scatter(ray, rec, color, &texture.value(&rec, textures))
+        }
+        Material::Metal(texture, fuzz) => {
+            metal_scatter(ray, rec, color, &texture.value(&rec, textures), *fuzz)
+        }
         Material::Dielectric(refractive_index) => {
             dielectric_scatter(ray, rec, color, *refractive_index)
         }
         Material::Mirror => mirror_scatter(ray, rec, color),
+        Material::Emissive(..) => None,
     }
 }
 
@@ -67,7 +186,7 @@ fn lambertian_scatter(
 ) -> Option<Ray> {
     let scatter_direction = rec.normal + random_unit_vec3();
     color.clone_from(material_color);
-    Some(Ray::new(rec.p, scatter_direction))
+    Some(rec.spawn_ray(scatter_direction))
 }
 
 fn metal_scatter(
@@ -78,7 +197,7 @@ fn metal_scatter(
     fuzz: f32,
 ) -> Option<Ray> {
     let reflected = reflect(ray.direction().normalize(), rec.normal);
-    let scattered = Ray::new(rec.p, reflected + (random_unit_vec3() * fuzz));
+    let scattered = rec.spawn_ray(reflected + (random_unit_vec3() * fuzz));
     color.clone_from(material_color);
     if scattered.direction().dot(rec.normal) > 0.0 {
         Some(scattered)
@@ -88,7 +207,7 @@ fn metal_scatter(
 }
 
 fn mirror_scatter(ray: Ray, rec: HitRecord, color: &mut Vec3A) -> Option<Ray> {
-    let reflected = Ray::new(rec.p, reflect(ray.direction().normalize(), rec.normal));
+    let reflected = rec.spawn_ray(reflect(ray.direction().normalize(), rec.normal));
     color.clone_from(&Vec3A::new(1.0, 1.0, 1.0));
     if reflected.direction().dot(rec.normal) > 0.0 {
         Some(reflected)
@@ -126,10 +245,10 @@ fn dielectric_scatter(
 
         if let Some(ray) = refract(ray.direction(), outward_normal, ni_over_nt) {
             if random_f32(0.0, 1.0) > schlick(cosine, refractive_index) {
-                return Some(Ray::new(rec.p, ray));
+                return Some(rec.spawn_ray(ray));
             }
         }
 
-        Some(Ray::new(rec.p, reflected))
+        Some(rec.spawn_ray(reflected))
     }
 }
@@ -0,0 +1,253 @@
+use crate::aabb::AABB;
+use crate::animation::{vec3_track, Interpolation, Track};
+use crate::configuration::{RonKeyframes, RonMotion, RonTransform};
+use crate::conv_py_vec;
+use crate::hittable::HitRecord;
+use crate::ray::{Ray, Time};
+use glam::{EulerRot, Quat, Vec3A};
+
+// Steps each stretch of the motion between two keys is split into when bounding it.
+const BOUND_STEPS: usize = 16;
+
+/// A translation, rotation and uniform scale of an object about a pivot.
+#[derive(Debug, Copy, Clone)]
+struct Transform {
+    translation: Vec3A,
+    // Degrees about the x, y and z axes.
+    angles: Vec3A,
+    rotation: Quat,
+    scale: f32,
+}
+
+/// Moves an object through keyframed transforms, applied about `pivot` to the object as it is
+/// given in the scene, its rest position.
+#[derive(Debug, Clone)]
+pub struct Motion {
+    pivot: Vec3A,
+    // Whether the keys are times within the shutter of every frame rather than of the animation.
+    over_shutter: bool,
+    translation: Track<Vec3A>,
+    rotation: Track<Vec3A>,
+    scale: Track<f32>,
+}
+
+impl Motion {
+    /// A motion from the `start` transform at time 0 to the `end` transform at time 1 of the
+    /// shutter, repeated in every frame.
+    pub fn from_ron(motion: &RonMotion) -> Motion {
+        let keys =
+            |f: fn(&RonTransform) -> Vec<f32>| vec![(0.0, f(&motion.start)), (1.0, f(&motion.end))];
+        Motion {
+            pivot: conv_py_vec(motion.pivot.clone()),
+            over_shutter: true,
+            translation: vec3_track(&keys(|t| t.translation.clone()), Interpolation::Linear),
+            rotation: vec3_track(&keys(|t| t.rotation.clone()), Interpolation::Linear),
+            scale: Track::new(
+                vec![(0.0, motion.start.scale), (1.0, motion.end.scale)],
+                Interpolation::Linear,
+            ),
+        }
+    }
+
+    pub fn from_keyframes(keyframes: &RonKeyframes) -> Motion {
+        Motion {
+            pivot: conv_py_vec(keyframes.pivot.clone()),
+            over_shutter: false,
+            translation: vec3_track(&keyframes.translation, keyframes.interpolation),
+            rotation: vec3_track(&keyframes.rotation, keyframes.interpolation),
+            scale: Track::new(keyframes.scale.clone(), keyframes.interpolation),
+        }
+    }
+
+    fn at(&self, time: Time) -> Transform {
+        self.at_key_time(if self.over_shutter {
+            time.shutter
+        } else {
+            time.animation()
+        })
+    }
+
+    fn at_key_time(&self, time: f32) -> Transform {
+        let angles = self.rotation.at(time).unwrap_or(Vec3A::ZERO);
+        let [x, y, z] = angles.to_array().map(f32::to_radians);
+        Transform {
+            translation: self.translation.at(time).unwrap_or(Vec3A::ZERO),
+            angles,
+            rotation: Quat::from_euler(EulerRot::XYZ, x, y, z),
+            scale: self.scale.at(time).unwrap_or(1.0),
+        }
+    }
+
+    fn world_point(&self, transform: &Transform, point: Vec3A) -> Vec3A {
+        self.pivot
+            + transform.translation
+            + transform.rotation * (point - self.pivot) * transform.scale
+    }
+
+    fn rest_point(&self, transform: &Transform, point: Vec3A) -> Vec3A {
+        self.pivot
+            + transform.rotation.inverse() * (point - self.pivot - transform.translation)
+                / transform.scale
+    }
+
+    fn rec_to_world(&self, transform: &Transform, rec: HitRecord) -> HitRecord {
+        HitRecord {
+            p: self.world_point(transform, rec.p),
+            normal: transform.rotation * rec.normal,
+            ..rec
+        }
+    }
+
+    /// Intersects `ray` with the moving object by carrying the ray into the object's rest
+    /// position at the ray's time, where `hit_at_rest` intersects it. Distances along the ray
+    /// and solid angles are the same in both, so only the hit point and normal move back.
+    pub fn hit<F>(&self, ray: Ray, t_min: f32, t_max: f32, hit_at_rest: F) -> Option<HitRecord>
+    where
+        F: FnOnce(Ray, f32, f32) -> Option<HitRecord>,
+    {
+        let transform = self.at(ray.time());
+        let rest = Ray::new_at(
+            self.rest_point(&transform, ray.origin()),
+            transform.rotation.inverse() * ray.direction() / transform.scale,
+            ray.time(),
+        );
+        hit_at_rest(rest, t_min, t_max).map(|rec| self.rec_to_world(&transform, rec))
+    }
+
+    /// Samples the moving object as seen from `origin` at `time` with `sample_at_rest`, which
+    /// samples it at its rest position from where `origin` is relative to it.
+    pub fn sample_from<F>(
+        &self,
+        origin: Vec3A,
+        time: Time,
+        sample_at_rest: F,
+    ) -> Option<(HitRecord, f32)>
+    where
+        F: FnOnce(Vec3A) -> Option<(HitRecord, f32)>,
+    {
+        let transform = self.at(time);
+        sample_at_rest(self.rest_point(&transform, origin))
+            .map(|(rec, pdf)| (self.rec_to_world(&transform, rec), pdf))
+    }
+
+    /// Bounds everywhere `aabb`, the bounds of the object at rest, passes through over the
+    /// motion.
+    pub fn bound(&self, aabb: AABB) -> AABB {
+        let corners = (0..8)
+            .map(|i| {
+                Vec3A::new(
+                    if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                    if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                    if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+                )
+            })
+            .collect::<Vec<_>>();
+        let reach = corners
+            .iter()
+            .map(|corner| corner.distance(self.pivot))
+            .fold(0.0, f32::max);
+        let mut times = self
+            .translation
+            .times()
+            .chain(self.rotation.times())
+            .chain(self.scale.times())
+            .collect::<Vec<_>>();
+        times.sort_by(f32::total_cmp);
+        times.dedup();
+
+        let mut min = Vec3A::splat(f32::INFINITY);
+        let mut max = Vec3A::splat(f32::NEG_INFINITY);
+        let mut add = |transform: &Transform, pad: f32| {
+            for corner in &corners {
+                let point = self.world_point(transform, *corner);
+                min = min.min(point - pad);
+                max = max.max(point + pad);
+            }
+        };
+        let mut previous = self.at_key_time(times.first().copied().unwrap_or(0.0));
+        add(&previous, 0.0);
+        for pair in times.windows(2) {
+            for i in 1..=BOUND_STEPS {
+                let next =
+                    self.at_key_time(pair[0] + (pair[1] - pair[0]) * i as f32 / BOUND_STEPS as f32);
+                // Every value eases monotonically from one key to the next, so within the step
+                // the object turns by at most the change in its angles and no point strays
+                // further than this from where it was at the start of the step.
+                let delta = (next.angles - previous.angles).abs();
+                let turn = (delta.x + delta.y + delta.z).to_radians();
+                let pad = reach
+                    * (previous.scale.max(next.scale) * turn + (next.scale - previous.scale).abs())
+                    + next.translation.distance(previous.translation);
+                add(&previous, pad);
+                previous = next;
+            }
+            add(&previous, 0.0);
+        }
+        AABB::new(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RaytracerScene;
+    use crate::create_image;
+    use crate::material::Material;
+    use crate::tests::{similarity, small_scene};
+    use crate::triangle::Triangle;
+
+    #[test]
+    fn test_motion_blur() {
+        let moving = |shutter: bool, motion: &str| {
+            let mut settings = RaytracerScene::from_ron(small_scene(true, 4));
+            settings.objects[0].motion = Some(ron::from_str(motion).unwrap());
+            if shutter {
+                settings.shutter = Some(ron::from_str("()").unwrap());
+            }
+            settings
+        };
+
+        let held = create_image(
+            moving(
+                false,
+                "(start: (translation: [0.3, 0.0, 0.0]), end: (translation: [0.3, 0.0, 0.0]))",
+            )
+            .to_ron(),
+        );
+        let mut moved = RaytracerScene::from_ron(small_scene(true, 4));
+        moved.objects[0].vectors[0] = vec![0.9, 0.0, -1.5];
+        assert!(similarity(held, create_image(moved.to_ron())) > 0.99);
+
+        let unblurred = moving(false, "(end: (translation: [0.6, 0.0, 0.0]))");
+        let mut blurred = moving(true, "(end: (translation: [0.6, 0.0, 0.0]))");
+        let image = create_image(blurred.to_ron());
+        assert_ne!(image, create_image(unblurred.to_ron()));
+        blurred.multithreading = false;
+        assert_eq!(image, create_image(blurred.to_ron()));
+
+        let motion: RonMotion = ron::from_str(
+            "(pivot: [1.0, 0.0, 0.0], end: (translation: [0.0, 1.0, 0.0], rotation: [0.0, 0.0, 90.0], scale: 2.0))",
+        )
+        .unwrap();
+        let triangle = Triangle::new(
+            Vec3A::new(2.0, 0.0, 0.0),
+            Vec3A::new(2.5, 0.5, 0.0),
+            Vec3A::new(2.0, 0.5, 0.5),
+            Material::Mirror,
+            false,
+        );
+        let bounds = triangle.with_motion(Motion::from_ron(&motion)).get_aabb();
+        for i in 0..=100 {
+            let time = i as f32 / 100.0;
+            // Where the first corner has been carried to at `time`.
+            let angle = std::f32::consts::FRAC_PI_2 * time;
+            let scale = 1.0 + time;
+            let corner = Vec3A::new(
+                1.0 + scale * angle.cos(),
+                1.0 * time + scale * angle.sin(),
+                0.0,
+            );
+            assert!(corner.cmpge(bounds.min).all() && corner.cmple(bounds.max).all());
+        }
+    }
+}
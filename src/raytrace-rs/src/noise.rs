@@ -1,6 +1,6 @@
+use crate::rng::Rng;
 use noise::utils::{NoiseMapBuilder, PlaneMapBuilder};
 use noise::{Fbm, MultiFractal, NoiseFn, Seedable};
-use rand::Rng;
 
 pub struct Noise {
     pub noise_map: Vec<f32>,
@@ -160,16 +160,17 @@ impl Noise {
             noise_map[i] = (noise_map[i] + lowest as f32) / (highest + lowest) as f32;
         }
 
-        if rain_factor > 0.0 {
+        // Droplets start away from the edges, so a map without an inside point gets no rain.
+        if rain_factor > 0.0 && r1 > 2 {
             for i in 0..noise_map.len() {
                 noise_map[i] = noise_map[i] * 10.0;
             }
             let min_diff = 0.001;
             let droplets = (r1 * r1) * drops_per_point;
-            let mut rng = rand::thread_rng();
+            let mut rng = Rng::new(seed_value as u64, 0);
             for _ in 0..droplets {
-                let mut d_x: i64 = rng.gen_range(1..(r1 as i64 - 1));
-                let mut d_y: i64 = rng.gen_range(1..(r1 as i64 - 1));
+                let mut d_x: i64 = rng.gen_range(1, r1 as i64 - 1);
+                let mut d_y: i64 = rng.gen_range(1, r1 as i64 - 1);
                 let mut itrs = 0;
 
                 loop {
@@ -248,3 +249,17 @@ impl Noise {
         Noise { noise_map }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rain_on_small_maps() {
+        // A 2x2 map has no inside point for droplets to start on, a 3x3 map has one.
+        for resolution in [1, 2] {
+            let noise = Noise::new(resolution, 2, 1.0, 2.0, 3, 0.5, 0.0, 1.0, 4);
+            assert_eq!(noise.noise_map.len(), (resolution + 1) * (resolution + 1));
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use crate::camera::Camera;
+use crate::configuration::RaytracerScene;
+use crate::framebuffer::{Framebuffer, Splat, Tile};
+use crate::hittables::Hittables;
+use crate::{crop_window, output_transform, to_image, trace_sample};
+use glam::Vec3A;
+use indicatif::ProgressBar;
+use std::ops::{AddAssign, Range};
+
+/// The filter weighted sum of the samples splatted into a pixel so far, along with the number of
+/// samples traced from inside it.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Accumulated {
+    pub sum: Vec3A,
+    pub weight: f32,
+    pub samples: usize,
+}
+
+impl Accumulated {
+    pub fn add(&mut self, colour: Vec3A, weight: f32) {
+        self.sum += colour * weight;
+        self.weight += weight;
+    }
+
+    pub fn mean(&self) -> Vec3A {
+        if self.weight > 0.0 {
+            self.sum / self.weight
+        } else {
+            Vec3A::ZERO
+        }
+    }
+}
+
+impl AddAssign for Accumulated {
+    fn add_assign(&mut self, other: Accumulated) {
+        self.sum += other.sum;
+        self.weight += other.weight;
+        self.samples += other.samples;
+    }
+}
+
+/// The mean radiance of every pixel, black where there are no samples yet.
+pub fn to_colours(accumulated: &Framebuffer<Accumulated>) -> Framebuffer<Vec3A> {
+    accumulated.map(Accumulated::mean)
+}
+
+/// Traces samples `samples` of the pixel at `(x, y)`, splatting them through the scene's filter.
+#[allow(clippy::too_many_arguments)]
+pub fn splat_samples(
+    settings: &RaytracerScene,
+    (x, y): (usize, usize),
+    samples: Range<usize>,
+    sample_count: usize,
+    camera: &Camera,
+    world: &Hittables,
+    splat: &mut Splat<Accumulated>,
+) {
+    let coord = [x as f32, y as f32];
+    for sample in samples.clone() {
+        let (colour, offset) = trace_sample(settings, &coord, sample, sample_count, camera, world);
+        settings
+            .filter
+            .splat((x, y), offset, colour, splat, Accumulated::add);
+    }
+    let pixel = splat.get_mut(x as isize, y as isize).unwrap();
+    pixel.samples = pixel.samples.max(samples.end);
+}
+
+/// Brings every pixel of `tiles` in `accumulated` up to `samples_per_pixel` samples, adding at
+/// most `samples_per_pass` to each per pass and calling `on_pass` with the buffer and the samples
+/// reached after each pass until it returns false. Pixels continue from the samples they already
+/// have, so a partly rendered buffer picks up exactly where it left off.
+#[allow(clippy::too_many_arguments)]
+pub fn render_passes<F>(
+    settings: &RaytracerScene,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+    pb: &ProgressBar,
+    accumulated: &mut Framebuffer<Accumulated>,
+    samples_per_pass: usize,
+    mut on_pass: F,
+) where
+    F: FnMut(&Framebuffer<Accumulated>, usize) -> bool,
+{
+    let total_samples = settings.samples_per_pixel.max(1);
+    let samples_per_pass = samples_per_pass.clamp(1, total_samples);
+    let width = accumulated.width();
+    let mut samples = tiles
+        .iter()
+        .flat_map(|tile| {
+            (tile.y..tile.y + tile.height).flat_map(move |y| {
+                let start = y * width + tile.x;
+                start..start + tile.width
+            })
+        })
+        .map(|i| accumulated.pixels()[i].samples)
+        .min()
+        .unwrap_or(0);
+    let passes = total_samples
+        .saturating_sub(samples)
+        .div_ceil(samples_per_pass);
+    pb.set_length((tiles.len() * passes) as u64);
+
+    while samples < total_samples {
+        let pass_end = (samples + samples_per_pass).min(total_samples);
+        accumulated.render_splatted(
+            tiles,
+            settings.filter.margin(),
+            crop_window(settings),
+            settings.multithreading,
+            pb,
+            |x, y, splat| {
+                let start = splat.get_mut(x as isize, y as isize).unwrap().samples;
+                splat_samples(
+                    settings,
+                    (x, y),
+                    start..pass_end,
+                    total_samples,
+                    camera,
+                    world,
+                    splat,
+                );
+            },
+        );
+        samples = pass_end;
+
+        if !on_pass(accumulated, samples) {
+            break;
+        }
+    }
+    pb.set_position((tiles.len() * passes) as u64);
+}
+
+/// Accumulates `samples_per_pass` samples for every pixel per pass into a float buffer, calling
+/// `callback` with the image so far after each pass until it returns false or every pixel has
+/// `samples_per_pixel` samples.
+pub fn render<F>(
+    settings: &RaytracerScene,
+    tiles: &[Tile],
+    camera: &Camera,
+    world: &Hittables,
+    pb: &ProgressBar,
+    samples_per_pass: usize,
+    mut callback: F,
+) -> Framebuffer<Vec3A>
+where
+    F: FnMut(&[Vec<Vec<u8>>], usize) -> bool,
+{
+    let mut accumulated = Framebuffer::new(
+        settings.image_width as usize,
+        settings.image_height as usize,
+        Accumulated::default(),
+    );
+    render_passes(
+        settings,
+        tiles,
+        camera,
+        world,
+        pb,
+        &mut accumulated,
+        samples_per_pass,
+        |accumulated, samples| {
+            callback(
+                &to_image(&output_transform(settings), &to_colours(accumulated)),
+                samples,
+            )
+        },
+    );
+
+    to_colours(&accumulated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Aperture;
+    use crate::tests::small_scene;
+    use crate::{
+        create_camera, create_image, create_image_progressive, create_objects, create_tiles,
+        create_world,
+    };
+
+    #[test]
+    fn test_progressive_render() {
+        let reference = create_image(small_scene(true, 3));
+        let mut passes = vec![];
+        let image = create_image_progressive(small_scene(true, 3), 3, |image, samples| {
+            assert_eq!(image.len(), 20);
+            passes.push(samples);
+            true
+        });
+        assert_eq!(passes, vec![3, 6, 8]);
+        assert_eq!(reference, image);
+
+        let mut calls = 0;
+        create_image_progressive(small_scene(false, 3), 1, |_, _| {
+            calls += 1;
+            false
+        });
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_resume_crop_window() {
+        let mut settings = RaytracerScene::from_ron(small_scene(false, 3));
+        settings.crop = Some(ron::from_str("(x: 5.0, y: 4.0, width: 12.0, height: 9.0)").unwrap());
+        let objects = create_objects(&settings);
+        let world = create_world(&settings, &objects);
+        let camera = create_camera(
+            &settings,
+            &objects,
+            &world,
+            &Aperture::new(&settings.aperture_shape),
+        );
+        let tiles = create_tiles(&settings);
+        let pb = ProgressBar::hidden();
+        let mut accumulated = Framebuffer::new(30, 20, Accumulated::default());
+        let mut passes = vec![];
+        render_passes(
+            &settings,
+            &tiles,
+            &camera,
+            &world,
+            &pb,
+            &mut accumulated,
+            4,
+            |_, samples| {
+                passes.push(samples);
+                true
+            },
+        );
+        assert_eq!(passes, vec![4, 8]);
+
+        // Only the window is rendered, so the pixels outside it are no reason to start again.
+        passes.clear();
+        render_passes(
+            &settings,
+            &tiles,
+            &camera,
+            &world,
+            &pb,
+            &mut accumulated,
+            4,
+            |_, samples| {
+                passes.push(samples);
+                true
+            },
+        );
+        assert!(passes.is_empty());
+    }
+}
@@ -1,16 +1,42 @@
 use glam::Vec3A;
 
+/// A moment `shutter` after the start of `frame`. Keyframes are placed at the time of the
+/// animation, `frame + shutter`, while start to end motions run over the shutter of every frame
+/// from 0 to 1.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Time {
+    pub frame: f32,
+    pub shutter: f32,
+}
+
+impl Time {
+    pub fn new(frame: f32, shutter: f32) -> Time {
+        Time { frame, shutter }
+    }
+    /// The time of the animation, where keyframes are placed.
+    pub fn animation(&self) -> f32 {
+        self.frame + self.shutter
+    }
+}
+
+/// A ray leaving `origin` along `direction` at `time` within the shutter interval, which places
+/// moving objects and cameras along their motion.
 #[derive(Debug, Copy, Clone)]
 pub struct Ray {
     origin: Vec3A,
     direction: Vec3A,
+    time: Time,
 }
 
 impl Ray {
     pub fn new(orig: Vec3A, dir: Vec3A) -> Ray {
+        Ray::new_at(orig, dir, Time::default())
+    }
+    pub fn new_at(orig: Vec3A, dir: Vec3A, time: Time) -> Ray {
         Ray {
             origin: orig,
             direction: dir,
+            time,
         }
     }
     pub fn origin(&self) -> Vec3A {
@@ -19,6 +45,9 @@ impl Ray {
     pub fn direction(&self) -> Vec3A {
         self.direction
     }
+    pub fn time(&self) -> Time {
+        self.time
+    }
     pub fn at(&self, t: f32) -> Vec3A {
         self.origin + self.direction * t
     }
@@ -0,0 +1,77 @@
+use std::cell::Cell;
+
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+
+/// A PCG32 generator. Small enough to be reseeded for every camera sample and fully specified
+/// here so images stay bit-identical across platforms and dependency versions.
+#[derive(Debug, Copy, Clone)]
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+/// SplitMix64 finaliser, used to turn neighbouring seeds into unrelated generator states.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+impl Rng {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Rng {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(mix(seed));
+        rng.next_u32();
+        rng
+    }
+
+    /// The stream for one camera sample of one pixel, independent of the order pixels and
+    /// samples are rendered in.
+    pub fn for_sample(seed: u64, pixel: u64, sample: u64) -> Self {
+        Rng::new(seed ^ mix(sample), pixel)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(PCG_MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    /// Uniform in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Uniform integer in [min, max).
+    pub fn gen_range(&mut self, min: i64, max: i64) -> i64 {
+        debug_assert!(max > min, "empty range {}..{}", min, max);
+        let span = (max - min) as u64;
+        min + ((self.next_u32() as u64 * span) >> 32) as i64
+    }
+}
+
+thread_local! {
+    static THREAD_RNG: Cell<Rng> = Cell::new(Rng::new(0, 0));
+}
+
+/// Replaces the generator used by `random()` on the current thread.
+pub fn seed_thread(rng: Rng) {
+    THREAD_RNG.with(|r| r.set(rng));
+}
+
+pub fn next_f32() -> f32 {
+    THREAD_RNG.with(|r| {
+        let mut rng = r.get();
+        let value = rng.next_f32();
+        r.set(rng);
+        value
+    })
+}
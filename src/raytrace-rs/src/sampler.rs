@@ -0,0 +1,406 @@
+use crate::random;
+use crate::rng::{self, Rng};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+/// Dimensions used by the camera for the pixel position and lens position.
+const CAMERA_DIMENSIONS: u32 = 4;
+/// Dimensions reserved for each bounce of a path. Anything a bounce draws beyond this falls back
+/// to plain random numbers rather than reusing dimensions belonging to the next bounce.
+const BOUNCE_DIMENSIONS: u32 = 16;
+
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Generates the numbers a camera sample uses for the pixel position, lens position, BSDF and
+/// light sampling. Every sampler except `Random` spreads the samples of a pixel evenly over each
+/// dimension so images converge with less noise.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Sampler {
+    /// Independent uniform random numbers.
+    #[default]
+    Random,
+    /// Jittered strata, shuffled independently for every dimension.
+    Stratified,
+    /// The Halton sequence with random digit permutations per pixel.
+    Halton,
+    /// The Sobol sequence with Owen scrambling and shuffling per pixel and dimension.
+    Sobol,
+}
+
+#[derive(Copy, Clone)]
+struct SampleState {
+    sampler: Sampler,
+    seed: u32,
+    index: u32,
+    count: u32,
+    dimension: u32,
+    end: u32,
+}
+
+thread_local! {
+    static STATE: Cell<SampleState> = const {
+        Cell::new(SampleState {
+            sampler: Sampler::Random,
+            seed: 0,
+            index: 0,
+            count: 1,
+            dimension: 0,
+            end: u32::MAX,
+        })
+    };
+}
+
+fn hash(a: u32, b: u32) -> u32 {
+    let mut x = a ^ b.wrapping_mul(0x9e3779b9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^ (x >> 16)
+}
+
+fn to_unit(x: u32) -> f32 {
+    (x >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+}
+
+/// Kensler's hashed permutation of `i` in [0, length).
+fn permute(mut i: u32, length: u32, p: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < length {
+            break;
+        }
+    }
+    (i.wrapping_add(p)) % length
+}
+
+/// Radical inverse of `index` with each digit passed through its own random permutation. The
+/// digits that tell apart the `count` samples of a pixel are permuted even when they are zero, so
+/// the samples stay stratified. Any finer digits past the last one of `index` are zeros whose
+/// permutations are uniformly random, so that tail is a single random number instead.
+fn scrambled_radical_inverse(base: u32, mut index: u32, count: u32, seed: u32) -> f32 {
+    let inverse_base = 1.0 / base as f32;
+    let mut weight = inverse_base;
+    let mut result = 0.0;
+    let mut digit = 0;
+    let mut strata = 1u32;
+    while index > 0 || strata < count {
+        result += permute(index % base, base, hash(seed, digit)) as f32 * weight;
+        index /= base;
+        weight *= inverse_base;
+        digit += 1;
+        strata = strata.saturating_mul(base);
+    }
+    (result + random() * weight * base as f32).min(0.9999999)
+}
+
+/// Laine and Karras' hash based approximation of an Owen scramble, applied to bit reversed input.
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+/// The first two dimensions of the Sobol sequence, which together form a (0, 2) sequence.
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut v = 1 << 31;
+    let mut i = index;
+    while i > 0 {
+        if i & 1 != 0 {
+            y ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    (index.reverse_bits(), y)
+}
+
+impl SampleState {
+    fn stratified(&self, dimensions: u32) -> (f32, f32) {
+        let p = hash(self.seed, self.dimension);
+        if dimensions == 1 {
+            let stratum = permute(self.index, self.count, p);
+            return ((stratum as f32 + random()) / self.count as f32, 0.0);
+        }
+        // Samples beyond the largest square grid that fits are left unstratified.
+        let n = (self.count as f32).sqrt() as u32;
+        if self.index >= n * n {
+            return (random(), random());
+        }
+        let stratum = permute(self.index, n * n, p);
+        (
+            ((stratum % n) as f32 + random()) / n as f32,
+            ((stratum / n) as f32 + random()) / n as f32,
+        )
+    }
+
+    fn halton(&self, dimensions: u32) -> (f32, f32) {
+        let value = |dimension: u32| match PRIMES.get(dimension as usize) {
+            Some(base) => {
+                scrambled_radical_inverse(*base, self.index, self.count, hash(self.seed, dimension))
+            }
+            None => random(),
+        };
+        if dimensions == 1 {
+            (value(self.dimension), 0.0)
+        } else {
+            (value(self.dimension), value(self.dimension + 1))
+        }
+    }
+
+    fn sobol(&self) -> (f32, f32) {
+        let p = hash(self.seed, self.dimension);
+        let index = owen_scramble(self.index, p);
+        let (x, y) = sobol_2d(index);
+        (
+            to_unit(owen_scramble(x, hash(p, 1))),
+            to_unit(owen_scramble(y, hash(p, 2))),
+        )
+    }
+
+    fn next(&mut self, dimensions: u32) -> (f32, f32) {
+        if self.dimension + dimensions > self.end {
+            return (random(), random());
+        }
+        let sample = match self.sampler {
+            Sampler::Random => (random(), random()),
+            Sampler::Stratified => self.stratified(dimensions),
+            Sampler::Halton => self.halton(dimensions),
+            Sampler::Sobol => self.sobol(),
+        };
+        self.dimension += dimensions;
+        sample
+    }
+}
+
+fn next(dimensions: u32) -> (f32, f32) {
+    STATE.with(|s| {
+        let mut state = s.get();
+        let sample = state.next(dimensions);
+        s.set(state);
+        sample
+    })
+}
+
+/// Starts camera sample `index` of `count` for a pixel on the current thread, also reseeding
+/// the thread's random numbers so the sample is independent of render order.
+pub fn start_sample(sampler: Sampler, seed: u64, pixel: u64, index: usize, count: usize) {
+    rng::seed_thread(Rng::for_sample(seed, pixel, index as u64));
+    STATE.with(|s| {
+        s.set(SampleState {
+            sampler,
+            seed: hash(seed as u32 ^ (seed >> 32) as u32, pixel as u32),
+            index: index as u32,
+            count: count.max(1) as u32,
+            dimension: 0,
+            end: u32::MAX,
+        })
+    });
+}
+
+/// Moves on to the dimensions reserved for bounce `depth` of the current path.
+pub fn start_bounce(depth: i32) {
+    STATE.with(|s| {
+        let mut state = s.get();
+        state.dimension = CAMERA_DIMENSIONS + depth as u32 * BOUNCE_DIMENSIONS;
+        state.end = state.dimension + BOUNCE_DIMENSIONS;
+        s.set(state);
+    });
+}
+
+pub fn get_1d() -> f32 {
+    next(1).0
+}
+
+pub fn get_2d() -> (f32, f32) {
+    next(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::RaytracerScene;
+    use crate::create_image;
+    use crate::tests::small_scene;
+
+    const SAMPLERS: [Sampler; 4] = [
+        Sampler::Random,
+        Sampler::Stratified,
+        Sampler::Halton,
+        Sampler::Sobol,
+    ];
+
+    /// The first 2D sample of every camera sample of `pixel` in the dimensions of `bounce`, or
+    /// of the camera when it is `None`.
+    fn pixel_samples(
+        sampler: Sampler,
+        pixel: u64,
+        count: usize,
+        bounce: Option<i32>,
+    ) -> Vec<(f32, f32)> {
+        (0..count)
+            .map(|index| {
+                start_sample(sampler, 9, pixel, index, count);
+                if let Some(depth) = bounce {
+                    start_bounce(depth);
+                }
+                get_2d()
+            })
+            .collect()
+    }
+
+    fn correlation(samples: &[(f32, f32)]) -> f32 {
+        let n = samples.len() as f32;
+        let (mx, my) = samples
+            .iter()
+            .fold((0.0, 0.0), |(x, y), s| (x + s.0 / n, y + s.1 / n));
+        let (mut xy, mut xx, mut yy) = (0.0, 0.0, 0.0);
+        for (x, y) in samples {
+            xy += (x - mx) * (y - my);
+            xx += (x - mx) * (x - mx);
+            yy += (y - my) * (y - my);
+        }
+        xy / (xx * yy).sqrt()
+    }
+
+    #[test]
+    fn test_samples_in_unit_interval() {
+        for sampler in SAMPLERS {
+            for pixel in 0..8 {
+                for index in 0..37 {
+                    start_sample(sampler, 3, pixel, index, 37);
+                    for depth in -1..4 {
+                        if depth >= 0 {
+                            start_bounce(depth);
+                        }
+                        for _ in 0..10 {
+                            let value = get_1d();
+                            let (x, y) = get_2d();
+                            for v in [value, x, y] {
+                                assert!((0.0..1.0).contains(&v), "{:?} gave {}", sampler, v);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_samples_are_stratified() {
+        for sampler in [Sampler::Stratified, Sampler::Sobol] {
+            for pixel in 0..4 {
+                let mut cells = pixel_samples(sampler, pixel, 16, None)
+                    .iter()
+                    .map(|(x, y)| (y * 4.0) as usize * 4 + (x * 4.0) as usize)
+                    .collect::<Vec<_>>();
+                cells.sort_unstable();
+                assert_eq!(cells, (0..16).collect::<Vec<_>>(), "{:?}", sampler);
+            }
+        }
+        for sampler in [Sampler::Stratified, Sampler::Halton, Sampler::Sobol] {
+            let mut strata = (0..16)
+                .map(|index| {
+                    start_sample(sampler, 9, 5, index, 16);
+                    (get_1d() * 16.0) as usize
+                })
+                .collect::<Vec<_>>();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..16).collect::<Vec<_>>(), "{:?}", sampler);
+        }
+    }
+
+    #[test]
+    fn test_dimensions_do_not_alias() {
+        for sampler in [Sampler::Stratified, Sampler::Halton, Sampler::Sobol] {
+            let camera = pixel_samples(sampler, 2, 256, None);
+            let bounces = (0..3)
+                .map(|depth| pixel_samples(sampler, 2, 256, Some(depth)))
+                .collect::<Vec<_>>();
+            let mut dimensions = vec![&camera];
+            dimensions.extend(&bounces);
+            for (i, a) in dimensions.iter().enumerate() {
+                for b in &dimensions[i + 1..] {
+                    let paired = a
+                        .iter()
+                        .zip(b.iter())
+                        .map(|(a, b)| (a.0, b.0))
+                        .collect::<Vec<_>>();
+                    assert!(correlation(&paired).abs() < 0.2, "{:?}", sampler);
+                }
+            }
+        }
+
+        // Drawing more than a bounce's share of dimensions leaves those of the next bounce alone.
+        let next_bounce = |overdraw: usize| {
+            start_sample(Sampler::Sobol, 9, 2, 3, 16);
+            start_bounce(0);
+            let drawn = (0..overdraw).map(|_| get_1d()).collect::<Vec<_>>();
+            start_bounce(1);
+            (drawn, get_2d())
+        };
+        let (drawn, expected) = next_bounce(0);
+        assert!(drawn.is_empty());
+        let (drawn, sample) = next_bounce(BOUNCE_DIMENSIONS as usize + 4);
+        assert_eq!(sample, expected);
+        assert!(!drawn.contains(&expected.0));
+    }
+
+    #[test]
+    fn test_sobol_converges_faster_than_random() {
+        let error = |a: &[Vec<Vec<u8>>], b: &[Vec<Vec<u8>>]| {
+            a.iter()
+                .flatten()
+                .flatten()
+                .zip(b.iter().flatten().flatten())
+                .map(|(x, y)| (*x as f32 - *y as f32).powi(2))
+                .sum::<f32>()
+        };
+        let scene = |sampler: Sampler, samples: usize, seed: u64| {
+            let mut settings = RaytracerScene::from_ron(small_scene(true, seed));
+            settings.samples_per_pixel = samples;
+            settings.sampler = sampler;
+            settings.to_ron()
+        };
+        let reference = create_image(scene(Sampler::Sobol, 1024, 1));
+        let total_error = |sampler: Sampler| {
+            (2..5)
+                .map(|seed| error(&create_image(scene(sampler, 16, seed)), &reference))
+                .sum::<f32>()
+        };
+        assert!(total_error(Sampler::Sobol) < total_error(Sampler::Random));
+    }
+}
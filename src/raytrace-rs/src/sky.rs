@@ -0,0 +1,223 @@
+use crate::configuration::RonTimeOfDay;
+use glam::Vec3A;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Converts the kcd/m^2 luminance of the sky model into the renderer's radiance units, putting
+/// a clear midday zenith at roughly a third of the default sky gradient.
+const SKY_SCALE: f32 = 1.0 / 20.0;
+/// Irradiance of the sun above the atmosphere in the same units as `SKY_SCALE`, chosen so a
+/// clear midday sun provides about four fifths of the light on the ground.
+const SUN_IRRADIANCE: f32 = 8.0;
+/// Wavelengths in micrometres used to evaluate atmospheric transmittance for each channel.
+const WAVELENGTHS: [f32; 3] = [0.65, 0.57, 0.475];
+
+/// The Preetham, Shirley and Smits analytic daylight model. Directions below the horizon see the
+/// sky at the horizon.
+pub struct PreethamSky {
+    sun_direction: Vec3A,
+    sun_zenith: f32,
+    perez_luminance: [f32; 5],
+    perez_x: [f32; 5],
+    perez_y: [f32; 5],
+    zenith: Vec3A,
+    intensity: f32,
+}
+
+fn perez(coefficients: &[f32; 5], theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / theta.cos().max(0.01)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+}
+
+fn xyy_to_rgb(xyy: Vec3A) -> Vec3A {
+    let (x, y, luminance) = (xyy.x, xyy.y, xyy.z);
+    if y <= 0.0 {
+        return Vec3A::ZERO;
+    }
+    let big_x = x * luminance / y;
+    let big_z = (1.0 - x - y) * luminance / y;
+    Vec3A::new(
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    )
+    .max(Vec3A::ZERO)
+}
+
+/// Unit vector pointing at the sun. Azimuth is measured in degrees clockwise from +z (north)
+/// towards +x (east).
+pub fn sun_direction(elevation: f32, azimuth: f32) -> Vec3A {
+    let elevation = elevation.to_radians();
+    let azimuth = azimuth.to_radians();
+    Vec3A::new(
+        elevation.cos() * azimuth.sin(),
+        elevation.sin(),
+        elevation.cos() * azimuth.cos(),
+    )
+}
+
+impl PreethamSky {
+    pub fn new(sun_direction: Vec3A, turbidity: f32, intensity: f32) -> Self {
+        let t = turbidity.max(1.0);
+        // The model is only defined with the sun above the horizon.
+        let sun_zenith = sun_direction.y.clamp(0.0, 1.0).acos().min(FRAC_PI_2 - 0.01);
+        let sun_direction = Vec3A::new(sun_direction.x, sun_zenith.cos(), sun_direction.z);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * sun_zenith);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let s = sun_zenith;
+        let zenith_x = t * t * (0.00166 * s * s * s - 0.00375 * s * s + 0.00209 * s)
+            + t * (-0.02903 * s * s * s + 0.06377 * s * s - 0.03202 * s + 0.00394)
+            + (0.11693 * s * s * s - 0.21196 * s * s + 0.06052 * s + 0.25886);
+        let zenith_y = t * t * (0.00275 * s * s * s - 0.00610 * s * s + 0.00317 * s)
+            + t * (-0.04214 * s * s * s + 0.08970 * s * s - 0.04153 * s + 0.00516)
+            + (0.15346 * s * s * s - 0.26756 * s * s + 0.06670 * s + 0.26688);
+
+        PreethamSky {
+            sun_direction: sun_direction.normalize(),
+            sun_zenith,
+            perez_luminance: [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            perez_x: [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            perez_y: [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+            zenith: Vec3A::new(zenith_x, zenith_y, zenith_luminance),
+            intensity,
+        }
+    }
+
+    pub fn radiance(&self, direction: Vec3A) -> Vec3A {
+        let d = direction.normalize();
+        let theta = d.y.clamp(0.001, 1.0).acos();
+        let gamma = d.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        let ratio = |coefficients: &[f32; 5], zenith: f32| {
+            zenith * perez(coefficients, theta, gamma) / perez(coefficients, 0.0, self.sun_zenith)
+        };
+        let xyy = Vec3A::new(
+            ratio(&self.perez_x, self.zenith.x),
+            ratio(&self.perez_y, self.zenith.y),
+            ratio(&self.perez_luminance, self.zenith.z),
+        );
+        xyy_to_rgb(xyy) * SKY_SCALE * self.intensity
+    }
+
+    /// Irradiance of the sun after Rayleigh and aerosol extinction through the atmosphere.
+    pub fn sun_irradiance(sun_direction: Vec3A, turbidity: f32, intensity: f32) -> Vec3A {
+        if sun_direction.y <= 0.0 {
+            return Vec3A::ZERO;
+        }
+        let zenith_degrees = sun_direction.y.min(1.0).acos().to_degrees();
+        let optical_mass =
+            1.0 / (sun_direction.y + 0.15 * (93.885 - zenith_degrees).max(0.01).powf(-1.253));
+        let beta = (0.04608 * turbidity.max(1.0) - 0.04586).max(0.0);
+        let transmittance = |lambda: f32| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * optical_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * optical_mass).exp();
+            rayleigh * aerosol
+        };
+        Vec3A::new(
+            transmittance(WAVELENGTHS[0]),
+            transmittance(WAVELENGTHS[1]),
+            transmittance(WAVELENGTHS[2]),
+        ) * SUN_IRRADIANCE
+            * intensity
+    }
+}
+
+fn day_of_year(year: i32, month: u32, day: u32) -> u32 {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_before = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let month_idx = (month.clamp(1, 12) - 1) as usize;
+    days_before[month_idx] + day + if leap && month > 2 { 1 } else { 0 }
+}
+
+/// Solar elevation and azimuth in degrees using the NOAA general solar position equations,
+/// which are accurate to within a fraction of a degree.
+pub fn solar_position(time: &RonTimeOfDay) -> (f32, f32) {
+    let day = day_of_year(time.year, time.month, time.day) as f32;
+    let utc_hour = time.hour - time.utc_offset;
+    let fractional_year = 2.0 * PI / 365.0 * (day - 1.0 + (utc_hour - 12.0) / 24.0);
+    let (g, g2, g3) = (
+        fractional_year,
+        2.0 * fractional_year,
+        3.0 * fractional_year,
+    );
+
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * g.cos()
+            - 0.032077 * g.sin()
+            - 0.014615 * g2.cos()
+            - 0.040849 * g2.sin());
+    let declination = 0.006918 - 0.399912 * g.cos() + 0.070257 * g.sin() - 0.006758 * g2.cos()
+        + 0.000907 * g2.sin()
+        - 0.002697 * g3.cos()
+        + 0.00148 * g3.sin();
+
+    let true_solar_minutes = utc_hour * 60.0 + equation_of_time + 4.0 * time.longitude;
+    let hour_angle = (true_solar_minutes / 4.0 - 180.0).to_radians();
+    let latitude = time.latitude.to_radians();
+
+    let cos_zenith = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .clamp(-1.0, 1.0);
+    let elevation = 90.0 - cos_zenith.acos().to_degrees();
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos())
+        .to_degrees()
+        + 180.0;
+    (elevation, azimuth.rem_euclid(360.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solar_position() {
+        let at = |latitude, longitude, (year, month, day), hour, utc_offset| {
+            solar_position(&RonTimeOfDay {
+                latitude,
+                longitude,
+                year,
+                month,
+                day,
+                hour,
+                utc_offset,
+            })
+        };
+        // Reference positions from the Astronomical Almanac's low precision formulas.
+        for ((elevation, azimuth), expected) in [
+            (at(51.4769, 0.0, (2020, 6, 20), 12.0, 0.0), (61.96, 179.17)),
+            (
+                at(-33.87, 151.21, (2021, 12, 21), 9.0, 11.0),
+                (38.49, 94.49),
+            ),
+            (
+                at(40.015, -105.27, (2023, 3, 14), 16.5, -6.0),
+                (27.78, 239.82),
+            ),
+            (at(51.4769, 0.0, (2020, 12, 21), 0.0, 0.0), (-61.96, 0.95)),
+        ] {
+            assert!((elevation - expected.0).abs() < 0.5, "{}", elevation);
+            let azimuth_error = (azimuth - expected.1 + 180.0).rem_euclid(360.0) - 180.0;
+            assert!(azimuth_error.abs() < 0.5, "{}", azimuth);
+        }
+    }
+}
@@ -1,17 +1,21 @@
 use crate::hittable;
 use crate::hittable::HitRecord;
 use crate::material;
-use crate::ray::Ray;
+use crate::motion::Motion;
+use crate::random_in_cone;
+use crate::ray::{Ray, Time};
 use crate::{aabb::AABB, hittable::set_face_normal};
 use glam::Vec3A;
+use std::f32::consts::PI;
 use std::intrinsics::{fadd_fast, fdiv_fast, fmul_fast, fsub_fast};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Sphere {
     pub center: Vec3A,
     pub radius: f32,
     material: material::Material,
     aabb: Option<AABB>,
+    motion: Option<Motion>,
 }
 
 #[allow(dead_code)]
@@ -22,21 +26,67 @@ impl Sphere {
             radius: rad,
             material: mat,
             aabb: None,
+            motion: None,
         };
         s.aabb = Some(s.get_aabb());
         s
     }
 
+    /// Moves the sphere over the shutter interval, bounding it over the whole motion.
+    pub fn with_motion(mut self, motion: Motion) -> Sphere {
+        self.aabb = Some(motion.bound(self.get_aabb()));
+        self.motion = Some(motion);
+        self
+    }
+
     pub fn get_aabb(&self) -> AABB {
         match self.aabb {
             Some(a) => a,
             None => AABB::new(self.center - self.radius, self.center + self.radius),
         }
     }
-}
 
-impl hittable::Hittable for Sphere {
-    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    pub fn get_material(&self) -> material::Material {
+        self.material
+    }
+
+    fn cos_theta_max(&self, origin: Vec3A) -> Option<f32> {
+        let dist_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if dist_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / dist_squared).sqrt())
+    }
+
+    /// Pdf of `sample_from` choosing any direction that hits the sphere from `origin`.
+    pub fn solid_angle_pdf(&self, origin: Vec3A) -> f32 {
+        match self.cos_theta_max(origin) {
+            Some(cos_theta_max) => 1.0 / (2.0 * PI * (1.0 - cos_theta_max)),
+            None => 0.0,
+        }
+    }
+
+    /// Samples a direction from `origin` uniformly over the cone the sphere subtends at `time`,
+    /// returning the point hit on the sphere and the solid angle pdf of choosing it.
+    pub fn sample_from(&self, origin: Vec3A, time: Time) -> Option<(HitRecord, f32)> {
+        match &self.motion {
+            Some(motion) => {
+                motion.sample_from(origin, time, |origin| self.sample_at_rest(origin, time))
+            }
+            None => self.sample_at_rest(origin, time),
+        }
+    }
+
+    fn sample_at_rest(&self, origin: Vec3A, time: Time) -> Option<(HitRecord, f32)> {
+        let cos_theta_max = self.cos_theta_max(origin)?;
+        let direction = random_in_cone(self.center - origin, cos_theta_max);
+
+        let rec = self.hit_at_rest(Ray::new_at(origin, direction, time), 0.0, f32::INFINITY)?;
+        Some((rec, 1.0 / (2.0 * PI * (1.0 - cos_theta_max))))
+    }
+
+    fn hit_at_rest(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         unsafe {
             let oc: Vec3A = ray.origin() - self.center;
             let a = ray.direction().length_squared();
@@ -60,6 +110,14 @@ impl hittable::Hittable for Sphere {
                         t: temp1,
                         material: self.material,
                         front_face,
+                        light_pdf: if self.material.is_emissive() {
+                            self.solid_angle_pdf(ray.origin())
+                        } else {
+                            0.0
+                        },
+                        time: ray.time(),
+                        uv: sphere_uv(outward_normal),
+                        rest_p: p,
                     });
                 }
             }
@@ -67,3 +125,22 @@ impl hittable::Hittable for Sphere {
         }
     }
 }
+
+/// Longitude and latitude of a point on the unit sphere, `u` running around the y axis from -x
+/// through +z and `v` up from the bottom pole.
+fn sphere_uv(point: Vec3A) -> (f32, f32) {
+    let theta = (-point.y).clamp(-1.0, 1.0).acos();
+    let phi = (-point.z).atan2(point.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+impl hittable::Hittable for Sphere {
+    fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        match &self.motion {
+            Some(motion) => motion.hit(ray, t_min, t_max, |ray, t_min, t_max| {
+                self.hit_at_rest(ray, t_min, t_max)
+            }),
+            None => self.hit_at_rest(ray, t_min, t_max),
+        }
+    }
+}
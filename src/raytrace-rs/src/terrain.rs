@@ -1,7 +1,8 @@
 use crate::colour_map::ColourMap;
 use crate::hittables::HittableObject;
-use crate::material::Material::Lambertian;
+use crate::material::Material;
 use crate::noise::Noise;
+use crate::texture::Texture;
 use crate::triangle::Triangle;
 use glam::Vec3A;
 use std::ops::Add;
@@ -61,8 +62,8 @@ impl Terrain {
                     let i0j1 = self.ground_points[(((i + 0) * r1) + (j + 1)) as usize];
                     let i1j0 = self.ground_points[(((i + 1) * r1) + (j + 0)) as usize];
                     let i1j1 = self.ground_points[(((i + 1) * r1) + (j + 1)) as usize];
-                    let color1: Vec3A;
-                    let color2: Vec3A;
+                    let material1: Material;
+                    let material2: Material;
 
                     match &colour_map {
                         Some(colour_map_) => {
@@ -74,28 +75,22 @@ impl Terrain {
                                 + i1j1.y / height_scale
                                 + i0j1.y / height_scale)
                                 / 3.0;
-                            color1 = colour_map_.to_colour(height1);
-                            color2 = colour_map_.to_colour(height2);
+                            material1 = colour_map_.to_material(height1);
+                            material2 = colour_map_.to_material(height2);
                         }
                         None => {
-                            color1 = Vec3A::new(0.2, 0.8, 0.4);
-                            color2 = Vec3A::new(0.2, 0.8, 0.4);
+                            material1 =
+                                Material::Lambertian(Texture::Constant(Vec3A::new(0.2, 0.8, 0.4)));
+                            material2 =
+                                Material::Lambertian(Texture::Constant(Vec3A::new(0.2, 0.8, 0.4)));
                         }
                     }
 
                     hittables.push(HittableObject::TriangleObj(Triangle::new(
-                        i0j1,
-                        i0j0,
-                        i1j0,
-                        Lambertian(color1),
-                        false,
+                        i0j1, i0j0, i1j0, material1, false,
                     )));
                     hittables.push(HittableObject::TriangleObj(Triangle::new(
-                        i1j0,
-                        i1j1,
-                        i0j1,
-                        Lambertian(color2),
-                        false,
+                        i1j0, i1j1, i0j1, material2, false,
                     )));
                 }
             }
@@ -0,0 +1,360 @@
+//! Colours that vary over the surface of an object, looked up where a ray hits it.
+use crate::configuration::{NoisePattern, RaytracerScene, RonTexture};
+use crate::hittable::HitRecord;
+use glam::Vec3A;
+use noise::{Fbm, MultiFractal, NoiseFn, Seedable};
+
+/// The colour of a `Lambertian` or `Metal` material. Materials are copied into every hit record,
+/// so anything larger than one colour is a `Pattern` in the scene's `Textures`, referred to by
+/// its index there.
+#[derive(Debug, Copy, Clone)]
+pub enum Texture {
+    Constant(Vec3A),
+    Pattern(usize),
+}
+
+#[derive(Debug)]
+pub enum Pattern {
+    Checker { even: Vec3A, odd: Vec3A, scale: f32 },
+    Image(ImageTexture),
+    Noise(NoiseTexture),
+}
+
+/// A linear RGB image stored row by row from the top left corner.
+#[derive(Debug)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3A>,
+}
+
+#[derive(Debug)]
+pub struct NoiseTexture {
+    pattern: NoisePattern,
+    colours: [Vec3A; 2],
+    scale: f32,
+    octaves: usize,
+    perlin: Fbm,
+}
+
+/// The patterns of a scene's textures, each built once however many objects share it and kept
+/// with the rest of the built scene.
+#[derive(Debug, Default)]
+pub struct Textures {
+    patterns: Vec<Pattern>,
+}
+
+/// The distinct textures of the scene's objects that are more than one colour, in the order of
+/// their patterns in the scene's `Textures`.
+pub fn patterned(settings: &RaytracerScene) -> Vec<&RonTexture> {
+    let mut patterned: Vec<&RonTexture> = vec![];
+    for texture in settings
+        .objects
+        .iter()
+        .filter_map(|obj| obj.texture.as_ref())
+    {
+        if !matches!(texture, RonTexture::Constant { .. }) && !patterned.contains(&texture) {
+            patterned.push(texture);
+        }
+    }
+    patterned
+}
+
+fn colour(values: &[f32], name: &str) -> Vec3A {
+    if values.len() != 3 {
+        panic!("the {} of a texture must be an RGB colour", name);
+    }
+    Vec3A::new(values[0], values[1], values[2])
+}
+
+impl Textures {
+    pub fn new(settings: &RaytracerScene) -> Textures {
+        Textures {
+            patterns: patterned(settings)
+                .into_iter()
+                .map(Pattern::from_ron)
+                .collect(),
+        }
+    }
+}
+
+impl Texture {
+    /// The texture for `texture`, whose pattern is found among the scene's `patterned` textures.
+    pub fn from_ron(texture: &RonTexture, patterned: &[&RonTexture]) -> Texture {
+        match texture {
+            RonTexture::Constant { colour: values } => Texture::Constant(colour(values, "colour")),
+            _ => Texture::Pattern(
+                patterned
+                    .iter()
+                    .position(|other| *other == texture)
+                    .expect("the texture is not one of the scene's textures"),
+            ),
+        }
+    }
+
+    /// The colour at the texture coordinates of the hit, or for solid textures the point hit on
+    /// the object at its rest position, so they move with it.
+    pub fn value(&self, rec: &HitRecord, textures: &Textures) -> Vec3A {
+        let index = match self {
+            Texture::Constant(colour) => return *colour,
+            Texture::Pattern(index) => *index,
+        };
+        match &textures.patterns[index] {
+            Pattern::Checker { even, odd, scale } => {
+                let x = (rec.uv.0 * scale).floor() as i64;
+                let y = (rec.uv.1 * scale).floor() as i64;
+                if (x + y).rem_euclid(2) == 0 {
+                    *even
+                } else {
+                    *odd
+                }
+            }
+            Pattern::Image(image) => image.sample(rec.uv),
+            Pattern::Noise(noise) => noise.value(rec.rest_p),
+        }
+    }
+}
+
+impl Pattern {
+    fn from_ron(texture: &RonTexture) -> Pattern {
+        match texture {
+            RonTexture::Constant { .. } => unreachable!("constant textures have no pattern"),
+            RonTexture::Checker { even, odd, scale } => Pattern::Checker {
+                even: colour(even, "even squares"),
+                odd: colour(odd, "odd squares"),
+                scale: *scale,
+            },
+            RonTexture::Image { path } => {
+                Pattern::Image(ImageTexture::load(path).unwrap_or_else(|e| panic!("{}", e)))
+            }
+            RonTexture::Noise {
+                pattern,
+                colours,
+                scale,
+                octaves,
+                seed,
+            } => {
+                if colours.len() != 2 {
+                    panic!("a noise texture blends between two colours");
+                }
+                Pattern::Noise(NoiseTexture {
+                    pattern: *pattern,
+                    colours: [
+                        colour(&colours[0], "colours"),
+                        colour(&colours[1], "colours"),
+                    ],
+                    scale: *scale,
+                    octaves: *octaves,
+                    perlin: Fbm::new().set_seed(*seed).set_octaves(1),
+                })
+            }
+        }
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl ImageTexture {
+    /// Loads a PNG or JPEG file, decoding its sRGB colours to linear ones.
+    pub fn load(path: &str) -> Result<ImageTexture, String> {
+        let image = image::open(path)
+            .map_err(|e| format!("unable to read texture {}: {}", path, e))?
+            .into_rgb8();
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                Vec3A::new(
+                    srgb_to_linear(pixel[0]),
+                    srgb_to_linear(pixel[1]),
+                    srgb_to_linear(pixel[2]),
+                )
+            })
+            .collect();
+        Ok(ImageTexture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels,
+        })
+    }
+
+    fn get(&self, x: i64, y: i64) -> Vec3A {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    /// Bilinearly filters the image at `uv`, with `v` running up from the bottom row and the
+    /// image repeating outside of `[0, 1]`.
+    fn sample(&self, uv: (f32, f32)) -> Vec3A {
+        let x = uv.0.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let y = (1.0 - uv.1.rem_euclid(1.0)) * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.get(x0, y0).lerp(self.get(x0 + 1, y0), fx);
+        let bottom = self.get(x0, y0 + 1).lerp(self.get(x0 + 1, y0 + 1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+impl NoiseTexture {
+    // A single octave of `Fbm` is Perlin noise, which the octaves here sum the magnitudes of.
+    fn turbulence(&self, point: Vec3A) -> f32 {
+        let mut point = point;
+        let mut weight = 1.0;
+        let mut sum = 0.0;
+        for _ in 0..self.octaves {
+            let noise = self
+                .perlin
+                .get([point.x as f64, point.y as f64, point.z as f64]);
+            sum += weight * noise.abs() as f32;
+            weight *= 0.5;
+            point *= 2.0;
+        }
+        sum
+    }
+
+    fn value(&self, point: Vec3A) -> Vec3A {
+        let point = point * self.scale;
+        let blend = match self.pattern {
+            NoisePattern::Turbulence => self.turbulence(point).min(1.0),
+            NoisePattern::Marble => 0.5 * (1.0 + (point.x + 10.0 * self.turbulence(point)).sin()),
+            NoisePattern::Wood => {
+                let radius = (point.x * point.x + point.z * point.z).sqrt();
+                (radius + self.turbulence(point)).fract()
+            }
+        };
+        self.colours[0].lerp(self.colours[1], blend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+    use crate::material::Material;
+    use crate::ray::{Ray, Time};
+    use crate::tests::{small_scene, temp_path};
+    use crate::triangle::Triangle;
+    use crate::{check_scene, create_image, create_objects, image_file};
+
+    #[test]
+    fn test_textures() {
+        let at = |uv: (f32, f32), p: Vec3A| HitRecord {
+            p,
+            normal: Vec3A::Y,
+            t: 1.0,
+            material: Material::Mirror,
+            front_face: true,
+            light_pdf: 0.0,
+            time: Time::default(),
+            uv,
+            rest_p: p,
+        };
+        let texture = |material: Material| match material {
+            Material::Lambertian(texture) | Material::Metal(texture, _) => texture,
+            _ => panic!("the material has no texture"),
+        };
+        let textured = |textures: Vec<Option<RonTexture>>| {
+            let mut settings = RaytracerScene::from_ron(small_scene(false, 2));
+            for (obj, texture) in settings.objects.iter_mut().zip(textures) {
+                obj.texture = texture;
+            }
+            settings
+        };
+
+        // The front of the diffuse sphere faces the camera, a quarter of the way around from -z
+        // and halfway up.
+        let checker =
+            ron::from_str("Checker(even: [1.0, 1.0, 1.0], odd: [0.0, 0.0, 0.0], scale: 4.0)")
+                .unwrap();
+        let settings = textured(vec![None, Some(checker)]);
+        let objects = create_objects(&settings);
+        let textures = Textures::new(&settings);
+        let ray = Ray::new(Vec3A::new(-0.7, 0.0, -3.0), Vec3A::Z);
+        let rec = objects[1].hit(ray, 0.001, f32::INFINITY).unwrap();
+        assert!((rec.uv.0 - 0.75).abs() < 1e-4 && (rec.uv.1 - 0.5).abs() < 1e-4);
+        let checker = texture(rec.material);
+        assert_eq!(checker.value(&rec, &textures), Vec3A::ZERO);
+        assert_eq!(checker.value(&at((0.1, 0.1), rec.p), &textures), Vec3A::ONE);
+        assert_eq!(create_image(settings.to_ron()).len(), 20);
+        let mut glass = settings;
+        glass.objects[0].material = vec!["Dielectric".to_string(), "1.5".to_string()];
+        glass.objects[0].texture = Some(RonTexture::Constant {
+            colour: vec![1.0, 1.0, 1.0],
+        });
+        assert!(check_scene(&glass.to_ron())
+            .unwrap_err()
+            .contains("not Dielectric"));
+
+        let triangle = Triangle::new(
+            Vec3A::new(0.0, 0.0, 0.0),
+            Vec3A::new(1.0, 0.0, 0.0),
+            Vec3A::new(0.0, 1.0, 0.0),
+            Material::Mirror,
+            false,
+        )
+        .with_uvs([(0.0, 0.0), (2.0, 0.0), (0.0, 2.0)]);
+        let ray = Ray::new(Vec3A::new(0.25, 0.5, 1.0), -Vec3A::Z);
+        let rec = triangle.hit(ray, 0.001, f32::INFINITY).unwrap();
+        assert!((rec.uv.0 - 0.5).abs() < 1e-5 && (rec.uv.1 - 1.0).abs() < 1e-5);
+
+        let path = temp_path("texture.png");
+        let rows = vec![
+            vec![vec![255, 0, 0], vec![0, 255, 0]],
+            vec![vec![0, 0, 255], vec![255, 255, 255]],
+        ];
+        image_file::save(&rows, &path).unwrap();
+        let image = Some(RonTexture::Image { path: path.clone() });
+        let settings = textured(vec![image.clone(), image]);
+        let objects = create_objects(&settings);
+        let textures = Textures::new(&settings);
+        std::fs::remove_file(&path).unwrap();
+        // The scene loads the image once for both of the spheres using it.
+        let image = texture(objects[0].get_material());
+        assert!(matches!(image, Texture::Pattern(0)));
+        assert!(matches!(
+            texture(objects[1].get_material()),
+            Texture::Pattern(0)
+        ));
+        let colour = |uv| image.value(&at(uv, Vec3A::ZERO), &textures);
+        // v runs up the image from its bottom row.
+        assert_eq!(colour((0.25, 0.75)), Vec3A::X);
+        assert_eq!(colour((0.75, 0.25)), Vec3A::ONE);
+        assert_eq!(colour((1.25, 1.75)), Vec3A::X);
+        assert!(colour((0.5, 0.5)).abs_diff_eq(Vec3A::new(0.5, 0.5, 0.5), 1e-5));
+
+        for pattern in [
+            NoisePattern::Turbulence,
+            NoisePattern::Marble,
+            NoisePattern::Wood,
+        ] {
+            let settings = textured(vec![Some(RonTexture::Noise {
+                pattern,
+                colours: vec![vec![0.0, 0.0, 0.0], vec![1.0, 0.5, 0.0]],
+                scale: 3.0,
+                octaves: 7,
+                seed: 1,
+            })]);
+            let textures = Textures::new(&settings);
+            let noise = texture(create_objects(&settings)[0].get_material());
+            let values: Vec<Vec3A> = (0..64)
+                .map(|i| Vec3A::new(i as f32 * 0.13, 0.4, 0.7))
+                .map(|p| noise.value(&at((0.0, 0.0), p), &textures))
+                .collect();
+            for value in &values {
+                assert!((0.0..=1.0).contains(&value.x) && (value.y - value.x / 2.0).abs() < 1e-5);
+            }
+            assert!(values
+                .iter()
+                .any(|value| (value.x - values[0].x).abs() > 0.1));
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::environment::luminance;
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+
+/// How radiance above 1.0 is brought into the displayable range.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ToneMap {
+    /// Clips each channel at 1.0.
+    #[default]
+    Clamp,
+    /// Scales colours by `1 / (1 + luminance)`, compressing highlights while keeping their hue.
+    Reinhard,
+    /// John Hable's filmic curve with a toe for the shadows and a white point of 11.2.
+    Filmic,
+    /// Krzysztof Narkowicz's fit of the ACES reference rendering transform.
+    Aces,
+}
+
+/// The curve that encodes display linear values into the 8 bit output.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Transfer {
+    /// A plain square root, which is what the renderer has always written.
+    #[default]
+    Gamma2,
+    /// The piecewise sRGB OETF that image viewers expect.
+    Srgb,
+}
+
+fn hable(x: Vec3A) -> Vec3A {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+impl ToneMap {
+    pub fn apply(&self, colour: Vec3A) -> Vec3A {
+        let colour = colour.max(Vec3A::ZERO);
+        let mapped = match self {
+            ToneMap::Clamp => colour,
+            ToneMap::Reinhard => colour / (1.0 + luminance(colour)),
+            ToneMap::Filmic => {
+                const WHITE: f32 = 11.2;
+                // Hable's curve is tuned for a 2x exposure boost before it.
+                hable(colour * 2.0) / hable(Vec3A::splat(WHITE))
+            }
+            ToneMap::Aces => {
+                (colour * (2.51 * colour + 0.03)) / (colour * (2.43 * colour + 0.59) + 0.14)
+            }
+        };
+        mapped.clamp(Vec3A::ZERO, Vec3A::ONE)
+    }
+}
+
+impl Transfer {
+    pub fn encode(&self, value: f32) -> f32 {
+        match self {
+            Transfer::Gamma2 => value.sqrt(),
+            Transfer::Srgb => {
+                if value <= 0.0031308 {
+                    12.92 * value
+                } else {
+                    1.055 * value.powf(1.0 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+
+    pub fn decode(&self, value: f32) -> f32 {
+        match self {
+            Transfer::Gamma2 => value * value,
+            Transfer::Srgb => {
+                if value <= 0.04045 {
+                    value / 12.92
+                } else {
+                    ((value + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+}
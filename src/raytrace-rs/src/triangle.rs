@@ -1,17 +1,22 @@
 use crate::hittable::{self, HitRecord};
 use crate::material::Material;
-use crate::ray::Ray;
+use crate::motion::Motion;
+use crate::ray::{Ray, Time};
+use crate::sampler;
 use crate::{aabb::AABB, hittable::set_face_normal};
 use glam::Vec3A;
 use std::intrinsics::{fadd_fast, fdiv_fast, fmul_fast};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Triangle {
     points: [Vec3A; 3],
     normal: Vec3A,
+    area: f32,
     material: Material,
     culling: bool,
+    uvs: [(f32, f32); 3],
     aabb: Option<AABB>,
+    motion: Option<Motion>,
 }
 
 impl Triangle {
@@ -23,23 +28,50 @@ impl Triangle {
         cull_back_face: bool,
     ) -> Triangle {
         let points_ = [point1, point2, point3];
-        let normal_ = {
+        let cross = {
             let a = point2 - point1;
             let b = point3 - point1;
-            a.cross(b).normalize()
+            a.cross(b)
         };
 
         let mut t = Triangle {
             points: points_,
-            normal: normal_,
+            normal: cross.normalize(),
+            area: cross.length() * 0.5,
             material: mat,
             culling: cull_back_face,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
             aabb: None,
+            motion: None,
         };
         t.aabb = Some(t.get_aabb());
         t
     }
 
+    /// Moves the triangle over the shutter interval, bounding it over the whole motion.
+    pub fn with_motion(mut self, motion: Motion) -> Triangle {
+        self.aabb = Some(motion.bound(self.get_aabb()));
+        self.motion = Some(motion);
+        self
+    }
+
+    /// Gives the triangle's points the texture coordinates `uvs`.
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Triangle {
+        self.uvs = uvs;
+        self
+    }
+
+    /// Texture coordinates of the point with barycentric weights `b1` and `b2` for the second and
+    /// third points.
+    fn uv_at(&self, b1: f32, b2: f32) -> (f32, f32) {
+        let b0 = 1.0 - b1 - b2;
+        let [uv0, uv1, uv2] = self.uvs;
+        (
+            uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2,
+            uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2,
+        )
+    }
+
     pub fn get_aabb(&self) -> AABB {
         match self.aabb {
             Some(a) => a,
@@ -70,10 +102,78 @@ impl Triangle {
             }
         }
     }
+
+    pub fn get_material(&self) -> Material {
+        self.material
+    }
+
+    /// Pdf with respect to solid angle of uniformly sampling `point` on the triangle from
+    /// `origin`.
+    pub fn solid_angle_pdf(&self, origin: Vec3A, point: Vec3A) -> f32 {
+        let to_point = point - origin;
+        let cosine = (self.normal.dot(to_point.normalize())).abs();
+        if cosine < 1e-6 {
+            return 0.0;
+        }
+        to_point.length_squared() / (cosine * self.area)
+    }
+
+    /// Samples a point uniformly over the triangle's area at `time`, returning the hit it would
+    /// produce from `origin` and the solid angle pdf of choosing it. A triangle with back face
+    /// culling can not be seen from behind, so nothing is sampled when `origin` is behind it.
+    pub fn sample_from(&self, origin: Vec3A, time: Time) -> Option<(HitRecord, f32)> {
+        match &self.motion {
+            Some(motion) => {
+                motion.sample_from(origin, time, |origin| self.sample_at_rest(origin, time))
+            }
+            None => self.sample_at_rest(origin, time),
+        }
+    }
+
+    fn sample_at_rest(&self, origin: Vec3A, time: Time) -> Option<(HitRecord, f32)> {
+        if self.culling && self.normal.dot(self.points[0] - origin) >= 0.0 {
+            return None;
+        }
+        let (s, t) = sampler::get_2d();
+        let su0 = s.sqrt();
+        let b0 = 1.0 - su0;
+        let b1 = t * su0;
+        let point = self.points[0] * b0 + self.points[1] * b1 + self.points[2] * (1.0 - b0 - b1);
+        let pdf = self.solid_angle_pdf(origin, point);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let (front_face, normal) = set_face_normal(Ray::new(origin, point - origin), self.normal);
+        Some((
+            HitRecord {
+                p: point,
+                normal,
+                t: 1.0,
+                material: self.material,
+                front_face,
+                light_pdf: pdf,
+                time,
+                uv: self.uv_at(b1, 1.0 - b0 - b1),
+                rest_p: point,
+            },
+            pdf,
+        ))
+    }
 }
 
 impl hittable::Hittable for Triangle {
     fn hit(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        match &self.motion {
+            Some(motion) => motion.hit(ray, t_min, t_max, |ray, t_min, t_max| {
+                self.hit_at_rest(ray, t_min, t_max)
+            }),
+            None => self.hit_at_rest(ray, t_min, t_max),
+        }
+    }
+}
+
+impl Triangle {
+    fn hit_at_rest(&self, ray: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         unsafe {
             let vertex0 = self.points[0];
             let vertex1 = self.points[1];
@@ -115,7 +215,49 @@ impl hittable::Hittable for Triangle {
                 t,
                 material: self.material,
                 front_face,
+                light_pdf: if self.material.is_emissive() {
+                    self.solid_angle_pdf(ray.origin(), intersection_point)
+                } else {
+                    0.0
+                },
+                time: ray.time(),
+                uv: self.uv_at(u, v),
+                rest_p: intersection_point,
             })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Hittable;
+
+    #[test]
+    fn test_culled_triangle_is_not_sampled_from_behind() {
+        let light = |culling: bool| {
+            Triangle::new(
+                Vec3A::new(0.0, 0.0, 0.0),
+                Vec3A::new(1.0, 0.0, 0.0),
+                Vec3A::new(0.0, 1.0, 0.0),
+                Material::Emissive(Vec3A::ONE, 1.0),
+                culling,
+            )
+        };
+        let front = Vec3A::new(0.2, 0.2, 1.0);
+        let behind = Vec3A::new(0.2, 0.2, -1.0);
+        let towards = |origin: Vec3A| Ray::new(origin, Vec3A::new(0.25, 0.25, 0.0) - origin);
+
+        let culled = light(true);
+        assert!(culled.sample_from(front, Time::default()).is_some());
+        assert!(culled.hit(towards(front), 0.001, f32::INFINITY).is_some());
+        assert!(culled.sample_from(behind, Time::default()).is_none());
+        assert!(culled.hit(towards(behind), 0.001, f32::INFINITY).is_none());
+
+        let two_sided = light(false);
+        assert!(two_sided.sample_from(behind, Time::default()).is_some());
+        assert!(two_sided
+            .hit(towards(behind), 0.001, f32::INFINITY)
+            .is_some());
+    }
+}
@@ -0,0 +1,48 @@
+import pytest
+
+import pyrays
+
+
+def test_solid_background():
+    x = pyrays.SolidBackground([0, 0, 0])
+    assert isinstance(x, pyrays.SolidBackground)
+    assert 'Solid(colour: [0.0, 0.0, 0.0])' == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.SolidBackground('a')
+
+
+def test_gradient_background():
+    x = pyrays.GradientBackground([1, 1, 1], [0.5, 0.7, 1.0])
+    assert isinstance(x, pyrays.GradientBackground)
+    assert 'Gradient(bottom: [1.0, 1.0, 1.0], top: [0.5, 0.7, 1.0])' == x._to_ron()
+
+
+def test_image_background():
+    x = pyrays.ImageBackground('studio.hdr', 2, 90)
+    assert isinstance(x, pyrays.ImageBackground)
+    assert 'Image(path: "studio.hdr", intensity: 2.0, rotation: 90.0)' == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.ImageBackground(1.0)
+
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.set_background('a')
+
+
+def test_sky_background():
+    x = pyrays.SkyBackground(30, 90)
+    assert isinstance(x, pyrays.SkyBackground)
+    assert ('Sky(sun_elevation: 30.0, sun_azimuth: 90.0, turbidity: 3.0, intensity: 1.0, '
+            'sun: true, sun_angular_diameter: 0.53, time_of_day: None)') == x._to_ron()
+
+    t = pyrays.TimeOfDay(51.5, -0.1, 2024, 6, 21, 18, 1)
+    x = pyrays.SkyBackground(time_of_day=t, sun=False)
+    assert ('Sky(sun_elevation: 45.0, sun_azimuth: 0.0, turbidity: 3.0, intensity: 1.0, '
+            'sun: false, sun_angular_diameter: 0.53, time_of_day: Some((latitude: 51.5, '
+            'longitude: -0.1, year: 2024, month: 6, day: 21, hour: 18.0, utc_offset: 1.0)))'
+            ) == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.SkyBackground(time_of_day='noon')
@@ -0,0 +1,35 @@
+import pytest
+
+import pyrays
+
+
+def test_point_light():
+    x = pyrays.PointLight([1, 2, 3], intensity=4)
+    assert isinstance(x, pyrays.PointLight)
+    assert ('Point(position: [1.0, 2.0, 3.0], colour: [1.0, 1.0, 1.0], intensity: 4.0, '
+            'falloff: true)') == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.PointLight('a')
+    with pytest.raises(TypeError):
+        pyrays.PointLight([1, 2, 3], intensity='a')
+
+
+def test_directional_light():
+    x = pyrays.DirectionalLight([0, -1, 0], [1.0, 0.9, 0.8], 2.0)
+    assert isinstance(x, pyrays.DirectionalLight)
+    assert ('Directional(direction: [0.0, -1.0, 0.0], colour: [1.0, 0.9, 0.8], intensity: 2.0, '
+            'angular_diameter: 0.0)') == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.DirectionalLight([0, -1])
+
+
+def test_spot_light():
+    x = pyrays.SpotLight([0, 5, 0], [0, -1, 0], 30, 5)
+    assert isinstance(x, pyrays.SpotLight)
+    assert ('Spot(position: [0.0, 5.0, 0.0], direction: [0.0, -1.0, 0.0], cone_angle: 30.0, '
+            'soft_edge: 5.0, colour: [1.0, 1.0, 1.0], intensity: 1.0)') == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.SpotLight([0, 5, 0], [0, -1, 0], 'a')
@@ -50,3 +50,12 @@ def test_metal():
         pyrays.Metal("a", 1.0)
     with pytest.raises(TypeError):
         pyrays.Metal([255.0, 255.0, 255.0], "a")
+
+
+def test_emissive():
+    x = pyrays.Emissive([1.0, 0.5, 0.25], 4)
+    assert isinstance(x, pyrays.Emissive)
+    assert '["Emissive", "1.0", "0.5", "0.25", "4.0"]' == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.Emissive([1.0, 0.5, 0.25], 'a')
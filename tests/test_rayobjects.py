@@ -37,6 +37,14 @@ def test_triangle():
     with pytest.raises(TypeError):
         pyrays.Triangle([1, 1, 1], [2, 2, 2], [3, 3, 3], 'a', False)
 
+
+def test_triangle_uvs():
+    with pytest.raises(TypeError):
+        pyrays.Triangle([0, 0, 0], [1, 0, 0], [0, 1, 0], pyrays.Mirror(), False, uvs=[(0, 0)])
+    with pytest.raises(TypeError):
+        pyrays.Triangle([0, 0, 0], [1, 0, 0], [0, 1, 0], pyrays.Mirror(), False,
+                        uvs=[(0, 0), (1, 0), (0, 1, 0)])
+
 def test_square():
     sqr = pyrays.Square([-1.0, 1.0, -1.0],
                         [1.0, 1.0, -1.0],
@@ -116,3 +124,36 @@ def test_procedural_terrain():
         pyrays.ProceduralTerrain([-1, 0, -1], [1, 0, 1], 'a', x)
     with pytest.raises(TypeError):
         pyrays.ProceduralTerrain([-1, 0, -1], [1, 0, 1], 2, 'a')
+
+def test_motion():
+    x = pyrays.Motion(pyrays.Transform(translation=[1, 0, 0], scale=2))
+    assert x._to_ron() == ('Some((pivot: [0.0, 0.0, 0.0], start: (translation: [0.0, 0.0, 0.0], '
+                           'rotation: [0.0, 0.0, 0.0], scale: 1.0), end: (translation: '
+                           '[1.0, 0.0, 0.0], rotation: [0.0, 0.0, 0.0], scale: 2.0)))')
+
+    with pytest.raises(TypeError):
+        pyrays.Motion([1, 0, 0])
+    with pytest.raises(TypeError):
+        pyrays.Transform(scale='a')
+    with pytest.raises(TypeError):
+        pyrays.Sphere([0.0, 0.0, 0.0], 1.0, pyrays.Mirror(), motion=pyrays.Transform())
+
+def test_keyframes():
+    x = pyrays.Keyframes(interpolation='Step').add(0, translation=[0, 0, 0]).add(2, scale=2)
+    assert x._to_ron() == ('Some((pivot: [0.0, 0.0, 0.0], interpolation: Step, translation: '
+                           '[(0.0, [0.0, 0.0, 0.0])], rotation: [], scale: [(2.0, 2.0)]))')
+
+    with pytest.raises(TypeError):
+        pyrays.Keyframes(interpolation='Cubic')
+    with pytest.raises(TypeError):
+        pyrays.Keyframes().add('a', scale=2)
+    with pytest.raises(TypeError):
+        pyrays.Sphere([0, 0, 0], 1.0, pyrays.Mirror(), motion=pyrays.Motion(pyrays.Transform()),
+                      keyframes=pyrays.Keyframes())
+
+def test_object_name():
+    x = pyrays.Sphere([0, 0, 0], 1.0, pyrays.Mirror(), name='ball')
+    assert x._to_ron().endswith(', name: Some("ball"))')
+
+    with pytest.raises(TypeError):
+        pyrays.Sphere([0, 0, 0], 1.0, pyrays.Mirror(), name=3)
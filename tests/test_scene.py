@@ -83,3 +83,117 @@ def test_single_matches_multi():
         assert imagehash.average_hash(single) - imagehash.average_hash(multi) < cutoff
         assert imagehash.average_hash(known_good_image) - imagehash.average_hash(multi) < cutoff
         assert imagehash.average_hash(single) - imagehash.average_hash(known_good_image) < cutoff
+
+def test_checkpoint_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, resume=True)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, checkpoint='a.ckpt', noise_threshold=0.01)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, checkpoint='a.ckpt', callback=lambda *_: True)
+    with pytest.raises(TypeError):
+        pyrays.merge_checkpoints([])
+
+def test_worker_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, worker=(2, 2))
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, worker=(0, 2), noise_threshold=0.01)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, worker=(0, 2), checkpoint='a.ckpt')
+    with pytest.raises(TypeError):
+        pyrays.assemble_partials([])
+
+def test_crop_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, crop=(0, 0, 1))
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, crop=(0, 0, 1, 'a'))
+
+def test_output_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, tone_map='Linear')
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, transfer='Rec709')
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, exposure='a')
+    with pytest.raises(TypeError):
+        pyrays.merge_checkpoints(['a.ckpt'], transfer='Linear')
+
+def test_linear_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, linear=True, callback=lambda *_: True)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, linear=True, workers=2)
+
+def test_filter_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, filter='Sinc')
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, filter='Gaussian', filter_radius='a')
+
+def test_shutter_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.raytrace(1, 1, 1, 1, False, shutter=(0.0, 0.5, 1.0))
+    with pytest.raises(TypeError):
+        scene.set_camera_end('a', [0.0, 0.0, 0.0], [0.0, 1.0, 0.0])
+
+def test_animation_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.add_camera_keyframe(0, camera_pos='a')
+    with pytest.raises(TypeError):
+        scene.add_light_keyframe(-1, 0, [0.0, 0.0, 0.0])
+    with pytest.raises(TypeError):
+        scene.set_interpolation('Cubic')
+    with pytest.raises(TypeError):
+        scene.render_sequence('frame_####.png', 2, 1, 1, 1, 1, 1, False)
+    with pytest.raises(TypeError):
+        scene.render_sequence('frame_####.png', 0, 1, 1, 1, 1, 1, False, resume=True)
+
+def test_projection_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.set_projection('Cylindrical')
+    with pytest.raises(TypeError):
+        scene.set_projection('Orthographic')
+    with pytest.raises(TypeError):
+        scene.set_projection('Fisheye', mapping='Stereographic')
+    with pytest.raises(TypeError):
+        scene.set_stereo(0.06, layout='Anaglyph')
+
+def test_physical_camera_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.set_physical_camera(50.0, 2.8, sensor=36.0)
+    with pytest.raises(TypeError):
+        scene.set_physical_camera(50.0, 'a')
+    with pytest.raises(TypeError):
+        scene.set_aperture_shape('Star')
+    with pytest.raises(TypeError):
+        scene.set_aperture_shape('Polygon', blades=2)
+    with pytest.raises(TypeError):
+        scene.set_aperture_shape('Image')
+
+def test_autofocus_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.set_autofocus(0.5)
+    with pytest.raises(TypeError):
+        scene.set_look_at(3)
+
+def test_calibration_exceptions():
+    scene = pyrays.Scene([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 0.0, 0.0], 0.0, 0.0, 0.0)
+    with pytest.raises(TypeError):
+        scene.set_intrinsics([[500.0, 0.0, 320.0], [0.0, 500.0, 240.0]])
+    with pytest.raises(TypeError):
+        scene.set_world_to_camera([[1.0, 'a', 0.0, 0.0]] * 4)
+    with pytest.raises(TypeError):
+        scene.set_distortion(k1='a')
@@ -0,0 +1,44 @@
+import pytest
+
+import pyrays
+
+
+def test_constant_texture():
+    x = pyrays.ConstantTexture([1, 0.5, 0.25])
+    assert 'Constant(colour: [1.0, 0.5, 0.25])' == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.ConstantTexture('a')
+
+
+def test_checker_texture():
+    x = pyrays.CheckerTexture([1, 1, 1], [0, 0, 0], 4)
+    assert 'Checker(even: [1.0, 1.0, 1.0], odd: [0.0, 0.0, 0.0], scale: 4.0)' == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.CheckerTexture([1, 1, 1], [0, 0, 0], 'a')
+
+
+def test_image_texture():
+    x = pyrays.ImageTexture('wood.jpg')
+    assert 'Image(path: "wood.jpg")' == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.ImageTexture(3)
+    with pytest.raises(TypeError):
+        pyrays.ImageTexture('wood.tga')
+
+
+def test_noise_texture():
+    x = pyrays.NoiseTexture('Marble', scale=2)
+    assert ('Noise(pattern: Marble, colours: [[1.0, 1.0, 1.0], [0.0, 0.0, 0.0]], scale: 2.0, '
+            'octaves: 7, seed: 0)') == x._to_ron()
+
+    with pytest.raises(TypeError):
+        pyrays.NoiseTexture('Granite')
+    with pytest.raises(TypeError):
+        pyrays.NoiseTexture('Wood', colours=[[1, 1, 1]])
+    with pytest.raises(TypeError):
+        pyrays.NoiseTexture('Wood', octaves=0)
+    with pytest.raises(TypeError):
+        pyrays.Diffuse(pyrays.NoiseTexture('Granite'))